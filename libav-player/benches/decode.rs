@@ -0,0 +1,56 @@
+//! Measures the cost of producing frames with and without the frame pool.
+//!
+//! Dropping each frame straight away returns it to the pool so the next frame
+//! reuses its allocation, while holding on to the frames forces the player to
+//! allocate a fresh frame every time, as it did before the pool was added.
+//!
+//! Run with `cargo bench -p libav-player --bench decode`.
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use libav_player::{DecodedFrame, InputSource, MediaPlayer, MediaPlayerBuilder};
+
+/// The number of frames decoded in each iteration.
+const NUM_FRAMES: usize = 60;
+
+fn create_player() -> MediaPlayer {
+    let source = InputSource::open_file("../media/test.mp4").unwrap();
+    let mut player = MediaPlayerBuilder::for_source(source).build().unwrap();
+    player.play().unwrap();
+    player
+}
+
+fn decode_frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+
+    group.bench_function("recycled_frames", |b| {
+        b.iter_batched(
+            create_player,
+            |mut player| {
+                for _ in 0..NUM_FRAMES {
+                    drop(player.process_next_frame().unwrap());
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.bench_function("allocated_frames", |b| {
+        b.iter_batched(
+            create_player,
+            |mut player| {
+                let mut frames: Vec<DecodedFrame> = Vec::with_capacity(NUM_FRAMES);
+                for _ in 0..NUM_FRAMES {
+                    frames.push(player.process_next_frame().unwrap());
+                }
+                frames
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode_frames);
+criterion_main!(benches);
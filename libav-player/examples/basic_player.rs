@@ -9,6 +9,9 @@ use libav_player::{
 
 fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
+    libav_player::init_logging();
+
+    dbg!(libav_player::ffmpeg_info());
 
     let source = InputSource::open_file("media/test.mp4")?;
 
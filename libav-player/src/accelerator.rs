@@ -1,5 +1,10 @@
+use std::collections::HashMap;
+use std::ptr;
+
 use rusty_ffmpeg::ffi as ffmpeg;
 
+use crate::error;
+
 #[cfg(target_os = "linux")]
 /// The default accelerator affinity for a Linux based distribution.
 ///
@@ -34,6 +39,9 @@ static DEFAULT_ACCELERATOR_AFFINITY: &[Accelerator] = &[
 /// for metal / M-series chips.
 static DEFAULT_ACCELERATOR_AFFINITY: &[Accelerator] = &[Accelerator::VideoToolbox];
 
+/// The maximum number of device ordinals probed when listing devices.
+const MAX_DEVICE_ORDINALS: usize = 16;
+
 mod hw_platform_flags {
     pub const WINDOWS: usize = 1 << 0;
     pub const LINUX: usize = 1 << 1;
@@ -92,6 +100,139 @@ pub enum Accelerator {
 }
 
 impl Accelerator {
+    /// Returns all accelerators FFmpeg was compiled with support for.
+    ///
+    /// This does not mean the accelerator is usable on this machine,
+    /// use [Accelerator::probe] to check it can actually be initialised.
+    pub fn available() -> Vec<Accelerator> {
+        let mut accelerators = Vec::new();
+        let mut device_type = ffmpeg::AV_HWDEVICE_TYPE_NONE;
+        loop {
+            device_type = unsafe { ffmpeg::av_hwdevice_iterate_types(device_type) };
+            if device_type == ffmpeg::AV_HWDEVICE_TYPE_NONE {
+                break;
+            }
+
+            if let Some(accelerator) = Self::try_from_av_hw_device_type(device_type) {
+                accelerators.push(accelerator);
+            }
+        }
+        accelerators
+    }
+
+    /// Attempts to create a hardware device for the accelerator using the
+    /// default device, returning `true` if it initialised successfully.
+    ///
+    /// This can take some time depending on the driver, so it should not be
+    /// called in a hot path.
+    pub fn probe(self) -> bool {
+        let mut hw_device = ptr::null_mut();
+        let result = unsafe {
+            ffmpeg::av_hwdevice_ctx_create(
+                &raw mut hw_device,
+                self.to_av_hw_device_type(),
+                ptr::null(),
+                ptr::null_mut(),
+                0,
+            )
+        };
+
+        if let Err(err) = error::convert_ff_result(result) {
+            tracing::debug!(accelerator = ?self, error = %err, "accelerator probe failed");
+            return false;
+        }
+
+        unsafe { ffmpeg::av_buffer_unref(&raw mut hw_device) };
+        true
+    }
+
+    /// Returns if FFmpeg can decode the codec using this accelerator.
+    ///
+    /// The `codec_name` is the FFmpeg codec name, i.e. `h264`, `hevc` or `av1`.
+    ///
+    /// This only checks FFmpeg has hardware decoding support for the codec,
+    /// not that the device supports a specific profile or can be initialised,
+    /// use [Accelerator::probe] for the latter.
+    pub fn supports_codec(self, codec_name: &str) -> bool {
+        let Ok(name) = std::ffi::CString::new(codec_name) else {
+            return false;
+        };
+
+        let descriptor =
+            unsafe { ffmpeg::avcodec_descriptor_get_by_name(name.as_ptr()) };
+        if descriptor.is_null() {
+            return false;
+        }
+
+        let codec_id = unsafe { (*descriptor).id };
+        match crate::codec::find_decoder_by_id(codec_id) {
+            Some(codec) => crate::codec::supports_accelerator(codec, self),
+            None => false,
+        }
+    }
+
+    /// Returns the devices which can be used with this accelerator.
+    ///
+    /// The [DeviceInfo::identifier] of each device can be passed directly
+    /// to [AcceleratorConfig::set_device].
+    ///
+    /// - `Vaapi` enumerates the DRM render nodes on Linux, i.e. `/dev/dri/renderD128`.
+    /// - `Cuda` and `Vulkan` enumerate device ordinals, each of which is probed
+    ///   to confirm it can be initialised.
+    ///
+    /// Other accelerators do not support enumeration and return an empty list,
+    /// in which case the default device will be used.
+    pub fn list_devices(self) -> crate::Result<Vec<DeviceInfo>> {
+        match self {
+            Accelerator::Vaapi if cfg!(target_os = "linux") => list_drm_render_nodes(),
+            Accelerator::Cuda | Accelerator::Vulkan => Ok(self.list_device_ordinals()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn list_device_ordinals(self) -> Vec<DeviceInfo> {
+        let mut devices = Vec::new();
+        for ordinal in 0..MAX_DEVICE_ORDINALS {
+            let identifier = ordinal.to_string();
+            let device = std::ffi::CString::new(identifier.as_str()).unwrap();
+
+            let mut hw_device = ptr::null_mut();
+            let result = unsafe {
+                ffmpeg::av_hwdevice_ctx_create(
+                    &raw mut hw_device,
+                    self.to_av_hw_device_type(),
+                    device.as_ptr(),
+                    ptr::null_mut(),
+                    0,
+                )
+            };
+            if result < 0 {
+                break;
+            }
+            unsafe { ffmpeg::av_buffer_unref(&raw mut hw_device) };
+
+            devices.push(DeviceInfo {
+                identifier,
+                name: None,
+            });
+        }
+        devices
+    }
+
+    pub(crate) fn to_av_hw_device_type(self) -> ffmpeg::AVHWDeviceType {
+        match self {
+            Accelerator::Vaapi => ffmpeg::AV_HWDEVICE_TYPE_VAAPI,
+            Accelerator::Vdpau => ffmpeg::AV_HWDEVICE_TYPE_VDPAU,
+            Accelerator::Cuda => ffmpeg::AV_HWDEVICE_TYPE_CUDA,
+            Accelerator::Qsv => ffmpeg::AV_HWDEVICE_TYPE_QSV,
+            Accelerator::Vulkan => ffmpeg::AV_HWDEVICE_TYPE_VULKAN,
+            Accelerator::Dxva2 => ffmpeg::AV_HWDEVICE_TYPE_DXVA2,
+            Accelerator::D3D11 => ffmpeg::AV_HWDEVICE_TYPE_D3D11VA,
+            Accelerator::D3D12 => ffmpeg::AV_HWDEVICE_TYPE_D3D12VA,
+            Accelerator::VideoToolbox => ffmpeg::AV_HWDEVICE_TYPE_VIDEOTOOLBOX,
+        }
+    }
+
     pub(crate) fn try_from_av_hw_device_type(
         device_type: ffmpeg::AVHWDeviceType,
     ) -> Option<Self> {
@@ -137,6 +278,16 @@ impl Accelerator {
         }
     }
 
+    /// Returns the name of the filter which scales frames on the device, if the
+    /// accelerator has one supporting aspect ratio preservation.
+    pub(crate) fn to_scale_filter_name(&self) -> Option<&'static str> {
+        match self {
+            Accelerator::Vaapi => Some("scale_vaapi"),
+            Accelerator::Cuda => Some("scale_cuda"),
+            _ => None,
+        }
+    }
+
     pub(crate) fn to_pixel_format_callback(
         &self,
     ) -> extern "C" fn(
@@ -157,11 +308,62 @@ impl Accelerator {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A device which can be targeted by an [Accelerator].
+pub struct DeviceInfo {
+    /// The identifier of the device, this can be passed to [AcceleratorConfig::set_device].
+    pub identifier: String,
+    /// A human-readable name of the device, if one could be determined.
+    pub name: Option<String>,
+}
+
+/// Enumerates the DRM render nodes available to VAAPI.
+///
+/// The name of each device is the kernel driver bound to it, i.e. `i915` or `amdgpu`.
+fn list_drm_render_nodes() -> crate::Result<Vec<DeviceInfo>> {
+    let entries = match std::fs::read_dir("/dev/dri") {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            let err =
+                error::FFmpegError::custom(format!("failed to read DRM devices: {err}"));
+            return Err(err.into());
+        },
+    };
+
+    let mut devices = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(node) = file_name.to_str() else {
+            continue;
+        };
+        if !node.starts_with("renderD") {
+            continue;
+        }
+
+        let driver_path = format!("/sys/class/drm/{node}/device/driver");
+        let name = std::fs::read_link(driver_path).ok().and_then(|driver| {
+            driver
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+
+        devices.push(DeviceInfo {
+            identifier: format!("/dev/dri/{node}"),
+            name,
+        });
+    }
+    devices.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+    Ok(devices)
+}
+
 #[derive(Debug, Clone)]
 /// The accelerator config controls the behaviour of hardware decoding used by FFmpeg
 /// when processing video streams.
 pub struct AcceleratorConfig {
     affinity: Box<[Accelerator]>,
+    codec_affinity: HashMap<String, Box<[Accelerator]>>,
     target_device: Option<std::ffi::CString>,
 }
 
@@ -169,6 +371,7 @@ impl Default for AcceleratorConfig {
     fn default() -> Self {
         let mut config = Self {
             affinity: Box::new([]),
+            codec_affinity: HashMap::new(),
             target_device: None,
         };
         config.set_accelerators(DEFAULT_ACCELERATOR_AFFINITY);
@@ -184,6 +387,31 @@ impl AcceleratorConfig {
         &self.affinity
     }
 
+    /// Returns the enabled accelerators for the given codec in the order
+    /// of the affinity for each accelerator.
+    ///
+    /// This is the override set via [AcceleratorConfig::set_accelerators_for_codec]
+    /// if there is one, otherwise the global [AcceleratorConfig::accelerators].
+    pub fn accelerators_for_codec(&self, codec: &str) -> &[Accelerator] {
+        self.codec_affinity
+            .get(&codec.to_ascii_lowercase())
+            .unwrap_or(&self.affinity)
+    }
+
+    /// Returns the first accelerator for the codec, in order of affinity, which
+    /// supports decoding the codec and can be initialised on this machine.
+    ///
+    /// This probes each candidate accelerator, so like [Accelerator::probe]
+    /// it should not be called in a hot path.
+    pub fn best_accelerator_for(&self, codec_name: &str) -> Option<Accelerator> {
+        self.accelerators_for_codec(codec_name)
+            .iter()
+            .copied()
+            .find(|accelerator| {
+                accelerator.supports_codec(codec_name) && accelerator.probe()
+            })
+    }
+
     pub(crate) fn device_target(&self) -> Option<&std::ffi::CStr> {
         self.target_device.as_deref()
     }
@@ -194,35 +422,22 @@ impl AcceleratorConfig {
     /// the system should have for each accelerator meaning it will pick
     /// hardware decoded in the order defined by this list.
     pub fn set_accelerators(&mut self, accelerators: &[Accelerator]) {
-        let target_platform = if cfg!(target_os = "windows") {
-            hw_platform_flags::WINDOWS
-        } else if cfg!(target_os = "linux") {
-            hw_platform_flags::LINUX
-        } else if cfg!(target_os = "macos") {
-            hw_platform_flags::MACOS
-        } else {
-            0
-        };
-
-        let mut warn_missing_hw_accel = target_platform != 0;
-        for accelerator in accelerators {
-            if accelerator.platform_flags() & target_platform != 0 {
-                warn_missing_hw_accel = false;
-                break;
-            }
-        }
-
-        if warn_missing_hw_accel {
-            tracing::warn!(
-                accelerators = ?accelerators,
-                "current target platform has no hardware accelerators to target, \
-                only software decoding is available",
-            );
-        }
+        self.affinity = normalise_affinity(accelerators);
+    }
 
-        let mut accelerators_owned = accelerators.to_vec();
-        accelerators_owned.dedup();
-        self.affinity = accelerators_owned.into_boxed_slice();
+    /// Set the enabled accelerators for a specific codec, overriding the
+    /// global accelerators set via [AcceleratorConfig::set_accelerators].
+    ///
+    /// The `codec` is the FFmpeg codec name, i.e. `h264`, `hevc` or `av1`.
+    /// Passing an empty list forces software decoding for the codec.
+    pub fn set_accelerators_for_codec(
+        &mut self,
+        codec: &str,
+        accelerators: &[Accelerator],
+    ) {
+        let affinity = normalise_affinity(accelerators);
+        self.codec_affinity
+            .insert(codec.to_ascii_lowercase(), affinity);
     }
 
     /// Set the target device of the accelerators.
@@ -235,6 +450,42 @@ impl AcceleratorConfig {
     }
 }
 
+/// Removes any duplicate accelerators, warning if none of them can be
+/// used on the current platform.
+///
+/// An empty list is used to force software decoding, so it is not warned about.
+fn normalise_affinity(accelerators: &[Accelerator]) -> Box<[Accelerator]> {
+    let target_platform = if cfg!(target_os = "windows") {
+        hw_platform_flags::WINDOWS
+    } else if cfg!(target_os = "linux") {
+        hw_platform_flags::LINUX
+    } else if cfg!(target_os = "macos") {
+        hw_platform_flags::MACOS
+    } else {
+        0
+    };
+
+    let mut warn_missing_hw_accel = target_platform != 0 && !accelerators.is_empty();
+    for accelerator in accelerators {
+        if accelerator.platform_flags() & target_platform != 0 {
+            warn_missing_hw_accel = false;
+            break;
+        }
+    }
+
+    if warn_missing_hw_accel {
+        tracing::warn!(
+            accelerators = ?accelerators,
+            "current target platform has no hardware accelerators to target, \
+            only software decoding is available",
+        );
+    }
+
+    let mut accelerators_owned = accelerators.to_vec();
+    accelerators_owned.dedup();
+    accelerators_owned.into_boxed_slice()
+}
+
 macro_rules! define_pix_fmt_selector {
     ($name:ident, $target:expr) => {
         extern "C" fn $name(
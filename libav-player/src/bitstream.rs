@@ -0,0 +1,132 @@
+use std::ffi::CString;
+use std::ptr;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::error;
+use crate::packet::{MediaPacket, Packet};
+
+/// Applies an FFmpeg bitstream filter to the packets of a single stream,
+/// created via [InputSource::bitstream_extractor](crate::InputSource::bitstream_extractor).
+///
+/// This rewrites the encoded data without decoding it, i.e. `h264_mp4toannexb`
+/// converts the length prefixed (AVCC) H.264 NAL units stored in MP4 into the
+/// Annex-B start code format most tools expect for elementary streams.
+pub struct BitstreamExtractor {
+    ctx: *mut ffmpeg::AVBSFContext,
+    stream_index: usize,
+}
+
+impl std::fmt::Debug for BitstreamExtractor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BitstreamExtractor")
+            .field("stream_index", &self.stream_index)
+            .finish()
+    }
+}
+
+impl BitstreamExtractor {
+    /// Create the bitstream filter with the given name for the stream.
+    pub(crate) fn new(
+        filter: &str,
+        stream: &ffmpeg::AVStream,
+    ) -> Result<Self, error::FFmpegError> {
+        let name = CString::new(filter).map_err(|_| {
+            error::FFmpegError::custom("bitstream filter name contains a null byte")
+        })?;
+        let bsf = unsafe { ffmpeg::av_bsf_get_by_name(name.as_ptr()) };
+        if bsf.is_null() {
+            return Err(error::FFmpegError::custom(format!(
+                "unknown bitstream filter {filter:?}"
+            )));
+        }
+
+        let mut extractor = Self {
+            ctx: ptr::null_mut(),
+            stream_index: stream.index as usize,
+        };
+
+        unsafe {
+            let result = ffmpeg::av_bsf_alloc(bsf, &raw mut extractor.ctx);
+            error::convert_ff_result(result)?;
+
+            let ctx = &mut *extractor.ctx;
+            let result = ffmpeg::avcodec_parameters_copy(ctx.par_in, stream.codecpar);
+            error::convert_ff_result(result)?;
+            ctx.time_base_in = stream.time_base;
+
+            let result = ffmpeg::av_bsf_init(extractor.ctx);
+            error::convert_ff_result(result).map_err(|err| {
+                err.with_context(format!("initialising bitstream filter {filter:?}"))
+            })?;
+        }
+
+        Ok(extractor)
+    }
+
+    #[inline]
+    /// Returns the index of the stream the packets are filtered for.
+    pub fn stream_index(&self) -> usize {
+        self.stream_index
+    }
+
+    /// Filter a packet, returning the packets produced by the filter.
+    ///
+    /// The packets are typically read via [crate::InputSource::read_raw_packet].
+    /// Filters may buffer packets internally, so a packet can produce no output
+    /// or several packets at once, see [BitstreamExtractor::flush]. Packets from
+    /// other streams are ignored and produce no output.
+    pub fn filter(&mut self, packet: &Packet) -> crate::Result<Vec<Packet>> {
+        if packet.stream_index() != self.stream_index {
+            return Ok(Vec::new());
+        }
+
+        // The filter takes ownership of the packet, so it is given a new reference.
+        let mut input = MediaPacket::new()?;
+        let result =
+            unsafe { ffmpeg::av_packet_ref(&mut *input, packet.as_av_packet()) };
+        error::convert_ff_result(result)?;
+
+        let result = unsafe { ffmpeg::av_bsf_send_packet(self.ctx, &mut *input) };
+        error::convert_ff_result(result)?;
+
+        self.receive_packets()
+    }
+
+    /// Signal that no more packets will be filtered, returning any packets
+    /// still buffered within the filter.
+    pub fn flush(&mut self) -> crate::Result<Vec<Packet>> {
+        let result = unsafe { ffmpeg::av_bsf_send_packet(self.ctx, ptr::null_mut()) };
+        error::convert_ff_result(result)?;
+
+        self.receive_packets()
+    }
+
+    fn receive_packets(&mut self) -> crate::Result<Vec<Packet>> {
+        let time_base = unsafe { (*self.ctx).time_base_out };
+
+        let mut packets = Vec::new();
+        loop {
+            let mut output = MediaPacket::new()?;
+            let result =
+                unsafe { ffmpeg::av_bsf_receive_packet(self.ctx, &mut *output) };
+            match error::convert_ff_result(result) {
+                Ok(_) => packets.push(Packet::new(output, time_base)),
+                Err(err) if err.needs_data() || err.is_eof() => return Ok(packets),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+// SAFETY: The filter context is uniquely owned and FFmpeg allows it to be
+//         used from any thread as long as it is not used concurrently.
+unsafe impl Send for BitstreamExtractor {}
+
+impl Drop for BitstreamExtractor {
+    fn drop(&mut self) {
+        if !self.ctx.is_null() {
+            unsafe { ffmpeg::av_bsf_free(&raw mut self.ctx) };
+        }
+    }
+}
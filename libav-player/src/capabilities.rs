@@ -0,0 +1,212 @@
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use std::ptr;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::MediaType;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The version of one of the linked FFmpeg libraries.
+pub struct LibraryVersion {
+    /// The major version, this changes whenever the ABI is broken.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The micro version.
+    pub micro: u32,
+}
+
+impl LibraryVersion {
+    fn from_av_version(version: u32) -> Self {
+        Self {
+            major: version >> 16,
+            minor: (version >> 8) & 0xFF,
+            micro: version & 0xFF,
+        }
+    }
+}
+
+impl Display for LibraryVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Information about the FFmpeg build the player is linked against.
+///
+/// This is useful to include in bug reports, in particular for issues
+/// with hardware decoding.
+pub struct FFmpegBuildInfo {
+    /// The FFmpeg release, i.e. `7.1` or a git revision for development builds.
+    pub version: String,
+    /// The version of the linked libavcodec.
+    pub libavcodec_version: LibraryVersion,
+    /// The version of the linked libavformat.
+    pub libavformat_version: LibraryVersion,
+    /// The version of the linked libavutil.
+    pub libavutil_version: LibraryVersion,
+    /// The flags FFmpeg was configured with, i.e. `--enable-gpl --enable-vaapi`.
+    pub configuration: String,
+    /// The names of the hardware device types FFmpeg was compiled with, i.e. `vaapi`.
+    ///
+    /// Unlike [Accelerator::available](crate::Accelerator::available) this includes
+    /// device types the player does not support.
+    pub hw_device_types: Vec<String>,
+}
+
+/// Returns information about the FFmpeg build the player is linked against.
+pub fn ffmpeg_info() -> FFmpegBuildInfo {
+    let mut hw_device_types = Vec::new();
+    let mut device_type = ffmpeg::AV_HWDEVICE_TYPE_NONE;
+    loop {
+        device_type = unsafe { ffmpeg::av_hwdevice_iterate_types(device_type) };
+        if device_type == ffmpeg::AV_HWDEVICE_TYPE_NONE {
+            break;
+        }
+
+        let name = unsafe { ffmpeg::av_hwdevice_get_type_name(device_type) };
+        if !name.is_null() {
+            hw_device_types.push(unsafe { c_str_to_string(name) });
+        }
+    }
+
+    unsafe {
+        FFmpegBuildInfo {
+            version: c_str_to_string(ffmpeg::av_version_info()),
+            libavcodec_version: LibraryVersion::from_av_version(
+                ffmpeg::avcodec_version(),
+            ),
+            libavformat_version: LibraryVersion::from_av_version(
+                ffmpeg::avformat_version(),
+            ),
+            libavutil_version: LibraryVersion::from_av_version(ffmpeg::avutil_version()),
+            configuration: c_str_to_string(ffmpeg::avcodec_configuration()),
+            hw_device_types,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A decoder available in the linked FFmpeg build.
+pub struct CodecDescriptor {
+    /// The name of the decoder, i.e. `h264` or `libdav1d`.
+    pub name: String,
+    /// A human-readable name of the decoder.
+    pub long_name: Option<String>,
+    /// The type of media the decoder produces.
+    pub media_type: MediaType,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An input format (demuxer) available in the linked FFmpeg build.
+pub struct FormatDescriptor {
+    /// The name of the format, this may list multiple names separated by a comma,
+    /// i.e. `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub name: String,
+    /// A human-readable name of the format.
+    pub long_name: Option<String>,
+    /// The file extensions commonly used by the format, i.e. `mkv`.
+    ///
+    /// This is empty for formats which are detected by probing the data instead.
+    pub extensions: Vec<String>,
+    /// The MIME types of the format, i.e. `video/webm`.
+    pub mime_types: Vec<String>,
+}
+
+/// Returns the decoders the player can use from the linked FFmpeg build.
+///
+/// Only video, audio and subtitle decoders are included, experimental decoders
+/// are excluded as FFmpeg refuses to open them.
+pub fn supported_decoders() -> Vec<CodecDescriptor> {
+    let mut decoders = Vec::new();
+    let mut opaque = ptr::null_mut();
+    loop {
+        let codec = unsafe { ffmpeg::av_codec_iterate(&raw mut opaque) };
+        let Some(codec) = (unsafe { codec.as_ref() }) else {
+            break;
+        };
+
+        if unsafe { ffmpeg::av_codec_is_decoder(codec) } == 0 {
+            continue;
+        }
+        if codec.capabilities & ffmpeg::AV_CODEC_CAP_EXPERIMENTAL as i32 != 0 {
+            continue;
+        }
+
+        let media_type = MediaType::from(codec.type_);
+        if !matches!(
+            media_type,
+            MediaType::Video | MediaType::Audio | MediaType::Subtitle
+        ) {
+            continue;
+        }
+
+        decoders.push(CodecDescriptor {
+            name: unsafe { c_str_to_string(codec.name) },
+            long_name: unsafe { c_str_to_option(codec.long_name) },
+            media_type,
+        });
+    }
+    decoders
+}
+
+/// Returns the input formats (demuxers) available in the linked FFmpeg build.
+pub fn supported_input_formats() -> Vec<FormatDescriptor> {
+    let mut formats = Vec::new();
+    let mut opaque = ptr::null_mut();
+    loop {
+        let format = unsafe { ffmpeg::av_demuxer_iterate(&raw mut opaque) };
+        let Some(format) = (unsafe { format.as_ref() }) else {
+            break;
+        };
+
+        formats.push(FormatDescriptor {
+            name: unsafe { c_str_to_string(format.name) },
+            long_name: unsafe { c_str_to_option(format.long_name) },
+            extensions: unsafe { c_str_to_list(format.extensions) },
+            mime_types: unsafe { c_str_to_list(format.mime_type) },
+        });
+    }
+    formats
+}
+
+/// Copies a C string returned by FFmpeg into a new string.
+///
+/// # Safety
+/// The pointer must be a valid null terminated string or null.
+unsafe fn c_str_to_string(ptr: *const std::ffi::c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Like [c_str_to_string] but returns `None` for null or empty strings.
+///
+/// # Safety
+/// The pointer must be a valid null terminated string or null.
+unsafe fn c_str_to_option(ptr: *const std::ffi::c_char) -> Option<String> {
+    let value = unsafe { c_str_to_string(ptr) };
+    if value.is_empty() { None } else { Some(value) }
+}
+
+/// Splits a comma separated C string returned by FFmpeg into its entries.
+///
+/// # Safety
+/// The pointer must be a valid null terminated string or null.
+unsafe fn c_str_to_list(ptr: *const std::ffi::c_char) -> Vec<String> {
+    unsafe { c_str_to_string(ptr) }
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,218 @@
+use rusty_ffmpeg::ffi as ffmpeg;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// The speaker position of a single audio channel.
+pub enum ChannelPosition {
+    /// Front left.
+    FrontLeft,
+    /// Front right.
+    FrontRight,
+    /// Front center.
+    FrontCenter,
+    /// Low frequency effects, i.e. the subwoofer.
+    LowFrequency,
+    /// Back left.
+    BackLeft,
+    /// Back right.
+    BackRight,
+    /// Front left of center.
+    FrontLeftOfCenter,
+    /// Front right of center.
+    FrontRightOfCenter,
+    /// Back center.
+    BackCenter,
+    /// Side left.
+    SideLeft,
+    /// Side right.
+    SideRight,
+    /// Top center.
+    TopCenter,
+    /// Top front left.
+    TopFrontLeft,
+    /// Top front center.
+    TopFrontCenter,
+    /// Top front right.
+    TopFrontRight,
+    /// Top back left.
+    TopBackLeft,
+    /// Top back center.
+    TopBackCenter,
+    /// Top back right.
+    TopBackRight,
+    /// A position not covered by the other variants.
+    Other,
+}
+
+impl ChannelPosition {
+    fn from_av_channel(channel: ffmpeg::AVChannel) -> Self {
+        match channel {
+            ffmpeg::AV_CHAN_FRONT_LEFT => Self::FrontLeft,
+            ffmpeg::AV_CHAN_FRONT_RIGHT => Self::FrontRight,
+            ffmpeg::AV_CHAN_FRONT_CENTER => Self::FrontCenter,
+            ffmpeg::AV_CHAN_LOW_FREQUENCY => Self::LowFrequency,
+            ffmpeg::AV_CHAN_BACK_LEFT => Self::BackLeft,
+            ffmpeg::AV_CHAN_BACK_RIGHT => Self::BackRight,
+            ffmpeg::AV_CHAN_FRONT_LEFT_OF_CENTER => Self::FrontLeftOfCenter,
+            ffmpeg::AV_CHAN_FRONT_RIGHT_OF_CENTER => Self::FrontRightOfCenter,
+            ffmpeg::AV_CHAN_BACK_CENTER => Self::BackCenter,
+            ffmpeg::AV_CHAN_SIDE_LEFT => Self::SideLeft,
+            ffmpeg::AV_CHAN_SIDE_RIGHT => Self::SideRight,
+            ffmpeg::AV_CHAN_TOP_CENTER => Self::TopCenter,
+            ffmpeg::AV_CHAN_TOP_FRONT_LEFT => Self::TopFrontLeft,
+            ffmpeg::AV_CHAN_TOP_FRONT_CENTER => Self::TopFrontCenter,
+            ffmpeg::AV_CHAN_TOP_FRONT_RIGHT => Self::TopFrontRight,
+            ffmpeg::AV_CHAN_TOP_BACK_LEFT => Self::TopBackLeft,
+            ffmpeg::AV_CHAN_TOP_BACK_CENTER => Self::TopBackCenter,
+            ffmpeg::AV_CHAN_TOP_BACK_RIGHT => Self::TopBackRight,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The layout of the audio channels, describing which speaker each channel maps to.
+pub enum ChannelLayout {
+    /// A single front center channel.
+    Mono,
+    /// Front left and front right.
+    Stereo,
+    /// Stereo with a low frequency channel.
+    Surround2_1,
+    /// Stereo with a front center channel.
+    Surround3_0,
+    /// Front left, front right, back left and back right.
+    Quad,
+    /// Front left, front right, front center, side left and side right.
+    Surround5_0,
+    /// [ChannelLayout::Surround5_0] with a low frequency channel.
+    Surround5_1,
+    /// Like [ChannelLayout::Surround5_1] but using the back rather than side channels.
+    Surround5_1Back,
+    /// [ChannelLayout::Surround5_1] with back left and back right channels.
+    Surround7_1,
+    /// A layout not covered by the other variants, described by a bitmask
+    /// of FFmpeg's native channel order.
+    Custom {
+        /// The FFmpeg channel mask, where bit `n` is set if the `AVChannel` `n` is present.
+        mask: u64,
+    },
+    /// The channel positions are unknown, only the number of channels is available.
+    Unspecified {
+        /// The number of channels.
+        channels: u32,
+    },
+}
+
+impl ChannelLayout {
+    /// The layouts which can be matched from an FFmpeg channel mask.
+    const NAMED_LAYOUTS: &[ChannelLayout] = &[
+        Self::Mono,
+        Self::Stereo,
+        Self::Surround2_1,
+        Self::Surround3_0,
+        Self::Quad,
+        Self::Surround5_0,
+        Self::Surround5_1,
+        Self::Surround5_1Back,
+        Self::Surround7_1,
+    ];
+
+    pub(crate) fn from_av_channel_layout(layout: &ffmpeg::AVChannelLayout) -> Self {
+        if layout.order != ffmpeg::AV_CHANNEL_ORDER_NATIVE {
+            return Self::Unspecified {
+                channels: layout.nb_channels.max(0) as u32,
+            };
+        }
+
+        let mask = unsafe { layout.u.mask };
+        Self::NAMED_LAYOUTS
+            .iter()
+            .copied()
+            .find(|named| named.mask() == Some(mask))
+            .unwrap_or(Self::Custom { mask })
+    }
+
+    /// Returns the FFmpeg channel mask for the layout.
+    ///
+    /// Returns `None` if the layout is [ChannelLayout::Unspecified].
+    pub(crate) fn mask(&self) -> Option<u64> {
+        use ffmpeg::{
+            AV_CHAN_BACK_LEFT as BL,
+            AV_CHAN_BACK_RIGHT as BR,
+            AV_CHAN_FRONT_CENTER as FC,
+            AV_CHAN_FRONT_LEFT as FL,
+            AV_CHAN_FRONT_RIGHT as FR,
+            AV_CHAN_LOW_FREQUENCY as LFE,
+            AV_CHAN_SIDE_LEFT as SL,
+            AV_CHAN_SIDE_RIGHT as SR,
+        };
+
+        let channels: &[ffmpeg::AVChannel] = match self {
+            Self::Mono => &[FC],
+            Self::Stereo => &[FL, FR],
+            Self::Surround2_1 => &[FL, FR, LFE],
+            Self::Surround3_0 => &[FL, FR, FC],
+            Self::Quad => &[FL, FR, BL, BR],
+            Self::Surround5_0 => &[FL, FR, FC, SL, SR],
+            Self::Surround5_1 => &[FL, FR, FC, LFE, SL, SR],
+            Self::Surround5_1Back => &[FL, FR, FC, LFE, BL, BR],
+            Self::Surround7_1 => &[FL, FR, FC, LFE, BL, BR, SL, SR],
+            Self::Custom { mask } => return Some(*mask),
+            Self::Unspecified { .. } => return None,
+        };
+
+        Some(
+            channels
+                .iter()
+                .fold(0, |mask, channel| mask | (1u64 << *channel as u64)),
+        )
+    }
+
+    /// Creates a new [ffmpeg::AVChannelLayout] describing the layout.
+    ///
+    /// The caller is responsible for uninitialising the returned layout.
+    pub(crate) fn to_av_channel_layout(&self) -> ffmpeg::AVChannelLayout {
+        let mut layout = unsafe { std::mem::zeroed::<ffmpeg::AVChannelLayout>() };
+        match self.mask() {
+            Some(mask) => unsafe {
+                ffmpeg::av_channel_layout_from_mask(&raw mut layout, mask);
+            },
+            None => unsafe {
+                ffmpeg::av_channel_layout_default(
+                    &raw mut layout,
+                    self.num_channels() as i32,
+                );
+            },
+        }
+        layout
+    }
+
+    /// Returns the number of channels in the layout.
+    pub fn num_channels(&self) -> usize {
+        match self {
+            Self::Unspecified { channels } => *channels as usize,
+            _ => self.mask().unwrap_or(0).count_ones() as usize,
+        }
+    }
+
+    /// Returns the speaker position of each channel in the order they appear
+    /// within a frame.
+    ///
+    /// Returns an empty vec if the layout is [ChannelLayout::Unspecified].
+    pub fn positions(&self) -> Vec<ChannelPosition> {
+        let Some(mask) = self.mask() else {
+            return Vec::new();
+        };
+
+        (0..u64::BITS)
+            .filter(|bit| mask & (1u64 << bit) != 0)
+            .map(|bit| ChannelPosition::from_av_channel(bit as ffmpeg::AVChannel))
+            .collect()
+    }
+
+    /// Returns if the layout contains a channel at the given position.
+    pub fn has_position(&self, position: ChannelPosition) -> bool {
+        self.positions().contains(&position)
+    }
+}
@@ -1,4 +1,181 @@
-use super::BaseDecoder;
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use super::{BaseDecoder, Decoder};
+use crate::resample::AudioResampler;
+use crate::stream::StreamInfo;
+use crate::{AudioFormat, ChannelLayout, SampleFormat, error};
 
 /// The decoder for processing audio packets and producing audio frames.
-pub type AudioDecoder = BaseDecoder;
\ No newline at end of file
+pub(crate) struct AudioDecoder {
+    inner: BaseDecoder,
+    resampler: Option<AudioResampler>,
+    frame: *mut ffmpeg::AVFrame,
+}
+
+impl AudioDecoder {
+    /// Open the audio decoder.
+    ///
+    /// If a `target_format` is provided, all decoded frames will be resampled
+    /// into that format before being returned.
+    ///
+    /// If a `downmix` layout is provided, all decoded frames will be remixed into
+    /// that layout, this is a no-op if the stream already uses the layout and no
+    /// `target_format` is set.
+    ///
+    /// The decoder is automatically opened and ready once returned.
+    pub(crate) fn open(
+        codec: &'static ffmpeg::AVCodec,
+        stream_info: StreamInfo,
+        codec_params: Option<&ffmpeg::AVCodecParameters>,
+        target_format: Option<AudioFormat>,
+        downmix: Option<ChannelLayout>,
+    ) -> Result<Self, error::FFmpegError> {
+        let mut decoder = Self::create(codec, stream_info)?;
+        if let Some(codec_params) = codec_params {
+            decoder.copy_codec_params(codec_params)?;
+        }
+        decoder.open()?;
+
+        decoder.resampler = match (target_format, downmix) {
+            (Some(target), layout) => Some(AudioResampler::new(target, layout)),
+            (None, Some(layout)) if decoder.channel_layout() != layout => {
+                // Keep the sample format and rate of the decoder, only remixing the channels.
+                let ctx = decoder.as_ctx();
+                let sample_format = SampleFormat::try_from_av_sample_fmt(ctx.sample_fmt)
+                    .unwrap_or(SampleFormat::FLTP);
+                let target = AudioFormat {
+                    sample_format,
+                    sample_rate: ctx.sample_rate as u32,
+                    channels: layout.num_channels() as u32,
+                };
+                Some(AudioResampler::new(target, Some(layout)))
+            },
+            (None, _) => None,
+        };
+
+        Ok(decoder)
+    }
+
+    fn create(
+        codec: &'static ffmpeg::AVCodec,
+        stream_info: StreamInfo,
+    ) -> Result<Self, error::FFmpegError> {
+        let inner = BaseDecoder::create(codec, stream_info)?;
+
+        let frame = unsafe { ffmpeg::av_frame_alloc() };
+        if frame.is_null() {
+            return Err(error::FFmpegError::custom("unable to allocate frame"));
+        }
+
+        Ok(Self {
+            inner,
+            resampler: None,
+            frame,
+        })
+    }
+
+    fn channel_layout(&self) -> ChannelLayout {
+        ChannelLayout::from_av_channel_layout(&self.as_ctx().ch_layout)
+    }
+
+    /// Returns the format of the audio frames produced by the decoder.
+    ///
+    /// Returns `None` if the decoder produces a sample format we do not support.
+    pub(crate) fn output_format(&self) -> Option<AudioFormat> {
+        if let Some(resampler) = self.resampler.as_ref() {
+            return Some(resampler.target());
+        }
+
+        let ctx = self.as_ctx();
+        Some(AudioFormat {
+            sample_format: SampleFormat::try_from_av_sample_fmt(ctx.sample_fmt)?,
+            sample_rate: ctx.sample_rate as u32,
+            channels: ctx.ch_layout.nb_channels as u32,
+        })
+    }
+}
+
+impl Decoder for AudioDecoder {
+    type Frame = ffmpeg::AVFrame;
+
+    fn as_mut_ctx(&mut self) -> &mut ffmpeg::AVCodecContext {
+        self.inner.as_mut_ctx()
+    }
+
+    fn as_ctx(&self) -> &ffmpeg::AVCodecContext {
+        self.inner.as_ctx()
+    }
+
+    fn open(&mut self) -> Result<(), error::FFmpegError> {
+        self.inner.open()
+    }
+
+    fn flush_buffers(&mut self) {
+        unsafe { ffmpeg::avcodec_flush_buffers(self.as_mut_ctx()) };
+
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+        }
+    }
+
+    fn decode(&mut self, frame: &mut Self::Frame) -> Result<(), error::FFmpegError> {
+        if self.resampler.is_none() {
+            let result =
+                unsafe { ffmpeg::avcodec_receive_frame(self.as_mut_ctx(), frame) };
+            error::convert_ff_result(result)?;
+            self.apply_context_to_frame(frame);
+            return Ok(());
+        }
+
+        let result =
+            unsafe { ffmpeg::avcodec_receive_frame(self.as_mut_ctx(), self.frame) };
+        match error::convert_ff_result(result) {
+            Ok(_) => {},
+            // Once the decoder is drained, the samples the resampler holds back
+            // for filtering are drained too so the tail of the audio is not lost.
+            Err(err) if err.is_eof() => {
+                let Some(resampler) = self.resampler.as_mut() else {
+                    return Err(err);
+                };
+                resampler.flush(frame)?;
+                self.apply_context_to_frame(frame);
+                return Ok(());
+            },
+            Err(err) => return Err(err),
+        }
+
+        let decoded = unsafe { &mut *self.frame };
+        self.apply_context_to_frame(decoded);
+
+        if let Some(resampler) = self.resampler.as_mut() {
+            let result = resampler.convert(decoded, frame);
+            unsafe { ffmpeg::av_frame_unref(decoded) };
+            result?;
+        }
+        self.apply_context_to_frame(frame);
+
+        Ok(())
+    }
+
+    fn apply_context_to_frame(&self, frame: &mut Self::Frame) {
+        self.inner.apply_context_to_frame(frame);
+
+        // Some decoders leave the sample rate unset on the frame itself,
+        // so fall back to what the codec negotiated.
+        let ctx = self.as_ctx();
+        if frame.sample_rate <= 0 {
+            frame.sample_rate = ctx.sample_rate;
+        }
+    }
+}
+
+impl Drop for AudioDecoder {
+    fn drop(&mut self) {
+        if !self.frame.is_null() {
+            unsafe {
+                ffmpeg::av_frame_unref(self.frame);
+                ffmpeg::av_frame_free(&raw mut self.frame);
+            };
+        }
+    }
+}
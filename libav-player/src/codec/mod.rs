@@ -8,9 +8,9 @@ use rusty_ffmpeg::ffi as ffmpeg;
 
 pub(crate) use self::audio::AudioDecoder;
 pub(crate) use self::subtitle::SubtitleDecoder;
-pub(crate) use self::video::VideoDecoder;
-use crate::error;
+pub(crate) use self::video::{VideoDecoder, supports_accelerator};
 use crate::stream::StreamInfo;
+use crate::{ThreadCount, error};
 
 /// Find a ffmpeg codec by name.
 ///
@@ -73,10 +73,25 @@ pub(crate) trait Decoder: Sized {
 
     /// Signal to the decoder that all packets have been read,
     /// and it should flush any remaining data.
+    ///
+    /// This sends a null packet to put the decoder into draining mode, any
+    /// frames buffered within the decoder are then returned by [Self::decode]
+    /// before it returns `AVERROR_EOF`.
     fn flush(&mut self) -> Result<(), error::FFmpegError> {
+        let result =
+            unsafe { ffmpeg::avcodec_send_packet(self.as_mut_ctx(), ptr::null_mut()) };
+        error::convert_ff_result(result)?;
         Ok(())
     }
 
+    /// Discard any packets and frames buffered within the decoder.
+    ///
+    /// This must be called after seeking to avoid frames from the
+    /// previous position being returned.
+    fn flush_buffers(&mut self) {
+        unsafe { ffmpeg::avcodec_flush_buffers(self.as_mut_ctx()) };
+    }
+
     /// Push packet data into the decoder.
     fn write_packet(
         &mut self,
@@ -145,6 +160,16 @@ impl BaseDecoder {
 
         Ok(decoder)
     }
+
+    /// Set the number of threads used for decoding, enabling both
+    /// frame and slice threading.
+    ///
+    /// This must be done before opening.
+    pub(crate) fn set_thread_count(&mut self, threads: ThreadCount) {
+        let ctx = self.as_mut_ctx();
+        ctx.thread_count = threads.to_av_thread_count();
+        ctx.thread_type = (ffmpeg::FF_THREAD_FRAME | ffmpeg::FF_THREAD_SLICE) as i32;
+    }
 }
 
 impl Decoder for BaseDecoder {
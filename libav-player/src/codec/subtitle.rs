@@ -4,6 +4,7 @@ use rusty_ffmpeg::ffi as ffmpeg;
 
 use crate::codec::{BaseDecoder, Decoder};
 use crate::error;
+use crate::packet::MediaPacket;
 use crate::stream::StreamInfo;
 
 /// A decoder for processing subtitle streams.
@@ -25,6 +26,32 @@ impl SubtitleDecoder {
             ready_subtitle: None,
         })
     }
+
+    /// Returns the ASS header, containing the `[Script Info]` and `[V4+ Styles]`
+    /// sections, which the decoded events must be rendered with.
+    ///
+    /// Returns `None` for bitmap subtitles or if the decoder provided no header.
+    pub(crate) fn header(&self) -> Option<String> {
+        let ctx = self.as_ctx();
+
+        let descriptor = unsafe { ffmpeg::avcodec_descriptor_get(ctx.codec_id) };
+        let is_bitmap = unsafe { descriptor.as_ref() }.is_some_and(|descriptor| {
+            descriptor.props & ffmpeg::AV_CODEC_PROP_BITMAP_SUB as i32 != 0
+        });
+        if is_bitmap || ctx.subtitle_header.is_null() || ctx.subtitle_header_size <= 0 {
+            return None;
+        }
+
+        let header = unsafe {
+            std::slice::from_raw_parts(
+                ctx.subtitle_header,
+                ctx.subtitle_header_size as usize,
+            )
+        };
+        // The header may or may not include a null terminator.
+        let header = header.strip_suffix(&[0]).unwrap_or(header);
+        Some(String::from_utf8_lossy(header).into_owned())
+    }
 }
 
 impl Decoder for SubtitleDecoder {
@@ -42,6 +69,22 @@ impl Decoder for SubtitleDecoder {
         self.inner.open()
     }
 
+    fn flush(&mut self) -> Result<(), error::FFmpegError> {
+        // FFmpeg does not support the send/receive API for subtitle decoders,
+        // instead decoders with a delay return their last subtitle when given
+        // an empty packet.
+        let mut packet = MediaPacket::new()?;
+        self.write_packet(&mut packet)
+    }
+
+    fn flush_buffers(&mut self) {
+        unsafe { ffmpeg::avcodec_flush_buffers(self.as_mut_ctx()) };
+
+        if let Some(mut subtitle) = self.ready_subtitle.take() {
+            unsafe { ffmpeg::avsubtitle_free(&raw mut subtitle) };
+        }
+    }
+
     fn write_packet(&mut self, packet: &mut ffmpeg::AVPacket) -> Result<(), error::FFmpegError> {
         let mut subtitle = ffmpeg::AVSubtitle {
             format: 0,
@@ -3,9 +3,18 @@ use std::ptr;
 use rusty_ffmpeg::ffi as ffmpeg;
 
 use super::{BaseDecoder, Decoder, find_decoder_by_name};
-use crate::filter::VideoFilterPipeline;
+use crate::filter::{VideoFilterOptions, VideoFilterPipeline};
 use crate::stream::StreamInfo;
-use crate::{Accelerator, AcceleratorConfig, OutputPixelFormat, error};
+use crate::{
+    Accelerator,
+    AcceleratorConfig,
+    OutputPixelFormat,
+    Resolution,
+    ScalingMode,
+    ThreadCount,
+    VideoDecoderInfo,
+    error,
+};
 
 /// The accelerated codec is a wrapper around [ffmpeg::AVCodec]
 /// and some hardware device if available.
@@ -15,10 +24,19 @@ use crate::{Accelerator, AcceleratorConfig, OutputPixelFormat, error};
 pub(crate) struct VideoDecoder {
     base_decoder: BaseDecoder,
     accelerator: Option<Accelerator>,
+    /// The accelerators attempted before the decoder was created which
+    /// could not be used.
+    failed_accelerators: Vec<Accelerator>,
     output_pixel_formats: Vec<OutputPixelFormat>,
+    filter_options: VideoFilterOptions,
     filter: Option<VideoFilterPipeline>,
     frame: *mut ffmpeg::AVFrame,
     has_flushed: bool,
+    /// The number of packets written since the decoder was opened or last flushed.
+    ///
+    /// Each packet is tagged with its number so frames can report the packet they
+    /// were decoded from, see [crate::VideoFrame::coded_picture_number].
+    packets_written: u64,
 }
 
 impl VideoDecoder {
@@ -26,8 +44,11 @@ impl VideoDecoder {
     ///
     /// This will automatically attempt to use hardware acceleration in the order defined by the
     /// [AcceleratorConfig] and use the first accelerator that supports the codec and target pixel
-    /// format output.
-    /// If no hardware accelerator is available this will fall back to software.
+    /// format output, preferring any overrides set for the codec.
+    /// If no hardware accelerator is available this will fall back to software,
+    /// using the provided number of `threads`.
+    ///
+    /// The `filter_options` are applied to every frame by the filter pipeline.
     ///
     /// The decoder is automatically opened and ready once returned.
     pub(crate) fn open(
@@ -35,14 +56,22 @@ impl VideoDecoder {
         stream_info: StreamInfo,
         codec_params: Option<&ffmpeg::AVCodecParameters>,
         output_pixel_format: Vec<OutputPixelFormat>,
+        filter_options: VideoFilterOptions,
         accelerator_config: &AcceleratorConfig,
+        threads: ThreadCount,
     ) -> Result<Self, error::FFmpegError> {
         assert!(
             !output_pixel_format.is_empty(),
             "at least one pixel format must be provided"
         );
 
-        for accelerator in accelerator_config.accelerators() {
+        let codec_name =
+            unsafe { std::ffi::CStr::from_ptr(ffmpeg::avcodec_get_name(codec.id)) };
+        let accelerators =
+            accelerator_config.accelerators_for_codec(&codec_name.to_string_lossy());
+
+        let mut failed_accelerators = Vec::new();
+        for accelerator in accelerators {
             tracing::debug!(accelerator = ?accelerator, "attempting to use accelerator");
 
             let result = create_accelerated_decoder(
@@ -54,7 +83,10 @@ impl VideoDecoder {
 
             let mut decoder = match result {
                 Ok(Some(decoder)) => decoder,
-                Ok(None) => continue,
+                Ok(None) => {
+                    failed_accelerators.push(*accelerator);
+                    continue;
+                },
                 Err(err) => return Err(err),
             };
 
@@ -64,16 +96,27 @@ impl VideoDecoder {
                 decoder.copy_codec_params(codec_params)?;
             }
             decoder.open()?;
+            decoder.failed_accelerators = failed_accelerators;
             decoder.output_pixel_formats = output_pixel_format;
+            decoder.filter_options = filter_options;
             return Ok(decoder);
         }
 
+        tracing::debug!(
+            threads = ?threads,
+            failed_accelerators = ?failed_accelerators,
+            "falling back to software decoding",
+        );
+
         let mut decoder = Self::create(codec, stream_info)?;
         if let Some(codec_params) = codec_params {
             decoder.copy_codec_params(codec_params)?;
         }
+        decoder.base_decoder.set_thread_count(threads);
         decoder.open()?;
+        decoder.failed_accelerators = failed_accelerators;
         decoder.output_pixel_formats = output_pixel_format;
+        decoder.filter_options = filter_options;
 
         Ok(decoder)
     }
@@ -82,7 +125,9 @@ impl VideoDecoder {
         codec: &'static ffmpeg::AVCodec,
         stream_info: StreamInfo,
     ) -> Result<Self, error::FFmpegError> {
-        let base_decoder = BaseDecoder::create(codec, stream_info)?;
+        let mut base_decoder = BaseDecoder::create(codec, stream_info)?;
+        // Carry the packet number through to the frames decoded from the packet.
+        base_decoder.as_mut_ctx().flags |= ffmpeg::AV_CODEC_FLAG_COPY_OPAQUE as i32;
 
         let frame = unsafe { ffmpeg::av_frame_alloc() };
         if frame.is_null() {
@@ -92,10 +137,13 @@ impl VideoDecoder {
         Ok(Self {
             base_decoder,
             accelerator: None,
+            failed_accelerators: Vec::new(),
+            filter_options: VideoFilterOptions::default(),
             filter: None,
             output_pixel_formats: Vec::new(),
             frame,
             has_flushed: false,
+            packets_written: 0,
         })
     }
 
@@ -107,54 +155,136 @@ impl VideoDecoder {
         self.accelerator
     }
 
-    pub(crate) fn hw_frames_ctx(&self) -> *mut ffmpeg::AVBufferRef {
-        let ctx = self.base_decoder.as_ctx();
-        ctx.hw_frames_ctx
+    /// Returns information about the accelerator and pixel format the decoder selected.
+    pub(crate) fn info(&self) -> VideoDecoderInfo {
+        let hw_pix_fmt = self.accelerator.and_then(|_| {
+            OutputPixelFormat::try_from_av_pix_fmt(self.as_ctx().sw_pix_fmt)
+        });
+
+        VideoDecoderInfo {
+            accelerator: self.accelerator,
+            hw_pix_fmt,
+            is_hardware: self.accelerator.is_some(),
+            failed_accelerators: self.failed_accelerators.clone(),
+        }
     }
 
-    fn pix_fmt(&self) -> ffmpeg::AVPixelFormat {
-        let accelerator = match self.accelerator() {
-            None => {
-                let ctx = self.as_ctx();
-                return ctx.sw_pix_fmt;
+    /// Returns the resolution of the frames produced by the decoder once any
+    /// cropping and scaling has been applied.
+    pub(crate) fn output_resolution(&self) -> Resolution {
+        let ctx = self.as_ctx();
+        let source = match self.filter_options.crop {
+            Some(crop) => crop.resolution(),
+            None => Resolution {
+                width: ctx.width.max(0) as usize,
+                height: ctx.height.max(0) as usize,
             },
-            Some(accelerator) => accelerator,
         };
 
-        match accelerator {
-            Accelerator::Vaapi => ffmpeg::AV_PIX_FMT_VAAPI,
-            Accelerator::Vdpau => ffmpeg::AV_PIX_FMT_VDPAU,
-            Accelerator::Cuda => ffmpeg::AV_PIX_FMT_CUDA,
-            Accelerator::Qsv => ffmpeg::AV_PIX_FMT_QSV,
-            Accelerator::Vulkan => ffmpeg::AV_PIX_FMT_VULKAN,
-            Accelerator::Dxva2 => ffmpeg::AV_PIX_FMT_DXVA2_VLD,
-            Accelerator::D3D11 => ffmpeg::AV_PIX_FMT_D3D11,
-            Accelerator::D3D12 => ffmpeg::AV_PIX_FMT_D3D12,
-            Accelerator::VideoToolbox => ffmpeg::AV_PIX_FMT_VIDEOTOOLBOX,
+        match self.filter_options.target_resolution {
+            Some((target, mode)) => mode.output_resolution(source, target),
+            None => source,
         }
     }
 
-    pub(crate) fn build_filter_args(&self) -> String {
-        if self.accelerator().is_some() {
-            format!(
-                "hwdownload,format={}",
-                crate::join_pixel_formats(&self.output_pixel_formats)
-            )
+    /// Set if the decoder should skip decoding non-reference frames,
+    /// which is used to catch up when playback falls behind.
+    pub(crate) fn set_skip_non_reference_frames(&mut self, skip: bool) {
+        let ctx = self.as_mut_ctx();
+        ctx.skip_frame = if skip {
+            ffmpeg::AVDISCARD_NONREF
+        } else {
+            ffmpeg::AVDISCARD_DEFAULT
+        };
+    }
+
+    pub(crate) fn hw_device_ctx(&self) -> *mut ffmpeg::AVBufferRef {
+        let ctx = self.base_decoder.as_ctx();
+        ctx.hw_device_ctx
+    }
+
+    /// Returns the output pixel formats the filter should convert the frame into.
+    ///
+    /// If the frame is already in one of the target formats (or will be once
+    /// downloaded from the device) only that format is returned, avoiding any
+    /// conversion at all. Otherwise, FFmpeg negotiates the cheapest conversion
+    /// from all the target formats.
+    fn filter_output_formats(&self, frame: &ffmpeg::AVFrame) -> Vec<OutputPixelFormat> {
+        let native_pix_fmt = if frame.hw_frames_ctx.is_null() {
+            frame.format
         } else {
-            format!(
-                "format={}",
-                crate::join_pixel_formats(&self.output_pixel_formats)
-            )
+            let hw_frames_ctx = unsafe {
+                &*(*frame.hw_frames_ctx)
+                    .data
+                    .cast::<ffmpeg::AVHWFramesContext>()
+            };
+            hw_frames_ctx.sw_format
+        };
+
+        match OutputPixelFormat::try_from_av_pix_fmt(native_pix_fmt) {
+            Some(format) if self.output_pixel_formats.contains(&format) => vec![format],
+            _ => self.output_pixel_formats.clone(),
         }
     }
 
-    pub(crate) fn filter_input_args(&self) -> std::ffi::CString {
+    pub(crate) fn build_filter_args(&self, frame: &ffmpeg::AVFrame) -> String {
+        let is_hw_frame = !frame.hw_frames_ctx.is_null();
+        let crop = self.filter_options.crop;
+        let target_resolution = self.filter_options.output_target(Resolution {
+            width: frame.width as usize,
+            height: frame.height as usize,
+        });
+
+        // Scaling on the device avoids downloading the full resolution frame,
+        // but cropping happens in software so must come before any scaling.
+        let hw_scale_filter = self
+            .accelerator
+            .filter(|_| is_hw_frame && crop.is_none())
+            .and_then(|accelerator| accelerator.to_scale_filter_name());
+
+        let mut filters = Vec::new();
+        if let (Some((resolution, mode)), Some(name)) =
+            (target_resolution, hw_scale_filter)
+        {
+            filters.push(format!("{name}={}", mode.to_filter_args(resolution)));
+        }
+
+        if is_hw_frame {
+            filters.push("hwdownload".to_string());
+        }
+
+        if let Some(crop) = crop {
+            filters.push(format!(
+                "crop=w={}:h={}:x={}:y={}:exact=1",
+                crop.width, crop.height, crop.x, crop.y,
+            ));
+        }
+
+        if let Some((resolution, mode)) = target_resolution {
+            if hw_scale_filter.is_none() {
+                filters.push(format!("scale={}", mode.to_filter_args(resolution)));
+            }
+            if mode == ScalingMode::Fill {
+                filters.push(format!("crop={}:{}", resolution.width, resolution.height));
+            }
+        }
+
+        let formats = crate::join_pixel_formats(&self.filter_output_formats(frame));
+        filters.push(format!("format={formats}"));
+
+        filters.join(",")
+    }
+
+    pub(crate) fn filter_input_args(
+        &self,
+        frame: &ffmpeg::AVFrame,
+    ) -> std::ffi::CString {
         use std::fmt::Write;
         let ctx = self.as_ctx();
         let mut args = String::new();
-        write!(args, "width={}", ctx.width).unwrap();
-        write!(args, ":height={}", ctx.height).unwrap();
-        write!(args, ":pix_fmt={}", self.pix_fmt()).unwrap();
+        write!(args, "width={}", frame.width).unwrap();
+        write!(args, ":height={}", frame.height).unwrap();
+        write!(args, ":pix_fmt={}", frame.format).unwrap();
         write!(
             args,
             ":time_base={}/{}",
@@ -179,11 +309,28 @@ impl VideoDecoder {
         std::ffi::CString::new(args).unwrap()
     }
 
-    fn ensure_filter_init(&mut self) -> Result<(), error::FFmpegError> {
-        if self.filter.is_some() {
-            return Ok(());
+    /// Ensure the filter graph exists and accepts the given frame.
+    ///
+    /// The graph is recreated if the frame dimensions or pixel format differ from
+    /// what the graph was configured with, which can happen mid-stream or after a seek.
+    fn ensure_filter_init(
+        &mut self,
+        frame: &ffmpeg::AVFrame,
+    ) -> Result<(), error::FFmpegError> {
+        if let Some(filter) = self.filter.as_ref() {
+            if filter.accepts(frame) {
+                return Ok(());
+            }
+            tracing::debug!(
+                width = frame.width,
+                height = frame.height,
+                pix_fmt = frame.format,
+                "frame parameters changed, recreating filter pipeline",
+            );
+            self.filter = None;
         }
-        let pipeline = crate::filter::create_video_filter_pipeline(self)?;
+
+        let pipeline = crate::filter::create_video_filter_pipeline(self, frame)?;
         self.filter = Some(pipeline);
         Ok(())
     }
@@ -193,9 +340,9 @@ impl VideoDecoder {
             unsafe { ffmpeg::avcodec_receive_frame(self.as_mut_ctx(), self.frame) };
         error::convert_ff_result(result)?;
 
-        self.ensure_filter_init()?;
+        let frame = unsafe { &mut *self.frame };
+        self.ensure_filter_init(frame)?;
         if let Some(filter) = self.filter.as_mut() {
-            let frame = unsafe { &mut *self.frame };
             filter.write_frame(frame)?;
         }
 
@@ -248,17 +395,35 @@ impl Decoder for VideoDecoder {
             }
         }
 
+        if let Some(filter) = self.filter.as_mut() {
+            filter.close()?;
+        }
         self.has_flushed = true;
 
         Ok(())
     }
 
+    fn flush_buffers(&mut self) {
+        unsafe { ffmpeg::avcodec_flush_buffers(self.as_mut_ctx()) };
+
+        // The filter graph may still be holding frames from before the flush,
+        // so it is recreated when the next frame arrives.
+        self.filter = None;
+        self.has_flushed = false;
+        self.packets_written = 0;
+    }
+
     fn write_packet(
         &mut self,
         packet: &mut ffmpeg::AVPacket,
     ) -> Result<(), error::FFmpegError> {
+        // The number is offset by one, so frames with a null `opaque` are
+        // known to have no packet number.
+        packet.opaque = ptr::without_provenance_mut((self.packets_written + 1) as usize);
+
         let result = unsafe { ffmpeg::avcodec_send_packet(self.as_mut_ctx(), packet) };
         error::convert_ff_result(result)?;
+        self.packets_written += 1;
 
         match self.transfer_decoded_frame_to_filter() {
             Err(err) if err.needs_data() => Ok(()),
@@ -272,6 +437,9 @@ impl Decoder for VideoDecoder {
             filter.read_frame(frame)?;
             self.apply_context_to_frame(frame);
             Ok(())
+        } else if self.has_flushed {
+            // No frames were ever written, so there is no filter to drain.
+            Err(error::FFmpegError::from_raw_errno(ffmpeg::AVERROR_EOF))
         } else {
             Err(error::FFmpegError::from_raw_errno(-(ffmpeg::EAGAIN as i32)))
         }
@@ -341,6 +509,20 @@ fn create_accelerated_decoder(
     Ok(Some(codec))
 }
 
+/// Returns if the codec can be decoded using the accelerator, either via a
+/// hardware config of the codec or a dedicated hardware decoder, i.e. `h264_qsv`.
+pub(crate) fn supports_accelerator(
+    codec: &ffmpeg::AVCodec,
+    accelerator: Accelerator,
+) -> bool {
+    if !find_accelerator_config(codec, accelerator).is_null() {
+        return true;
+    }
+
+    let full_codec_name = format_codec_name_with_accelerator(codec, accelerator);
+    find_decoder_by_name(&full_codec_name).is_some()
+}
+
 fn format_codec_name_with_accelerator(
     codec: &ffmpeg::AVCodec,
     accelerator: Accelerator,
@@ -0,0 +1,269 @@
+use rusty_ffmpeg::ffi as ffmpeg;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// The matrix coefficients used to convert between YUV and RGB.
+pub enum ColorSpace {
+    #[default]
+    /// The color space is unknown or not set.
+    Unspecified,
+    /// The frame is RGB, no matrix is applied.
+    Rgb,
+    /// ITU-R BT.709, used by most HD content.
+    Bt709,
+    /// FCC Title 47 Code of Federal Regulations 73.682.
+    Fcc,
+    /// ITU-R BT.470 System B/G, also used by BT.601 PAL content.
+    Bt470bg,
+    /// SMPTE 170M, also used by BT.601 NTSC content.
+    Smpte170m,
+    /// SMPTE 240M.
+    Smpte240m,
+    /// YCgCo.
+    YCgCo,
+    /// ITU-R BT.2020 non-constant luminance, used by most UHD and HDR content.
+    Bt2020Ncl,
+    /// ITU-R BT.2020 constant luminance.
+    Bt2020Cl,
+    /// SMPTE 2085.
+    Smpte2085,
+    /// Chromaticity-derived non-constant luminance.
+    ChromaDerivedNcl,
+    /// Chromaticity-derived constant luminance.
+    ChromaDerivedCl,
+    /// ITU-R BT.2100 ICtCp.
+    ICtCp,
+}
+
+impl ColorSpace {
+    pub(crate) fn from_av_color_space(color_space: ffmpeg::AVColorSpace) -> Self {
+        match color_space {
+            ffmpeg::AVCOL_SPC_RGB => Self::Rgb,
+            ffmpeg::AVCOL_SPC_BT709 => Self::Bt709,
+            ffmpeg::AVCOL_SPC_FCC => Self::Fcc,
+            ffmpeg::AVCOL_SPC_BT470BG => Self::Bt470bg,
+            ffmpeg::AVCOL_SPC_SMPTE170M => Self::Smpte170m,
+            ffmpeg::AVCOL_SPC_SMPTE240M => Self::Smpte240m,
+            ffmpeg::AVCOL_SPC_YCGCO => Self::YCgCo,
+            ffmpeg::AVCOL_SPC_BT2020_NCL => Self::Bt2020Ncl,
+            ffmpeg::AVCOL_SPC_BT2020_CL => Self::Bt2020Cl,
+            ffmpeg::AVCOL_SPC_SMPTE2085 => Self::Smpte2085,
+            ffmpeg::AVCOL_SPC_CHROMA_DERIVED_NCL => Self::ChromaDerivedNcl,
+            ffmpeg::AVCOL_SPC_CHROMA_DERIVED_CL => Self::ChromaDerivedCl,
+            ffmpeg::AVCOL_SPC_ICTCP => Self::ICtCp,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// The range of values used by each component.
+pub enum ColorRange {
+    #[default]
+    /// The color range is unknown or not set.
+    Unspecified,
+    /// Limited or "TV" range, i.e. `16..=235` for 8-bit luma.
+    Limited,
+    /// Full or "PC" range, i.e. `0..=255` for 8-bit luma.
+    Full,
+}
+
+impl ColorRange {
+    pub(crate) fn from_av_color_range(color_range: ffmpeg::AVColorRange) -> Self {
+        match color_range {
+            ffmpeg::AVCOL_RANGE_MPEG => Self::Limited,
+            ffmpeg::AVCOL_RANGE_JPEG => Self::Full,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// The chromaticity coordinates of the source primaries.
+pub enum ColorPrimaries {
+    #[default]
+    /// The color primaries are unknown or not set.
+    Unspecified,
+    /// ITU-R BT.709, also used by sRGB.
+    Bt709,
+    /// ITU-R BT.470 System M.
+    Bt470m,
+    /// ITU-R BT.470 System B/G, also used by BT.601 PAL content.
+    Bt470bg,
+    /// SMPTE 170M, also used by BT.601 NTSC content.
+    Smpte170m,
+    /// SMPTE 240M.
+    Smpte240m,
+    /// Generic film using Illuminant C.
+    Film,
+    /// ITU-R BT.2020, used by most UHD and HDR content.
+    Bt2020,
+    /// SMPTE ST 428-1 (CIE 1931 XYZ).
+    Smpte428,
+    /// SMPTE ST 431-2 (DCI-P3).
+    Smpte431,
+    /// SMPTE ST 432-1 (Display P3).
+    Smpte432,
+    /// EBU Tech. 3213-E.
+    Ebu3213,
+}
+
+impl ColorPrimaries {
+    pub(crate) fn from_av_color_primaries(
+        color_primaries: ffmpeg::AVColorPrimaries,
+    ) -> Self {
+        match color_primaries {
+            ffmpeg::AVCOL_PRI_BT709 => Self::Bt709,
+            ffmpeg::AVCOL_PRI_BT470M => Self::Bt470m,
+            ffmpeg::AVCOL_PRI_BT470BG => Self::Bt470bg,
+            ffmpeg::AVCOL_PRI_SMPTE170M => Self::Smpte170m,
+            ffmpeg::AVCOL_PRI_SMPTE240M => Self::Smpte240m,
+            ffmpeg::AVCOL_PRI_FILM => Self::Film,
+            ffmpeg::AVCOL_PRI_BT2020 => Self::Bt2020,
+            ffmpeg::AVCOL_PRI_SMPTE428 => Self::Smpte428,
+            ffmpeg::AVCOL_PRI_SMPTE431 => Self::Smpte431,
+            ffmpeg::AVCOL_PRI_SMPTE432 => Self::Smpte432,
+            ffmpeg::AVCOL_PRI_EBU3213 => Self::Ebu3213,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// The transfer characteristics (opto-electronic transfer function) of the frame.
+pub enum ColorTransfer {
+    #[default]
+    /// The transfer characteristics are unknown or not set.
+    Unspecified,
+    /// ITU-R BT.709.
+    Bt709,
+    /// Gamma 2.2, also used by ITU-R BT.470 System M.
+    Gamma22,
+    /// Gamma 2.8, also used by ITU-R BT.470 System B/G.
+    Gamma28,
+    /// SMPTE 170M, also used by BT.601 content.
+    Smpte170m,
+    /// SMPTE 240M.
+    Smpte240m,
+    /// Linear transfer characteristics.
+    Linear,
+    /// Logarithmic transfer characteristics (100:1 range).
+    Log,
+    /// Logarithmic transfer characteristics (100 * Sqrt(10) : 1 range).
+    LogSqrt,
+    /// IEC 61966-2-4.
+    Iec61966_2_4,
+    /// ITU-R BT.1361 extended color gamut.
+    Bt1361Ecg,
+    /// IEC 61966-2-1, better known as sRGB.
+    Iec61966_2_1,
+    /// ITU-R BT.2020 for 10-bit systems.
+    Bt2020_10,
+    /// ITU-R BT.2020 for 12-bit systems.
+    Bt2020_12,
+    /// SMPTE ST 2084, better known as PQ, used by HDR10 content.
+    Smpte2084,
+    /// SMPTE ST 428-1.
+    Smpte428,
+    /// ARIB STD-B67, better known as hybrid log-gamma (HLG).
+    AribStdB67,
+}
+
+impl ColorTransfer {
+    pub(crate) fn from_av_color_transfer(
+        color_trc: ffmpeg::AVColorTransferCharacteristic,
+    ) -> Self {
+        match color_trc {
+            ffmpeg::AVCOL_TRC_BT709 => Self::Bt709,
+            ffmpeg::AVCOL_TRC_GAMMA22 => Self::Gamma22,
+            ffmpeg::AVCOL_TRC_GAMMA28 => Self::Gamma28,
+            ffmpeg::AVCOL_TRC_SMPTE170M => Self::Smpte170m,
+            ffmpeg::AVCOL_TRC_SMPTE240M => Self::Smpte240m,
+            ffmpeg::AVCOL_TRC_LINEAR => Self::Linear,
+            ffmpeg::AVCOL_TRC_LOG => Self::Log,
+            ffmpeg::AVCOL_TRC_LOG_SQRT => Self::LogSqrt,
+            ffmpeg::AVCOL_TRC_IEC61966_2_4 => Self::Iec61966_2_4,
+            ffmpeg::AVCOL_TRC_BT1361_ECG => Self::Bt1361Ecg,
+            ffmpeg::AVCOL_TRC_IEC61966_2_1 => Self::Iec61966_2_1,
+            ffmpeg::AVCOL_TRC_BT2020_10 => Self::Bt2020_10,
+            ffmpeg::AVCOL_TRC_BT2020_12 => Self::Bt2020_12,
+            ffmpeg::AVCOL_TRC_SMPTE2084 => Self::Smpte2084,
+            ffmpeg::AVCOL_TRC_SMPTE428 => Self::Smpte428,
+            ffmpeg::AVCOL_TRC_ARIB_STD_B67 => Self::AribStdB67,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// The static HDR10 metadata describing the display the content was mastered on
+/// and the light levels of the content itself.
+///
+/// Each field is `None` if the stream does not provide it.
+pub struct Hdr10Metadata {
+    /// The CIE 1931 `xy` chromaticity coordinates of the red, green and blue
+    /// primaries of the mastering display, in that order.
+    pub display_primaries: Option<[[f64; 2]; 3]>,
+    /// The CIE 1931 `xy` chromaticity coordinates of the white point of the
+    /// mastering display.
+    pub white_point: Option<[f64; 2]>,
+    /// The minimum luminance of the mastering display in cd/m².
+    pub min_luminance: Option<f64>,
+    /// The maximum luminance of the mastering display in cd/m².
+    pub max_luminance: Option<f64>,
+    /// The maximum content light level (MaxCLL) in cd/m².
+    pub max_cll: Option<u32>,
+    /// The maximum frame-average light level (MaxFALL) in cd/m².
+    pub max_fall: Option<u32>,
+}
+
+impl Hdr10Metadata {
+    /// Create the metadata from the raw mastering display and content light
+    /// level side data.
+    ///
+    /// Returns `None` if neither is provided.
+    pub(crate) fn from_raw(
+        mastering_display: Option<&ffmpeg::AVMasteringDisplayMetadata>,
+        content_light_level: Option<&ffmpeg::AVContentLightMetadata>,
+    ) -> Option<Self> {
+        if mastering_display.is_none() && content_light_level.is_none() {
+            return None;
+        }
+
+        let mut metadata = Self::default();
+
+        if let Some(mastering_display) = mastering_display {
+            if mastering_display.has_primaries != 0 {
+                let primaries = mastering_display.display_primaries;
+                metadata.display_primaries = Some(primaries.map(|primary| {
+                    [rational_to_f64(primary[0]), rational_to_f64(primary[1])]
+                }));
+                metadata.white_point = Some([
+                    rational_to_f64(mastering_display.white_point[0]),
+                    rational_to_f64(mastering_display.white_point[1]),
+                ]);
+            }
+
+            if mastering_display.has_luminance != 0 {
+                metadata.min_luminance =
+                    Some(rational_to_f64(mastering_display.min_luminance));
+                metadata.max_luminance =
+                    Some(rational_to_f64(mastering_display.max_luminance));
+            }
+        }
+
+        if let Some(content_light_level) = content_light_level {
+            metadata.max_cll = Some(content_light_level.MaxCLL);
+            metadata.max_fall = Some(content_light_level.MaxFALL);
+        }
+
+        Some(metadata)
+    }
+}
+
+fn rational_to_f64(rational: ffmpeg::AVRational) -> f64 {
+    if rational.den == 0 {
+        0.0
+    } else {
+        rational.num as f64 / rational.den as f64
+    }
+}
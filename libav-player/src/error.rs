@@ -3,26 +3,157 @@ use rusty_ffmpeg::ffi as ffmpeg;
 /// A player result type alias.
 pub type Result<T> = std::result::Result<T, PlayerError>;
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, Clone)]
 /// An error that occurred within the player.
 pub enum PlayerError {
     #[error(transparent)]
     /// An error the was raised by the FFmpeg libraries.
-    FFmpegError(#[from] FFmpegError),
+    FFmpegError(FFmpegError),
+    #[error("network timeout: {0}")]
+    /// A network operation or read from the source timed out, this is typically
+    /// transient and the operation can be retried.
+    NetworkTimeout(FFmpegError),
+    #[error("invalid data: {0}")]
+    /// The input contained data which could not be demuxed or decoded,
+    /// i.e. a corrupt bitstream.
+    InvalidData(FFmpegError),
+    #[error("I/O error: {0}")]
+    /// Reading from or writing to the underlying input failed.
+    Io(FFmpegError),
+    #[error("permission denied: {0}")]
+    /// Access to the input was denied.
+    PermissionDenied(FFmpegError),
+    #[error("operation cancelled")]
+    /// The operation was aborted via a cancel handle.
+    ///
+    /// See [crate::InputSource::open_url_with_cancel] and [crate::MediaPlayer::cancel_handle].
+    Cancelled,
     #[error("end of stream")]
     /// The streams have been fully consumed and there are no frames
     /// left to process.
     EndOfStream,
+    #[error("the player is paused")]
+    /// The player is paused and will not produce frames until
+    /// [crate::MediaPlayer::play] is called.
+    Paused,
+    #[error("the source is not seekable")]
+    /// The source does not support seeking, i.e. a live stream or a pipe.
+    ///
+    /// See [crate::InputSource::is_seekable].
+    NotSeekable,
     #[error("no available streams")]
     /// There are no streams available to play.
     NoAvailableStreams,
+    #[error("stream {index} is not a valid {expected:?} stream")]
+    /// The stream index provided does not exist or is not of the expected media type.
+    InvalidStream {
+        /// The index of the stream requested.
+        index: usize,
+        /// The media type the stream was expected to be.
+        expected: crate::MediaType,
+    },
+    #[error(
+        "crop region {width}x{height} at ({x}, {y}) does not fit within the {}x{} video",
+        resolution.width,
+        resolution.height,
+    )]
+    /// The crop region provided does not fit within the video resolution.
+    ///
+    /// See [crate::MediaPlayerBuilder::with_crop].
+    InvalidCrop {
+        /// The x offset of the region in pixels.
+        x: usize,
+        /// The y offset of the region in pixels.
+        y: usize,
+        /// The width of the region in pixels.
+        width: usize,
+        /// The height of the region in pixels.
+        height: usize,
+        /// The resolution of the video stream.
+        resolution: crate::Resolution,
+    },
+    #[error("video resolution {resolution} exceeds the maximum of {max}")]
+    /// The video resolution exceeds the maximum allowed resolution.
+    ///
+    /// See [crate::MediaPlayerBuilder::with_max_resolution].
+    ResolutionTooLarge {
+        /// The resolution the video would be decoded at.
+        resolution: crate::Resolution,
+        /// The maximum allowed resolution.
+        max: crate::Resolution,
+    },
 }
 
-#[derive(Debug)]
+impl PlayerError {
+    /// Returns the underlying [FFmpegError] if the error was raised by FFmpeg.
+    pub fn ffmpeg_error(&self) -> Option<&FFmpegError> {
+        match self {
+            Self::FFmpegError(err)
+            | Self::NetworkTimeout(err)
+            | Self::InvalidData(err)
+            | Self::Io(err)
+            | Self::PermissionDenied(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FFmpegError> for PlayerError {
+    fn from(err: FFmpegError) -> Self {
+        match err.kind() {
+            FFmpegErrorKind::Timeout => PlayerError::NetworkTimeout(err),
+            FFmpegErrorKind::InvalidData => PlayerError::InvalidData(err),
+            FFmpegErrorKind::Other(ffmpeg::AVERROR_EXIT) => PlayerError::Cancelled,
+            FFmpegErrorKind::Other(errno) if errno == -(ffmpeg::EIO as i32) => {
+                PlayerError::Io(err)
+            },
+            FFmpegErrorKind::Other(errno) if errno == -(ffmpeg::EACCES as i32) => {
+                PlayerError::PermissionDenied(err)
+            },
+            _ => PlayerError::FFmpegError(err),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// The common kinds of [FFmpegError], named from their errno.
+pub enum FFmpegErrorKind {
+    /// The end of the input was reached, `AVERROR_EOF`.
+    Eof,
+    /// More data is required before the operation can complete, `AVERROR(EAGAIN)`.
+    Again,
+    /// The operation is not supported by the input or codec, `AVERROR(ENOSYS)`.
+    NotImplemented,
+    /// The input contained invalid data, `AVERROR_INVALIDDATA`.
+    InvalidData,
+    /// The requested stream could not be found, `AVERROR_STREAM_NOT_FOUND`.
+    StreamNotFound,
+    /// The operation timed out, `AVERROR(ETIMEDOUT)`.
+    Timeout,
+    /// Any other errno.
+    Other(i32),
+}
+
+impl FFmpegErrorKind {
+    fn from_errno(errno: i32) -> Self {
+        match errno {
+            ffmpeg::AVERROR_EOF => Self::Eof,
+            ffmpeg::AVERROR_INVALIDDATA => Self::InvalidData,
+            ffmpeg::AVERROR_STREAM_NOT_FOUND => Self::StreamNotFound,
+            errno if errno == -(ffmpeg::EAGAIN as i32) => Self::Again,
+            errno if errno == -(ffmpeg::ENOSYS as i32) => Self::NotImplemented,
+            errno if errno == -(ffmpeg::ETIMEDOUT as i32) => Self::Timeout,
+            errno => Self::Other(errno),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 /// An error originating from libav / FFmpeg.
 pub struct FFmpegError {
     errno: i32,
     msg: String,
+    context: Option<String>,
 }
 
 impl FFmpegError {
@@ -32,38 +163,67 @@ impl FFmpegError {
         self.errno
     }
 
+    #[inline]
+    /// Returns the kind of error, see [FFmpegErrorKind].
+    pub fn kind(&self) -> FFmpegErrorKind {
+        FFmpegErrorKind::from_errno(self.errno)
+    }
+
     #[inline]
     /// Returns the error message from FFmpeg.
     pub fn message(&self) -> &str {
         &self.msg
     }
 
+    #[inline]
+    /// Returns the operation which failed, i.e. `opening video stream 0`,
+    /// if it is known.
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    /// Attach the operation which failed to the error.
+    pub(crate) fn with_context(mut self, context: impl std::fmt::Display) -> Self {
+        self.context = Some(context.to_string());
+        self
+    }
+
     #[inline]
     pub(crate) fn needs_data(&self) -> bool {
-        self.errno() == -(ffmpeg::EAGAIN as i32)
+        self.kind() == FFmpegErrorKind::Again
     }
 
     #[inline]
     pub(crate) fn is_eof(&self) -> bool {
-        self.errno() == ffmpeg::AVERROR_EOF
+        self.kind() == FFmpegErrorKind::Eof
     }
 
     pub(crate) fn from_raw_errno(errno: i32) -> Self {
         let msg = ffmpeg::av_err2str(errno);
-        Self { errno, msg }
+        Self {
+            errno,
+            msg,
+            context: None,
+        }
     }
 
     pub(crate) fn custom(msg: impl std::fmt::Display) -> Self {
         Self {
             errno: ffmpeg::AVERROR_UNKNOWN,
             msg: msg.to_string(),
+            context: None,
         }
     }
 }
 
 impl std::fmt::Display for FFmpegError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "FFmpeg Error ({:?}): {}", self.errno, self.msg)
+        match self.context.as_deref() {
+            Some(context) => {
+                write!(f, "FFmpeg Error ({:?}) {context}: {}", self.errno, self.msg)
+            },
+            None => write!(f, "FFmpeg Error ({:?}): {}", self.errno, self.msg),
+        }
     }
 }
 
@@ -4,7 +4,68 @@ use std::ptr;
 use rusty_ffmpeg::ffi as ffmpeg;
 
 use crate::codec::VideoDecoder;
-use crate::error;
+use crate::{Resolution, ScalingMode, error};
+
+#[derive(Debug, Clone, Default)]
+/// Additional processing the filter pipeline applies to the video frames.
+pub(crate) struct VideoFilterOptions {
+    /// Crop the frames to the given region before any scaling.
+    pub(crate) crop: Option<CropRect>,
+    /// Scale the frames to the given resolution.
+    pub(crate) target_resolution: Option<(Resolution, ScalingMode)>,
+    /// Scale any frames exceeding the given resolution down to fit within it.
+    pub(crate) max_resolution: Option<Resolution>,
+}
+
+impl VideoFilterOptions {
+    /// Returns the resolution and [ScalingMode] frames of the `source` resolution
+    /// are scaled to, shrinking the target resolution to fit the max resolution.
+    ///
+    /// This is checked for every frame, as the resolution can change mid-stream.
+    pub(crate) fn output_target(
+        &self,
+        source: Resolution,
+    ) -> Option<(Resolution, ScalingMode)> {
+        let source = self.crop.map_or(source, |crop| crop.resolution());
+        self.max_resolution
+            .and_then(|max| {
+                crate::cap_target_resolution(source, self.target_resolution, max)
+            })
+            .or(self.target_resolution)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// A region of the source video frame in pixels.
+pub(crate) struct CropRect {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl CropRect {
+    /// Returns if the region is non-empty and fully within the given resolution.
+    pub(crate) fn fits_within(&self, resolution: Resolution) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self
+                .x
+                .checked_add(self.width)
+                .is_some_and(|end| end <= resolution.width)
+            && self
+                .y
+                .checked_add(self.height)
+                .is_some_and(|end| end <= resolution.height)
+    }
+
+    pub(crate) fn resolution(&self) -> Resolution {
+        Resolution {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
 
 /// Creates the video decoder filter pipeline.
 ///
@@ -12,21 +73,22 @@ use crate::error;
 /// which may be done either in hardware or software depending on support.
 pub(crate) fn create_video_filter_pipeline(
     video_decoder: &VideoDecoder,
+    frame: &ffmpeg::AVFrame,
 ) -> Result<VideoFilterPipeline, error::FFmpegError> {
     tracing::debug!(
         target_pixel_formats = ?video_decoder.output_pixel_formats(),
         "creating filter pipeline",
     );
 
-    let mut pipeline = VideoFilterPipeline::new()?;
+    let mut pipeline = VideoFilterPipeline::new(frame)?;
     let mut inputs = ptr::null_mut();
     let mut outputs = ptr::null_mut();
 
-    let buffer_src_args = video_decoder.filter_input_args();
+    let buffer_src_args = video_decoder.filter_input_args(frame);
     let buffer_src = unsafe { ffmpeg::avfilter_get_by_name(c"buffer".as_ptr()) };
     let buffer_sink = unsafe { ffmpeg::avfilter_get_by_name(c"buffersink".as_ptr()) };
 
-    let filter = video_decoder.build_filter_args();
+    let filter = video_decoder.build_filter_args(frame);
     tracing::debug!(filter = ?filter, "got filter graph");
     let filter_graph_str = CString::new(filter).unwrap();
 
@@ -43,7 +105,7 @@ pub(crate) fn create_video_filter_pipeline(
         tracing::debug!("filter input created");
 
         let params = ffmpeg::av_buffersrc_parameters_alloc();
-        (*params).hw_frames_ctx = video_decoder.hw_frames_ctx();
+        (*params).hw_frames_ctx = frame.hw_frames_ctx;
         ffmpeg::av_buffersrc_parameters_set(pipeline.buffer_src_ctx, params);
         ffmpeg::av_free(params.cast());
 
@@ -102,14 +164,17 @@ pub(crate) fn create_video_filter_pipeline(
         ffmpeg::avfilter_inout_free(&raw mut outputs);
 
         // Attach hardware context if available to the filters.
+        let hw_device_ctx = video_decoder.hw_device_ctx();
         let graph = &mut *pipeline.filter_graph;
         for i in 0..graph.nb_filters {
             let ctx = *graph.filters.offset(i as isize);
             assert!(!ctx.is_null());
 
             tracing::debug!("filter_stage: {:?}", std::ffi::CStr::from_ptr((*ctx).name));
-            if (*(*ctx).filter).flags as u32 & ffmpeg::AVFILTER_FLAG_HWDEVICE != 0 {
-                (*ctx).hw_device_ctx = video_decoder.hw_frames_ctx();
+            if !hw_device_ctx.is_null()
+                && (*(*ctx).filter).flags as u32 & ffmpeg::AVFILTER_FLAG_HWDEVICE != 0
+            {
+                (*ctx).hw_device_ctx = ffmpeg::av_buffer_ref(hw_device_ctx);
             }
         }
         tracing::debug!("attached hardware context");
@@ -133,10 +198,13 @@ pub struct VideoFilterPipeline {
     filter_graph: *mut ffmpeg::AVFilterGraph,
     buffer_src_ctx: *mut ffmpeg::AVFilterContext,
     buffer_sink_ctx: *mut ffmpeg::AVFilterContext,
+    input_width: i32,
+    input_height: i32,
+    input_format: i32,
 }
 
 impl VideoFilterPipeline {
-    fn new() -> Result<Self, error::FFmpegError> {
+    fn new(frame: &ffmpeg::AVFrame) -> Result<Self, error::FFmpegError> {
         let filter_graph = unsafe { ffmpeg::avfilter_graph_alloc() };
         if filter_graph.is_null() {
             Err(error::FFmpegError::custom(
@@ -147,10 +215,21 @@ impl VideoFilterPipeline {
                 filter_graph,
                 buffer_src_ctx: ptr::null_mut(),
                 buffer_sink_ctx: ptr::null_mut(),
+                input_width: frame.width,
+                input_height: frame.height,
+                input_format: frame.format,
             })
         }
     }
 
+    /// Returns if the frame matches the dimensions and pixel format the
+    /// pipeline was created for.
+    pub(crate) fn accepts(&self, frame: &ffmpeg::AVFrame) -> bool {
+        frame.width == self.input_width
+            && frame.height == self.input_height
+            && frame.format == self.input_format
+    }
+
     pub(crate) fn write_frame(
         &mut self,
         frame: &mut ffmpeg::AVFrame,
@@ -175,6 +254,16 @@ impl VideoFilterPipeline {
         error::convert_ff_result(result)?;
         Ok(())
     }
+
+    /// Signal that no more frames will be written, any frames buffered within
+    /// the filters are then returned by [Self::read_frame] before it returns EOF.
+    pub(crate) fn close(&mut self) -> Result<(), error::FFmpegError> {
+        let result = unsafe {
+            ffmpeg::av_buffersrc_add_frame_flags(self.buffer_src_ctx, ptr::null_mut(), 0)
+        };
+        error::convert_ff_result(result)?;
+        Ok(())
+    }
 }
 
 impl Drop for VideoFilterPipeline {
@@ -0,0 +1,238 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{DecodedFrame, MediaPlayer, PlayerError, SeekMode};
+
+/// The number of decoded frames the player thread can get ahead of the consumer
+/// before it blocks.
+const FRAME_CHANNEL_CAPACITY: usize = 4;
+
+enum Command {
+    Play,
+    Pause,
+    Seek {
+        position: Duration,
+        mode: SeekMode,
+        generation: u64,
+    },
+}
+
+/// A frame or error produced by the player thread, tagged with the seek
+/// generation it belongs to.
+struct TaggedFrame {
+    generation: u64,
+    result: crate::Result<DecodedFrame>,
+}
+
+/// A handle to a [MediaPlayer] running on a dedicated thread, created via
+/// [MediaPlayer::spawn].
+///
+/// Controls are sent to the player thread without waiting for them to be applied,
+/// any errors they produce are returned by [PlayerHandle::recv_frame].
+///
+/// The player thread is stopped and joined when the handle is dropped.
+pub struct PlayerHandle {
+    commands: Option<mpsc::Sender<Command>>,
+    frames: Option<mpsc::Receiver<TaggedFrame>>,
+    cancel: Arc<AtomicBool>,
+    generation: u64,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PlayerHandle {
+    pub(crate) fn spawn(player: MediaPlayer) -> Self {
+        let (commands_tx, commands_rx) = mpsc::channel();
+        let (frames_tx, frames_rx) = mpsc::sync_channel(FRAME_CHANNEL_CAPACITY);
+        let cancel = player.cancel_handle();
+
+        let thread = std::thread::Builder::new()
+            .name("libav-player".to_string())
+            .spawn(move || run_player(player, commands_rx, frames_tx))
+            .expect("failed to spawn player thread");
+
+        Self {
+            commands: Some(commands_tx),
+            frames: Some(frames_rx),
+            cancel,
+            generation: 0,
+            thread: Some(thread),
+        }
+    }
+
+    /// Begin the media decoding.
+    ///
+    /// The player thread is idle until this is called.
+    pub fn play(&self) {
+        self.send(Command::Play);
+    }
+
+    /// Pause the media decoding.
+    ///
+    /// As with [MediaPlayer::pause], the player thread stops decoding frames
+    /// until [PlayerHandle::play] is called.
+    ///
+    /// Once any frames decoded before the pause have been received,
+    /// [PlayerHandle::recv_frame] returns [PlayerError::Paused] rather than
+    /// blocking until playback resumes.
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    /// Seek to a target position in the source.
+    ///
+    /// This is equivalent to calling [PlayerHandle::seek_with] with [SeekMode::Backward].
+    pub fn seek(&mut self, position: Duration) {
+        self.seek_with(position, SeekMode::Backward);
+    }
+
+    /// Seek to a target position in the source using the given [SeekMode].
+    ///
+    /// Any frames decoded before the seek which have not been received yet are discarded.
+    pub fn seek_with(&mut self, position: Duration, mode: SeekMode) {
+        self.generation += 1;
+        self.send(Command::Seek {
+            position,
+            mode,
+            generation: self.generation,
+        });
+    }
+
+    /// Wait for the next frame produced by the player thread.
+    ///
+    /// Returns [PlayerError::EndOfStream] once the streams have been fully
+    /// consumed, the player thread then idles until the next seek or play.
+    ///
+    /// Returns [PlayerError::Paused] once after [PlayerHandle::pause], further
+    /// calls block until [PlayerHandle::play] is called.
+    ///
+    /// Returns [PlayerError::Cancelled] if the player thread has stopped.
+    pub fn recv_frame(&self) -> crate::Result<DecodedFrame> {
+        let frames = self
+            .frames
+            .as_ref()
+            .expect("receiver is only taken on drop");
+        loop {
+            let frame = frames.recv().map_err(|_| PlayerError::Cancelled)?;
+            if frame.generation == self.generation {
+                return frame.result;
+            }
+        }
+    }
+
+    /// Take the next frame produced by the player thread if one is ready
+    /// without blocking.
+    ///
+    /// Returns `None` if no frame is ready yet, see [PlayerHandle::recv_frame]
+    /// for the possible errors.
+    pub fn try_recv_frame(&self) -> Option<crate::Result<DecodedFrame>> {
+        let frames = self
+            .frames
+            .as_ref()
+            .expect("receiver is only taken on drop");
+        loop {
+            let frame = match frames.try_recv() {
+                Ok(frame) => frame,
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Some(Err(PlayerError::Cancelled));
+                },
+            };
+            if frame.generation == self.generation {
+                return Some(frame.result);
+            }
+        }
+    }
+
+    fn send(&self, command: Command) {
+        if let Some(commands) = self.commands.as_ref() {
+            // If the thread has stopped, the error is surfaced on the next receive.
+            let _ = commands.send(command);
+        }
+    }
+}
+
+impl Drop for PlayerHandle {
+    fn drop(&mut self) {
+        // Closing both channels wakes the player thread if it is waiting on
+        // either, and the cancel flag aborts any blocking reads from the source.
+        self.cancel.store(true, Ordering::Relaxed);
+        self.commands.take();
+        self.frames.take();
+
+        let has_panicked = self
+            .thread
+            .take()
+            .is_some_and(|thread| thread.join().is_err());
+        if has_panicked {
+            tracing::error!("player thread panicked");
+        }
+    }
+}
+
+fn run_player(
+    mut player: MediaPlayer,
+    commands: mpsc::Receiver<Command>,
+    frames: mpsc::SyncSender<TaggedFrame>,
+) {
+    let mut generation = 0;
+    let mut is_playing = false;
+    // Set after the end of the stream or an error, so we wait for a new command
+    // rather than spinning on the same error.
+    let mut is_stalled = false;
+
+    loop {
+        let command = if is_playing && !is_stalled {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+
+        let result = match command {
+            None => player.process_next_frame(),
+            Some(Command::Play) => {
+                is_playing = true;
+                is_stalled = false;
+                match player.play() {
+                    Ok(()) => continue,
+                    Err(err) => Err(err),
+                }
+            },
+            Some(Command::Pause) => {
+                is_playing = false;
+                // Wake up any consumer waiting on a frame that will not arrive.
+                player.pause().and(Err(PlayerError::Paused))
+            },
+            Some(Command::Seek {
+                position,
+                mode,
+                generation: seek_generation,
+            }) => {
+                generation = seek_generation;
+                is_stalled = false;
+                match player.seek_with(position, mode) {
+                    Ok(()) => continue,
+                    Err(err) => Err(err),
+                }
+            },
+        };
+
+        if result.is_err() {
+            is_stalled = true;
+        }
+
+        if frames.send(TaggedFrame { generation, result }).is_err() {
+            break;
+        }
+    }
+
+    tracing::debug!("player thread stopped");
+}
@@ -1,15 +1,51 @@
-use std::ffi::CString;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CString, c_int, c_void};
 use std::fmt::Formatter;
+use std::io::{Read, Seek};
 use std::path::Path;
 use std::ptr;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use rusty_ffmpeg::ffi as ffmpeg;
 
-use crate::codec::{AudioDecoder, SubtitleDecoder, VideoDecoder};
+use crate::bitstream::BitstreamExtractor;
+use crate::codec::{AudioDecoder, Decoder, SubtitleDecoder, VideoDecoder};
+use crate::filter::VideoFilterOptions;
+use crate::io::CustomIo;
+use crate::keyframe::{Keyframe, KeyframeIndex};
+use crate::packet::{MediaPacket, Packet};
+use crate::player::MediaRawFrame;
+use crate::remux::OutputContext;
 use crate::stream::StreamInfo;
-use crate::{AcceleratorConfig, MediaType, OutputPixelFormat, error};
+use crate::{
+    AcceleratorConfig,
+    AudioFormat,
+    ChannelLayout,
+    MediaType,
+    OutputPixelFormat,
+    SampleFormat,
+    SeekMode,
+    ThreadCount,
+    error,
+    pts_to_duration,
+};
+
+/// The duration of a stream [InputSource::estimate_bitrate] reads before estimating.
+const BITRATE_PROBE_DURATION: Duration = Duration::from_secs(10);
+/// The maximum number of packets [InputSource::estimate_bitrate] reads from all streams,
+/// this bounds the work done for streams with sparse packets.
+const BITRATE_PROBE_MAX_PACKETS: usize = 10_000;
+/// The maximum number of packets [InputSource::estimate_gop] reads from all streams,
+/// this bounds the work done for streams with very long or no repeating GOPs.
+const GOP_PROBE_MAX_PACKETS: usize = 10_000;
+/// The number of samples per channel reduced into a single min/max pair while
+/// decoding a waveform, before the pairs are reduced into the requested buckets.
+const WAVEFORM_BLOCK_SIZE: usize = 256;
+/// The min/max pair of a block or bucket which has not seen any samples yet.
+const WAVEFORM_EMPTY_PEAK: (f32, f32) = (f32::INFINITY, f32::NEG_INFINITY);
 
 /// The input source is a media source containing video or audio or both.
 ///
@@ -18,6 +54,22 @@ use crate::{AcceleratorConfig, MediaType, OutputPixelFormat, error};
 pub struct InputSource {
     url: url::Url,
     ctx: ptr::NonNull<ffmpeg::AVFormatContext>,
+    /// The custom IO context used when reading from something other than a URL.
+    ///
+    /// This must be dropped after the format context is closed.
+    io: Option<CustomIo>,
+    /// When set, any in-flight FFmpeg IO is aborted.
+    ///
+    /// The format context holds a raw pointer to this flag so it must
+    /// outlive the context.
+    cancel: Arc<AtomicBool>,
+    /// The bitrates estimated by [InputSource::estimate_bitrate] for each stream index.
+    bitrate_estimates: HashMap<usize, Option<usize>>,
+    /// The GOP lengths estimated by [InputSource::estimate_gop] for each stream index.
+    gop_estimates: HashMap<usize, Option<usize>>,
+    /// Set while no packets have been read since the source was opened or rewound,
+    /// probing from the start would otherwise lose the current read position.
+    is_at_start: bool,
 }
 
 impl std::fmt::Debug for InputSource {
@@ -50,25 +102,260 @@ impl InputSource {
     /// This method can block for an arbitrary amount of time as FFmpeg reads the source,
     /// some things like HLS streams can take several seconds.
     pub fn open_url(url: url::Url) -> crate::Result<Self> {
+        Self::open_url_with_options(url, &[])
+    }
+
+    /// Create a new [InputSource] using the provided [url::Url] and FFmpeg options.
+    ///
+    /// The options are passed to the demuxer and protocol when opening the input,
+    /// for example `("rtsp_transport", "tcp")`, `("user_agent", "...")` or
+    /// `("rw_timeout", "5000000")` to set a 5-second read timeout on network streams.
+    ///
+    /// Read timeouts while playing are returned as [crate::PlayerError::NetworkTimeout]
+    /// by [crate::MediaPlayer::process_next_frame], which can be called again to retry.
+    ///
+    /// Returns an error listing the option keys if any were not recognised by FFmpeg.
+    ///
+    /// WARNING:
+    /// This method can block for an arbitrary amount of time as FFmpeg reads the source,
+    /// some things like HLS streams can take several seconds.
+    pub fn open_url_with_options(
+        url: url::Url,
+        options: &[(&str, &str)],
+    ) -> crate::Result<Self> {
+        Self::open_url_inner(url, options, Arc::default())
+    }
+
+    /// Create a new [InputSource] using the provided [url::Url], limiting how much of
+    /// the source FFmpeg reads to detect the streams.
+    ///
+    /// - `probe_size` is the maximum number of bytes read, FFmpeg defaults to 5MB.
+    /// - `analyze_duration` is the maximum duration of media read, FFmpeg defaults
+    ///   to 5 seconds.
+    ///
+    /// Lower values reduce the time taken to open the source, which is useful for
+    /// live streams, but may leave stream parameters such as the framerate unknown
+    /// or miss streams which only start later on. Higher values help detect sparse
+    /// streams, i.e. subtitles, at the cost of a slower open.
+    ///
+    /// `None` keeps the FFmpeg default.
+    ///
+    /// WARNING:
+    /// This method can block for an arbitrary amount of time as FFmpeg reads the source,
+    /// some things like HLS streams can take several seconds.
+    pub fn open_url_with_probe(
+        url: url::Url,
+        probe_size: Option<u32>,
+        analyze_duration: Option<Duration>,
+    ) -> crate::Result<Self> {
+        let probe_size = probe_size.map(|size| size.to_string());
+        let analyze_duration =
+            analyze_duration.map(|duration| duration.as_micros().to_string());
+
+        let mut options = Vec::new();
+        if let Some(probe_size) = probe_size.as_deref() {
+            options.push(("probesize", probe_size));
+        }
+        if let Some(analyze_duration) = analyze_duration.as_deref() {
+            options.push(("analyzeduration", analyze_duration));
+        }
+
+        Self::open_url_inner(url, &options, Arc::default())
+    }
+
+    /// Create a new [InputSource] using the provided [url::Url] which can be
+    /// cancelled from another thread.
+    ///
+    /// Setting the `cancel` flag to `true` aborts the open if it is still in progress,
+    /// returning [crate::PlayerError::Cancelled], the same flag also aborts any reads
+    /// once the source is being played, see [crate::MediaPlayer::cancel_handle].
+    pub fn open_url_with_cancel(
+        url: url::Url,
+        cancel: Arc<AtomicBool>,
+    ) -> crate::Result<Self> {
+        Self::open_url_inner(url, &[], cancel)
+    }
+
+    fn open_url_inner(
+        url: url::Url,
+        options: &[(&str, &str)],
+        cancel: Arc<AtomicBool>,
+    ) -> crate::Result<Self> {
         let url_cstr = CString::from_str(url.as_str()).expect(
             "provided URL should never reasonably contain a null terminator mid string",
         );
 
-        let mut ctx = ptr::null_mut();
+        let mut options = Options::new(options)?;
+
+        let mut ctx = alloc_format_context(&cancel)?;
         let result = unsafe {
             ffmpeg::avformat_open_input(
                 &raw mut ctx,
                 url_cstr.as_ptr(),
                 ptr::null_mut(),
-                ptr::null_mut(),
+                options.as_mut_ptr(),
             )
         };
-        error::convert_ff_result(result)?;
+        error::convert_ff_result(result)
+            .map_err(|err| err.with_context(format!("opening input {url}")))?;
 
         tracing::debug!(url = url.as_str(), "successfully opened input");
 
         if let Some(ctx) = ptr::NonNull::new(ctx) {
-            let mut source = Self { url, ctx };
+            let mut source = Self {
+                url,
+                ctx,
+                io: None,
+                cancel,
+                bitrate_estimates: HashMap::new(),
+                gop_estimates: HashMap::new(),
+                is_at_start: true,
+            };
+
+            let unused = options.keys();
+            if !unused.is_empty() {
+                let err = error::FFmpegError::custom(format!(
+                    "options were not recognised by FFmpeg: {}",
+                    unused.join(", "),
+                ));
+                return Err(err.into());
+            }
+
+            source.init_source()?;
+            Ok(source)
+        } else {
+            panic!(
+                "ffmpeg::avformat_open_input returned null after returning a successful result code"
+            );
+        }
+    }
+
+    /// Create a new [InputSource] from media held in memory.
+    ///
+    /// The buffer is owned by the source and kept alive for its lifetime,
+    /// [InputSource::url] returns `memory:` for sources created this way.
+    pub fn open_from_memory(data: Vec<u8>) -> crate::Result<Self> {
+        let io = CustomIo::from_memory(data)?;
+        let url = "memory:".parse().expect("url parses should not fail");
+        Self::open_custom_io(url, io, None, &[])
+    }
+
+    /// Create a new [InputSource] which reads media from the given reader.
+    ///
+    /// This allows streaming from things like network socket wrappers, encrypted
+    /// files or archive entries, [InputSource::url] returns `reader:` for sources
+    /// created this way.
+    ///
+    /// If the reader is unable to report its position via [Seek], it is treated as
+    /// non-seekable and only formats which can be read linearly will open.
+    ///
+    /// Reads failing with [std::io::ErrorKind::TimedOut] or [std::io::ErrorKind::WouldBlock],
+    /// i.e. from a socket with a read timeout, are returned as
+    /// [crate::PlayerError::NetworkTimeout] and can be retried.
+    pub fn open_reader<R>(reader: R) -> crate::Result<Self>
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let io = CustomIo::from_reader(reader)?;
+        let url = "reader:".parse().expect("url parses should not fail");
+        Self::open_custom_io(url, io, None, &[])
+    }
+
+    /// Create a single [InputSource] which plays each of the `sources` one after
+    /// another as one timeline.
+    ///
+    /// This uses FFmpeg's `concat` demuxer, packets are transparently read from the
+    /// next source once the previous one ends and their timestamps are offset by the
+    /// total duration of the sources before them. [InputSource::url] returns `concat:`
+    /// for sources created this way.
+    ///
+    /// Every source must have been opened from a URL and have the same streams, with
+    /// matching media types and codecs, otherwise an error describing the mismatch
+    /// is returned.
+    pub fn concat(sources: Vec<InputSource>) -> crate::Result<Self> {
+        let Some(first) = sources.first() else {
+            let err = error::FFmpegError::custom("at least one source must be provided");
+            return Err(err.into());
+        };
+
+        let mut script = String::from("ffconcat version 1.0\n");
+        for (index, source) in sources.iter().enumerate() {
+            if source.io.is_some() {
+                let err = error::FFmpegError::custom(format!(
+                    "source {index} ({}) was not opened from a URL",
+                    source.url,
+                ));
+                return Err(err.into());
+            }
+
+            check_matching_streams(first, source, index)?;
+
+            let url = source.url.as_str().replace('\'', r"'\''");
+            script.push_str(&format!("file '{url}'\n"));
+            script.push_str(&format!(
+                "duration {:.6}\n",
+                source.duration().as_secs_f64()
+            ));
+        }
+        tracing::debug!(script = script, "created concat script");
+
+        let io = CustomIo::from_memory(script.into_bytes())?;
+        let url = "concat:".parse().expect("url parses should not fail");
+        // The URLs are absolute so the demuxer must allow them.
+        Self::open_custom_io(url, io, Some(c"concat"), &[("safe", "0")])
+    }
+
+    fn open_custom_io(
+        url: url::Url,
+        io: CustomIo,
+        format: Option<&std::ffi::CStr>,
+        options: &[(&str, &str)],
+    ) -> crate::Result<Self> {
+        let cancel = Arc::default();
+        let mut options = Options::new(options)?;
+
+        let input_format = match format {
+            Some(name) => {
+                let input_format =
+                    unsafe { ffmpeg::av_find_input_format(name.as_ptr()) };
+                if input_format.is_null() {
+                    let err = error::FFmpegError::custom(format!(
+                        "input format {name:?} is not available",
+                    ));
+                    return Err(err.into());
+                }
+                input_format
+            },
+            None => ptr::null(),
+        };
+
+        let mut ctx = alloc_format_context(&cancel)?;
+
+        // If opening fails FFmpeg frees the format context for us,
+        // but the IO context is still ours to free when `io` is dropped.
+        let result = unsafe {
+            (*ctx).pb = io.as_ptr();
+            ffmpeg::avformat_open_input(
+                &raw mut ctx,
+                ptr::null(),
+                input_format,
+                options.as_mut_ptr(),
+            )
+        };
+        error::convert_ff_result(result)?;
+
+        tracing::debug!(url = url.as_str(), "successfully opened custom input");
+
+        if let Some(ctx) = ptr::NonNull::new(ctx) {
+            let mut source = Self {
+                url,
+                ctx,
+                io: Some(io),
+                cancel,
+                bitrate_estimates: HashMap::new(),
+                gop_estimates: HashMap::new(),
+                is_at_start: true,
+            };
             source.init_source()?;
             Ok(source)
         } else {
@@ -78,6 +365,12 @@ impl InputSource {
         }
     }
 
+    #[inline]
+    /// Returns the flag which aborts any in-flight FFmpeg IO when set.
+    pub(crate) fn cancel_handle(&self) -> Arc<AtomicBool> {
+        self.cancel.clone()
+    }
+
     fn init_source(&mut self) -> crate::Result<()> {
         let result = unsafe {
             ffmpeg::avformat_find_stream_info(self.ctx.as_ptr(), ptr::null_mut())
@@ -95,7 +388,7 @@ impl InputSource {
         unsafe { &mut *(self.ctx.as_ptr()) }
     }
 
-    fn streams(&self) -> &[*mut ffmpeg::AVStream] {
+    pub(crate) fn streams(&self) -> &[*mut ffmpeg::AVStream] {
         let ctx = self.as_ctx();
         unsafe { std::slice::from_raw_parts(ctx.streams, self.num_streams()) }
     }
@@ -116,23 +409,147 @@ impl InputSource {
         &self.url
     }
 
+    /// Returns the short name of the container format, i.e. `matroska,webm`.
+    ///
+    /// Some demuxers handle several formats, in which case this is the
+    /// comma separated list of names FFmpeg uses for the demuxer.
+    pub fn format_name(&self) -> &str {
+        let ctx = self.as_ctx();
+        if ctx.iformat.is_null() {
+            return "unknown";
+        }
+
+        let name = unsafe { std::ffi::CStr::from_ptr((*ctx.iformat).name) };
+        name.to_str().unwrap_or("unknown")
+    }
+
+    /// Returns the descriptive name of the container format,
+    /// i.e. `QuickTime / MOV`.
+    ///
+    /// Returns `None` if FFmpeg does not provide a descriptive name.
+    pub fn format_long_name(&self) -> Option<String> {
+        let ctx = self.as_ctx();
+        if ctx.iformat.is_null() {
+            return None;
+        }
+
+        let long_name = unsafe { (*ctx.iformat).long_name };
+        if long_name.is_null() {
+            return None;
+        }
+
+        let long_name = unsafe { std::ffi::CStr::from_ptr(long_name) };
+        Some(long_name.to_string_lossy().into_owned())
+    }
+
+    /// Returns the metadata tags of the container, i.e. `title` or `encoder`.
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        let ctx = self.as_ctx();
+        unsafe { crate::get_all_metadata(ctx.metadata) }
+    }
+
+    /// Returns everything known about the source without opening any decoders.
+    ///
+    /// This is intended for scanning media libraries, where a single call per
+    /// file is preferable to querying each getter individually.
+    pub fn probe(&self) -> MediaInfo {
+        MediaInfo {
+            url: self.url.to_string(),
+            format_name: self.format_name().to_string(),
+            format_long_name: self.format_long_name(),
+            duration: self.duration(),
+            metadata: self.metadata(),
+            streams: self.iter_streams().collect(),
+            chapters: self.chapters(),
+            programs: self.programs(),
+        }
+    }
+
     /// Returns the number of streams available from the source.
     pub fn num_streams(&self) -> usize {
         let ctx = self.as_ctx();
         ctx.nb_streams as usize
     }
 
+    /// Returns the chapter markers within the source.
+    ///
+    /// Returns an empty vec if the container has no chapters.
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let ctx = self.as_ctx();
+        if ctx.chapters.is_null() {
+            return Vec::new();
+        }
+
+        let chapters = unsafe {
+            std::slice::from_raw_parts(ctx.chapters, ctx.nb_chapters as usize)
+        };
+        chapters
+            .iter()
+            .map(|chapter| unsafe { Chapter::from_raw(*chapter) })
+            .collect()
+    }
+
+    /// Returns the programs within the source, i.e. the variants of an HLS master
+    /// playlist or the services of an MPEG-TS broadcast.
+    ///
+    /// Returns an empty vec if the container has no programs, which is the case
+    /// for most local files.
+    pub fn programs(&self) -> Vec<ProgramInfo> {
+        let ctx = self.as_ctx();
+        if ctx.programs.is_null() {
+            return Vec::new();
+        }
+
+        let programs = unsafe {
+            std::slice::from_raw_parts(ctx.programs, ctx.nb_programs as usize)
+        };
+        programs
+            .iter()
+            .map(|program| unsafe { ProgramInfo::from_raw(*program) })
+            .collect()
+    }
+
+    /// Returns any pictures embedded within the source, typically album cover art.
+    ///
+    /// These are exposed by the container as video streams with the
+    /// `ATTACHED_PIC` disposition and do not need to be decoded to be displayed
+    /// if the application can handle the encoded image directly.
+    pub fn attached_pictures(&self) -> Vec<AttachedPicture> {
+        self.streams()
+            .iter()
+            .filter_map(|stream| unsafe { AttachedPicture::from_raw(*stream) })
+            .collect()
+    }
+
+    /// Returns the files attached to the source, typically the fonts used to
+    /// render ASS subtitles within MKV files.
+    ///
+    /// Only streams with the [MediaType::Attachment] type are included.
+    pub fn attachments(&self) -> Vec<Attachment> {
+        self.streams()
+            .iter()
+            .filter_map(|stream| unsafe { Attachment::from_raw(*stream) })
+            .collect()
+    }
+
     /// Iterate over all available audio, video and subtitle streams in the source.
+    ///
+    /// See [InputSource::iter_all_streams] to include data and attachment streams.
     pub fn iter_streams(&self) -> impl Iterator<Item = StreamInfo> {
+        self.iter_all_streams().filter(|stream| {
+            matches!(
+                stream.media_type,
+                MediaType::Video | MediaType::Audio | MediaType::Subtitle
+            )
+        })
+    }
+
+    /// Iterate over every stream in the source, including data streams like
+    /// timecodes or telemetry and attachments like the fonts used by ASS subtitles.
+    pub fn iter_all_streams(&self) -> impl Iterator<Item = StreamInfo> {
         self.streams()
             .iter()
             .map(|v| unsafe { StreamInfo::from_raw(*v) })
-            .filter(|stream| {
-                matches!(
-                    stream.media_type,
-                    MediaType::Video | MediaType::Audio | MediaType::Subtitle
-                )
-            })
     }
 
     /// Attempts to return the stream info at the given index.
@@ -180,10 +597,73 @@ impl InputSource {
         Ok(Some(stream))
     }
 
+    /// Like [InputSource::find_best_stream], but if `program_streams` are
+    /// provided only the streams of that program are considered.
+    ///
+    /// FFmpeg can restrict its search to the program of a related stream, but it
+    /// uses the first program containing that stream, which breaks for HLS
+    /// variants sharing the same audio rendition, so the program's streams are
+    /// ranked here instead, preferring default streams which are not aimed at
+    /// impaired viewers, then the highest bitrate.
+    pub(crate) fn find_best_program_stream(
+        &self,
+        media_type: MediaType,
+        preferred_stream_index: Option<usize>,
+        program_streams: Option<&[usize]>,
+    ) -> crate::Result<Option<StreamInfo>> {
+        let program_streams = match program_streams {
+            Some(indices) if preferred_stream_index.is_none() => indices,
+            _ => return self.find_best_stream(media_type, preferred_stream_index),
+        };
+
+        let streams = self.streams();
+        let av_media_type = media_type.to_av_media_type();
+        let best = program_streams
+            .iter()
+            .copied()
+            .filter(|&index| index < streams.len())
+            .filter_map(|index| {
+                let stream = unsafe { &*streams[index] };
+                let params = unsafe { &*stream.codecpar };
+                if params.codec_type != av_media_type {
+                    return None;
+                }
+
+                let has_decoder =
+                    unsafe { !ffmpeg::avcodec_find_decoder(params.codec_id).is_null() };
+                let is_attached_pic =
+                    stream.disposition & ffmpeg::AV_DISPOSITION_ATTACHED_PIC as i32 != 0;
+                let is_unconfigured_audio = media_type == MediaType::Audio
+                    && (params.ch_layout.nb_channels == 0 || params.sample_rate == 0);
+                if !has_decoder || is_attached_pic || is_unconfigured_audio {
+                    return None;
+                }
+
+                let impaired = (ffmpeg::AV_DISPOSITION_HEARING_IMPAIRED
+                    | ffmpeg::AV_DISPOSITION_VISUAL_IMPAIRED)
+                    as i32;
+                let score = u8::from(stream.disposition & impaired == 0)
+                    + u8::from(
+                        stream.disposition & ffmpeg::AV_DISPOSITION_DEFAULT as i32 != 0,
+                    );
+                Some((index, (score, params.bit_rate)))
+            })
+            // Ties go to the first stream, matching `av_find_best_stream`.
+            .max_by_key(|&(index, rank)| (rank, std::cmp::Reverse(index)));
+
+        Ok(best.map(|(index, _)| unsafe { StreamInfo::from_raw(streams[index]) }))
+    }
+
     /// Open a target audio stream index for decoding.
+    ///
+    /// If a `target_format` is provided the decoded audio will be resampled
+    /// into that format, and if a `downmix` layout is provided the audio will be
+    /// remixed into that layout.
     pub(crate) fn open_audio_stream(
         &self,
         index: usize,
+        target_format: Option<AudioFormat>,
+        downmix: Option<ChannelLayout>,
     ) -> Result<AudioDecoder, error::FFmpegError> {
         let stream_info = self.stream(index);
         let streams = self.streams();
@@ -191,7 +671,14 @@ impl InputSource {
         let stream = unsafe { &*streams[index] };
         let parameters = unsafe { stream.codecpar.as_ref() };
 
-        AudioDecoder::open(stream_info.codec(), stream_info, parameters)
+        AudioDecoder::open(
+            stream_info.codec(),
+            stream_info,
+            parameters,
+            target_format,
+            downmix,
+        )
+        .map_err(|err| err.with_context(format!("opening audio stream {index}")))
     }
 
     /// Open a target subtitle stream index for decoding.
@@ -206,6 +693,7 @@ impl InputSource {
         let parameters = unsafe { stream.codecpar.as_ref() };
 
         SubtitleDecoder::open(stream_info.codec(), stream_info, parameters)
+            .map_err(|err| err.with_context(format!("opening subtitle stream {index}")))
     }
 
     /// A specialised variant of `open_stream` for video decoding using
@@ -215,6 +703,8 @@ impl InputSource {
         index: usize,
         accelerator_config: &AcceleratorConfig,
         target_pixel_formats: Vec<OutputPixelFormat>,
+        filter_options: VideoFilterOptions,
+        threads: ThreadCount,
     ) -> Result<VideoDecoder, error::FFmpegError> {
         let stream_info = self.stream(index);
         let streams = self.streams();
@@ -227,8 +717,11 @@ impl InputSource {
             stream_info,
             parameters,
             target_pixel_formats,
+            filter_options,
             accelerator_config,
+            threads,
         )
+        .map_err(|err| err.with_context(format!("opening video stream {index}")))
     }
 
     /// Keep any streams which match the provided predicate and discard the rest.
@@ -242,73 +735,1785 @@ impl InputSource {
         for stream in self.streams_mut() {
             let stream = unsafe { &mut **stream };
             let info = unsafe { StreamInfo::from_raw(stream) };
+            set_stream_discard(stream, predicate(&info));
+        }
+    }
 
-            if !predicate(&info) {
-                stream.discard = ffmpeg::AVDISCARD_ALL;
-            }
+    /// Estimate the bitrate of the stream at the given index in kilobits per second.
+    ///
+    /// This is intended for containers like MKV which do not store the bitrate
+    /// of each stream, see [StreamInfo::bitrate]. Packets from the start of the
+    /// source are read until ten seconds of the stream is covered and their sizes
+    /// are summed over the duration they cover.
+    ///
+    /// The source is rewound to the start afterwards, so this must be called
+    /// before reading any packets. The estimate is cached for each stream so
+    /// repeated calls are cheap.
+    ///
+    /// Returns `None` if the stream does not exist, is disabled or no packets
+    /// with timestamps could be read. `None` is also returned without reading
+    /// anything if packets have already been read, as the read position could
+    /// not be restored afterwards.
+    pub fn estimate_bitrate(&mut self, stream_index: usize) -> Option<usize> {
+        if stream_index >= self.num_streams() {
+            return None;
+        }
+
+        if let Some(estimate) = self.bitrate_estimates.get(&stream_index) {
+            return *estimate;
+        }
+
+        if !self.is_at_start {
+            tracing::warn!("cannot estimate stream bitrate after reading packets");
+            return None;
+        }
+
+        let result = self.probe_bitrate(stream_index);
+
+        if let Err(err) = self.rewind() {
+            tracing::warn!(error = %err, "failed to rewind source after probing bitrate");
+        }
+
+        match result {
+            Ok(estimate) => {
+                self.bitrate_estimates.insert(stream_index, estimate);
+                estimate
+            },
+            Err(err) => {
+                // The failure may be transient, so the next call probes again.
+                tracing::warn!(error = %err, "failed to estimate stream bitrate");
+                None
+            },
         }
     }
 
-    pub(crate) fn seek(&mut self, position: Duration) -> Result<(), error::FFmpegError> {
-        let pos_ts = (position.as_secs_f32() * ffmpeg::AV_TIME_BASE as f32) as i64;
-        let pos_min_ts = pos_ts + (5 * ffmpeg::AV_TIME_BASE as i64);
-        let pos_max_ts = pos_ts + (5 * ffmpeg::AV_TIME_BASE as i64);
-        let result = unsafe {
-            ffmpeg::avformat_seek_file(
-                self.ctx.as_ptr(),
-                -1,
-                pos_min_ts,
-                pos_ts,
-                pos_max_ts,
-                0,
-            )
+    fn probe_bitrate(
+        &mut self,
+        stream_index: usize,
+    ) -> Result<Option<usize>, error::FFmpegError> {
+        let time_base = unsafe { (*self.streams()[stream_index]).time_base };
+
+        let mut total_bytes = 0;
+        let mut first_ts = None;
+        let mut end_ts = 0;
+        let mut packet = MediaPacket::new()?;
+        for _ in 0..BITRATE_PROBE_MAX_PACKETS {
+            packet.reset();
+            match self.read_packet(&mut packet) {
+                Ok(()) => {},
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err),
+            }
+
+            if packet.stream_index as usize != stream_index {
+                continue;
+            }
+
+            let ts = if packet.pts == ffmpeg::AV_NOPTS_VALUE {
+                packet.dts
+            } else {
+                packet.pts
+            };
+            if ts == ffmpeg::AV_NOPTS_VALUE {
+                continue;
+            }
+
+            total_bytes += packet.size.max(0) as usize;
+            let start_ts = *first_ts.get_or_insert(ts);
+            end_ts = end_ts.max(ts + packet.duration.max(0));
+
+            if pts_to_duration(end_ts - start_ts, time_base) >= BITRATE_PROBE_DURATION {
+                break;
+            }
+        }
+
+        let Some(first_ts) = first_ts else {
+            return Ok(None);
         };
-        error::convert_ff_result(result)?;
-        Ok(())
-    }
 
-    pub(crate) fn play(&mut self) -> Result<(), error::FFmpegError> {
-        let result = unsafe { ffmpeg::av_read_play(self.ctx.as_ptr()) };
-        error::convert_ff_result(result)?;
-        Ok(())
-    }
+        let covered = pts_to_duration(end_ts - first_ts, time_base);
+        if covered.is_zero() {
+            return Ok(None);
+        }
 
-    pub(crate) fn pause(&mut self) -> Result<(), error::FFmpegError> {
-        let result = unsafe { ffmpeg::av_read_pause(self.ctx.as_ptr()) };
-        error::convert_ff_result(result)?;
-        Ok(())
+        let kbps = (total_bytes * 8) as f64 / covered.as_secs_f64() / 1000.0;
+        Ok(Some(kbps.round() as usize))
     }
 
-    pub(crate) fn read_packet(
+    /// Estimate the GOP length of the stream at the given index, the typical
+    /// number of frames from one keyframe to the next.
+    ///
+    /// Packets from the start of the source are read until `sample_count` complete
+    /// GOPs of the stream are seen, returning the median length. Long GOPs make
+    /// exact seeks expensive, as every frame from the preceding keyframe up to the
+    /// target must be decoded, see [SeekMode::Exact].
+    ///
+    /// The source is rewound to the start afterwards, so this must be called
+    /// before reading any packets. Successful estimates are cached for each stream
+    /// so repeated calls are cheap, regardless of the `sample_count`.
+    ///
+    /// Returns `None` if the stream does not exist or fewer than two keyframes
+    /// could be read, i.e. the stream only has a single keyframe. `None` is also
+    /// returned without reading anything if packets have already been read or the
+    /// source is not seekable, as the read position could not be restored afterwards.
+    pub fn estimate_gop(
         &mut self,
-        packet: &mut ffmpeg::AVPacket,
-    ) -> Result<(), error::FFmpegError> {
-        let result = unsafe { ffmpeg::av_read_frame(self.ctx.as_ptr(), packet) };
-        error::convert_ff_result(result)?;
-        Ok(())
+        stream_index: usize,
+        sample_count: usize,
+    ) -> Option<usize> {
+        assert!(sample_count > 0, "sample count must be greater than zero");
+        if stream_index >= self.num_streams() {
+            return None;
+        }
+
+        if let Some(estimate) = self.gop_estimates.get(&stream_index) {
+            return *estimate;
+        }
+
+        if !self.is_at_start || !self.is_seekable() {
+            tracing::warn!(
+                "cannot estimate stream GOP length without rewinding the source"
+            );
+            return None;
+        }
+
+        let result = self.probe_gop(stream_index, sample_count);
+
+        if let Err(err) = self.rewind() {
+            tracing::warn!(error = %err, "failed to rewind source after probing GOP");
+        }
+
+        match result {
+            Ok(estimate) => {
+                self.gop_estimates.insert(stream_index, estimate);
+                estimate
+            },
+            Err(err) => {
+                // The failure may be transient, so the next call probes again.
+                tracing::warn!(error = %err, "failed to estimate stream GOP length");
+                None
+            },
+        }
     }
-}
 
-// SAFETY: We are allowed to call `avformat_free_context` from a different thread to
-//         where we called `avformat_open_input`.
-unsafe impl Send for InputSource {}
+    fn probe_gop(
+        &mut self,
+        stream_index: usize,
+        sample_count: usize,
+    ) -> Result<Option<usize>, error::FFmpegError> {
+        let mut lengths = Vec::with_capacity(sample_count);
+        // The number of frames since the last keyframe, `None` until the first one.
+        let mut current = None;
+        let mut packet = MediaPacket::new()?;
+        for _ in 0..GOP_PROBE_MAX_PACKETS {
+            packet.reset();
+            match self.read_packet(&mut packet) {
+                Ok(()) => {},
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err),
+            }
 
-impl Drop for InputSource {
-    fn drop(&mut self) {
-        unsafe { ffmpeg::avformat_free_context(self.ctx.as_ptr()) }
+            if packet.stream_index as usize != stream_index {
+                continue;
+            }
+
+            let is_keyframe = packet.flags & ffmpeg::AV_PKT_FLAG_KEY as i32 != 0;
+            match current {
+                Some(length) if is_keyframe => {
+                    lengths.push(length);
+                    if lengths.len() == sample_count {
+                        break;
+                    }
+                    current = Some(1);
+                },
+                Some(length) => current = Some(length + 1),
+                None if is_keyframe => current = Some(1),
+                None => {},
+            }
+        }
+
+        if lengths.is_empty() {
+            return Ok(None);
+        }
+
+        lengths.sort_unstable();
+        Ok(Some(lengths[lengths.len() / 2]))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::stream::{Fraction, Resolution};
+    /// Generate a waveform overview of the best audio stream, as `buckets`
+    /// `(min, max)` sample pairs evenly spaced across the whole source.
+    ///
+    /// The audio is decoded from start to finish, each pair covers the lowest
+    /// and highest sample across all channels within its slice of the audio.
+    /// Samples are in the range `-1.0..=1.0`, although decoders producing floats
+    /// can exceed full scale. If the audio is shorter than `buckets` blocks of
+    /// 256 samples, neighbouring buckets repeat the same pair.
+    ///
+    /// This is a heavy operation that consumes the whole source, it is rewound
+    /// to the start both before and after decoding, so any packets already read
+    /// do not affect the result.
+    ///
+    /// Returns [error::PlayerError::NoAvailableStreams] if there is no audio stream,
+    /// or [error::PlayerError::NotSeekable] if the source cannot be rewound.
+    pub fn generate_waveform(
+        &mut self,
+        buckets: usize,
+    ) -> crate::Result<Vec<(f32, f32)>> {
+        assert!(buckets > 0, "buckets must be greater than zero");
 
-    #[test]
-    fn test_direct_file_open() {
-        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        let Some(stream) = self.find_best_stream(MediaType::Audio, None)? else {
+            return Err(error::PlayerError::NoAvailableStreams);
+        };
+        if !self.is_seekable() {
+            return Err(error::PlayerError::NotSeekable);
+        }
+        self.rewind()?;
+
+        // Only read the packets of the audio stream, restoring the existing
+        // discard state afterwards.
+        let discard: Vec<_> = self
+            .streams()
+            .iter()
+            .map(|stream| unsafe { (**stream).discard })
+            .collect();
+        self.keep_streams(|info| info.index == stream.index);
+
+        let result = self.decode_waveform(&stream);
+
+        for (stream, discard) in self.streams_mut().iter().zip(discard) {
+            unsafe { (**stream).discard = discard };
+        }
+        self.rewind()?;
+
+        let blocks = result?;
+        Ok(reduce_waveform_blocks(&blocks, buckets))
+    }
+
+    /// Build an index of the keyframes within the stream at the given index,
+    /// which [crate::MediaPlayer::seek_to_keyframe] uses to seek straight to them.
+    ///
+    /// Packets are read from the start to the end of the source once, only keyframes
+    /// with a known PTS and byte position are recorded. The source is rewound to the
+    /// start afterwards, so this should be called before reading any packets.
+    ///
+    /// Returns [error::PlayerError::NotSeekable] if the source cannot be rewound.
+    pub fn build_keyframe_index(
+        &mut self,
+        stream_index: usize,
+    ) -> crate::Result<KeyframeIndex> {
+        assert!(
+            stream_index < self.num_streams(),
+            "stream index out of bounds"
+        );
+        if !self.is_seekable() {
+            return Err(error::PlayerError::NotSeekable);
+        }
+
+        // Only read the packets of the stream, restoring the existing
+        // discard state afterwards.
+        let discard: Vec<_> = self
+            .streams()
+            .iter()
+            .map(|stream| unsafe { (**stream).discard })
+            .collect();
+        self.keep_streams(|info| info.index == stream_index);
+
+        let result = self.scan_keyframes(stream_index);
+
+        for (stream, discard) in self.streams_mut().iter().zip(discard) {
+            unsafe { (**stream).discard = discard };
+        }
+        self.rewind()?;
+
+        Ok(KeyframeIndex::new(stream_index, result?))
+    }
+
+    fn scan_keyframes(
+        &mut self,
+        stream_index: usize,
+    ) -> Result<Vec<Keyframe>, error::FFmpegError> {
+        let time_base = unsafe { (*self.streams()[stream_index]).time_base };
+
+        let mut keyframes = Vec::new();
+        let mut packet = MediaPacket::new()?;
+        loop {
+            packet.reset();
+            match self.read_packet(&mut packet) {
+                Ok(()) => {},
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err),
+            }
+
+            let is_keyframe = packet.flags & ffmpeg::AV_PKT_FLAG_KEY as i32 != 0;
+            if packet.stream_index as usize != stream_index
+                || !is_keyframe
+                || packet.pts == ffmpeg::AV_NOPTS_VALUE
+                || packet.pos < 0
+            {
+                continue;
+            }
+
+            keyframes.push(Keyframe {
+                pts: pts_to_duration(packet.pts, time_base),
+                byte_pos: packet.pos as u64,
+            });
+        }
+
+        Ok(keyframes)
+    }
+
+    /// Decode the audio stream, reducing the samples into min/max pairs
+    /// of [WAVEFORM_BLOCK_SIZE] samples.
+    fn decode_waveform(
+        &mut self,
+        stream: &StreamInfo,
+    ) -> Result<Vec<(f32, f32)>, error::FFmpegError> {
+        let (Some(sample_rate), Some(layout)) =
+            (stream.sample_rate, stream.channel_layout)
+        else {
+            return Err(error::FFmpegError::custom(
+                "audio stream has an unknown format",
+            ));
+        };
+
+        // Resample into packed floats while keeping the layout, so the samples
+        // can be read directly without any remixing.
+        let target = AudioFormat {
+            sample_format: SampleFormat::FLT,
+            sample_rate,
+            channels: layout.num_channels() as u32,
+        };
+        let mut decoder =
+            self.open_audio_stream(stream.index, Some(target), Some(layout))?;
+
+        let mut packet = MediaPacket::new()?;
+        let mut frame = MediaRawFrame::new()?;
+        let mut blocks = Vec::new();
+        let mut block = WAVEFORM_EMPTY_PEAK;
+        let mut block_len = 0;
+        loop {
+            match decoder.decode(&mut frame) {
+                Ok(()) => {},
+                Err(err) if err.is_eof() => break,
+                Err(err) if err.needs_data() => {
+                    packet.reset();
+                    match self.read_packet(&mut packet) {
+                        Ok(()) => {},
+                        Err(err) if err.is_eof() => {
+                            decoder.flush()?;
+                            continue;
+                        },
+                        Err(err) => return Err(err),
+                    }
+
+                    if packet.stream_index as usize == stream.index {
+                        decoder.write_packet(&mut packet)?;
+                    }
+                    continue;
+                },
+                Err(err) => return Err(err),
+            }
+
+            let channels = frame.ch_layout.nb_channels.max(1) as usize;
+            let samples = unsafe {
+                std::slice::from_raw_parts(
+                    frame.data[0].cast::<f32>(),
+                    frame.nb_samples.max(0) as usize * channels,
+                )
+            };
+            for sample in samples.chunks_exact(channels) {
+                for &value in sample {
+                    block = (block.0.min(value), block.1.max(value));
+                }
+
+                block_len += 1;
+                if block_len == WAVEFORM_BLOCK_SIZE {
+                    blocks.push(block);
+                    block = WAVEFORM_EMPTY_PEAK;
+                    block_len = 0;
+                }
+            }
+        }
+
+        if block_len > 0 {
+            blocks.push(block);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Enable or disable reading packets from the stream at the given index.
+    ///
+    /// Packets from disabled streams are skipped by the demuxer and never returned
+    /// by [InputSource::read_raw_packet], so callers only pay for the streams they
+    /// need. If every stream is disabled, reading behaves as if the end of the
+    /// source has been reached.
+    ///
+    /// All streams are enabled when the source is opened. Note that
+    /// [crate::MediaPlayerBuilder::build] disables any streams the player
+    /// does not decode.
+    ///
+    /// Returns [error::PlayerError::InvalidStream] if there is no stream at the index.
+    pub fn set_stream_enabled(
+        &mut self,
+        index: usize,
+        enabled: bool,
+    ) -> crate::Result<()> {
+        if index >= self.num_streams() {
+            return Err(error::PlayerError::InvalidStream {
+                index,
+                expected: MediaType::Unknown,
+            });
+        }
+
+        let stream = unsafe { &mut *self.streams_mut()[index] };
+        set_stream_discard(stream, enabled);
+        Ok(())
+    }
+
+    pub(crate) fn seek(
+        &mut self,
+        position: Duration,
+        mode: SeekMode,
+    ) -> Result<(), error::FFmpegError> {
+        let pos_ts = (position.as_secs_f64() * ffmpeg::AV_TIME_BASE as f64) as i64;
+        let (pos_min_ts, pos_max_ts) = match mode {
+            SeekMode::Backward | SeekMode::Exact => (i64::MIN, pos_ts),
+            SeekMode::Forward => (pos_ts, i64::MAX),
+            SeekMode::Any => (i64::MIN, i64::MAX),
+        };
+        let result = unsafe {
+            ffmpeg::avformat_seek_file(
+                self.ctx.as_ptr(),
+                -1,
+                pos_min_ts,
+                pos_ts,
+                pos_max_ts,
+                mode.to_av_seek_flags(),
+            )
+        };
+        self.is_at_start = false;
+        error::convert_ff_result(result)
+            .map_err(|err| err.with_context(format!("seeking to {position:?}")))?;
+        Ok(())
+    }
+
+    /// Returns if the source supports seeking.
+    ///
+    /// Sources read from a pipe, a reader which cannot [Seek], or a live stream
+    /// are not seekable, UIs should disable any controls for seeking.
+    pub fn is_seekable(&self) -> bool {
+        let ctx = self.as_ctx();
+        if ctx.pb.is_null() {
+            // Formats with `AVFMT_NOFILE`, i.e. RTSP, do their own IO and can only
+            // seek if they know the duration of the stream, which live streams do not.
+            return ctx.duration != ffmpeg::AV_NOPTS_VALUE && ctx.duration > 0;
+        }
+
+        let seekable = unsafe { (*ctx.pb).seekable };
+        seekable & ffmpeg::AVIO_SEEKABLE_NORMAL as i32 != 0
+    }
+
+    /// Rewind the source back to the beginning, reusing the already open
+    /// context rather than opening and probing the source again.
+    ///
+    /// This also clears the end of file state, so [InputSource::read_raw_packet]
+    /// returns packets again after reaching the end of the source.
+    pub fn rewind(&mut self) -> crate::Result<()> {
+        self.seek(Duration::ZERO, SeekMode::Backward)?;
+        self.clear_io_error();
+        self.is_at_start = true;
+
+        Ok(())
+    }
+
+    /// Clear the end of file and error state of the IO context.
+    ///
+    /// A failed read leaves these set, so any further reads fail straight away
+    /// until they are cleared or the IO context seeks.
+    pub(crate) fn clear_io_error(&mut self) {
+        let pb = self.as_mut_ctx().pb;
+        if !pb.is_null() {
+            unsafe {
+                (*pb).eof_reached = 0;
+                (*pb).error = 0;
+            }
+        }
+    }
+
+    /// Returns if the container format supports seeking to a byte offset.
+    ///
+    /// Formats with an index, such as MP4, only support seeking by timestamp.
+    pub(crate) fn supports_byte_seek(&self) -> bool {
+        let format = unsafe { &*self.as_ctx().iformat };
+        format.flags & ffmpeg::AVFMT_NO_BYTE_SEEK as i32 == 0
+    }
+
+    /// Seek to the given byte `offset` in the input.
+    ///
+    /// This is a fallback for formats which cannot seek by timestamp, the
+    /// decoders must be flushed afterward just like with [InputSource::seek].
+    pub(crate) fn seek_bytes(&mut self, offset: i64) -> Result<(), error::FFmpegError> {
+        let result = unsafe {
+            ffmpeg::av_seek_frame(
+                self.ctx.as_ptr(),
+                -1,
+                offset,
+                ffmpeg::AVSEEK_FLAG_BYTE as i32,
+            )
+        };
+        self.is_at_start = false;
+        error::convert_ff_result(result)
+            .map_err(|err| err.with_context(format!("seeking to byte {offset}")))?;
+        Ok(())
+    }
+
+    pub(crate) fn play(&mut self) -> Result<(), error::FFmpegError> {
+        let result = unsafe { ffmpeg::av_read_play(self.ctx.as_ptr()) };
+        error::convert_ff_result(result)?;
+        Ok(())
+    }
+
+    pub(crate) fn pause(&mut self) -> Result<(), error::FFmpegError> {
+        let result = unsafe { ffmpeg::av_read_pause(self.ctx.as_ptr()) };
+        error::convert_ff_result(result)?;
+        Ok(())
+    }
+
+    /// Read the next packet from the source without decoding it.
+    ///
+    /// Packets are returned in the order they are stored in the container,
+    /// interleaved across all streams which have not been discarded.
+    ///
+    /// Returns `None` once the end of the source is reached.
+    pub fn read_raw_packet(&mut self) -> crate::Result<Option<Packet>> {
+        let mut packet = MediaPacket::new()?;
+        match self.read_packet(&mut packet) {
+            Ok(()) => {},
+            Err(err) if err.is_eof() => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+
+        let stream = unsafe { &*self.streams()[packet.stream_index as usize] };
+        Ok(Some(Packet::new(packet, stream.time_base)))
+    }
+
+    /// Create a [BitstreamExtractor] applying the named FFmpeg bitstream filter
+    /// to the packets of the stream at the given index.
+    ///
+    /// This is typically used to extract an elementary stream, i.e. `h264_mp4toannexb`
+    /// or `hevc_mp4toannexb` converts H.264 or HEVC from MP4 into Annex-B.
+    /// The packets are fed in from [InputSource::read_raw_packet].
+    ///
+    /// Returns an error if the filter does not exist or does not support the
+    /// codec of the stream.
+    pub fn bitstream_extractor(
+        &self,
+        stream_index: usize,
+        filter: &str,
+    ) -> crate::Result<BitstreamExtractor> {
+        assert!(
+            stream_index < self.num_streams(),
+            "stream index out of bounds"
+        );
+
+        let stream = unsafe { &*self.streams()[stream_index] };
+        let extractor = BitstreamExtractor::new(filter, stream)?;
+        Ok(extractor)
+    }
+
+    /// Copy the streams at the given indices into a new file without re-encoding
+    /// them, i.e. to extract the audio or change the container.
+    ///
+    /// The container format is picked from the extension of `output_path` and
+    /// must support the codecs of the streams. Packets are copied from the current
+    /// read position until the end of the source, so this should typically be
+    /// called on a newly opened source. Streams disabled via
+    /// [InputSource::set_stream_enabled] produce no packets.
+    ///
+    /// Returns [error::PlayerError::NoAvailableStreams] if no streams are given.
+    /// If remuxing fails after the output file is created, the partially written
+    /// file is removed.
+    pub fn remux_to(
+        &mut self,
+        output_path: impl AsRef<Path>,
+        stream_indices: &[usize],
+    ) -> crate::Result<()> {
+        if stream_indices.is_empty() {
+            return Err(error::PlayerError::NoAvailableStreams);
+        }
+
+        let mut output = OutputContext::new(output_path.as_ref())?;
+
+        // Maps the index of each input stream to its output stream.
+        let mut stream_mapping = vec![None; self.num_streams()];
+        for &index in stream_indices {
+            assert!(index < self.num_streams(), "stream index out of bounds");
+            if stream_mapping[index].is_some() {
+                continue;
+            }
+
+            let stream = unsafe { &*self.streams()[index] };
+            stream_mapping[index] = Some(output.add_stream(stream)?);
+        }
+
+        let result = self.copy_packets_to(&mut output, &stream_mapping);
+        if result.is_err() && !output.pb.is_null() {
+            // The file must be closed before it can be removed on Windows.
+            drop(output);
+            if let Err(err) = std::fs::remove_file(output_path.as_ref()) {
+                tracing::warn!(error = %err, "failed to remove partial remux output");
+            }
+        }
+        result
+    }
+
+    /// Write the header, packets of the mapped streams and trailer to `output`.
+    fn copy_packets_to(
+        &mut self,
+        output: &mut OutputContext,
+        stream_mapping: &[Option<usize>],
+    ) -> crate::Result<()> {
+        output.write_header()?;
+
+        let mut packet = MediaPacket::new()?;
+        loop {
+            packet.reset();
+            match self.read_packet(&mut packet) {
+                Ok(()) => {},
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err.into()),
+            }
+
+            let index = packet.stream_index as usize;
+            let Some(output_index) = stream_mapping[index] else {
+                continue;
+            };
+
+            let time_base = unsafe { (*self.streams()[index]).time_base };
+            output.write_packet(&mut packet, time_base, output_index)?;
+        }
+
+        output.write_trailer()?;
+        Ok(())
+    }
+
+    pub(crate) fn read_packet(
+        &mut self,
+        packet: &mut ffmpeg::AVPacket,
+    ) -> Result<(), error::FFmpegError> {
+        let result = unsafe { ffmpeg::av_read_frame(self.ctx.as_ptr(), packet) };
+        self.is_at_start = false;
+        error::convert_ff_result(result)?;
+        Ok(())
+    }
+}
+
+// SAFETY: We are allowed to call `avformat_free_context` from a different thread to
+//         where we called `avformat_open_input`.
+unsafe impl Send for InputSource {}
+
+impl Drop for InputSource {
+    fn drop(&mut self) {
+        let mut ctx = self.ctx.as_ptr();
+        unsafe { ffmpeg::avformat_close_input(&raw mut ctx) };
+        // The custom IO context is only safe to free once the format context is closed.
+        drop(self.io.take());
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A summary of an [InputSource], see [InputSource::probe].
+pub struct MediaInfo {
+    /// The URL the source was opened from.
+    pub url: String,
+    /// The short name of the container format, see [InputSource::format_name].
+    pub format_name: String,
+    /// The descriptive name of the container format,
+    /// see [InputSource::format_long_name].
+    pub format_long_name: Option<String>,
+    /// The duration of the source.
+    pub duration: Duration,
+    /// The metadata tags of the container.
+    pub metadata: BTreeMap<String, String>,
+    /// The audio, video and subtitle streams within the source.
+    pub streams: Vec<StreamInfo>,
+    /// The chapter markers within the source.
+    pub chapters: Vec<Chapter>,
+    /// The programs within the source.
+    pub programs: Vec<ProgramInfo>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A program within an [InputSource], grouping the streams which are played together.
+///
+/// See [InputSource::programs].
+pub struct ProgramInfo {
+    /// The ID of the program, used to select it via
+    /// [MediaPlayerBuilder::with_program](crate::MediaPlayerBuilder::with_program).
+    pub id: i32,
+    /// The indices of the streams which belong to the program.
+    pub stream_indices: Vec<usize>,
+    /// The bitrate advertised for the program in bits per second,
+    /// provided for the variants of an HLS master playlist.
+    pub bitrate: Option<usize>,
+    /// The metadata tags of the program, i.e. `service_name` for MPEG-TS.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ProgramInfo {
+    /// Creates a new [ProgramInfo] using the given raw pointer.
+    unsafe fn from_raw(program: *const ffmpeg::AVProgram) -> Self {
+        assert!(!program.is_null());
+
+        let program = unsafe { &*program };
+        let stream_indices = if program.stream_index.is_null() {
+            Vec::new()
+        } else {
+            let indices = unsafe {
+                std::slice::from_raw_parts(
+                    program.stream_index,
+                    program.nb_stream_indexes as usize,
+                )
+            };
+            indices.iter().map(|&index| index as usize).collect()
+        };
+
+        Self {
+            id: program.id,
+            stream_indices,
+            bitrate: unsafe {
+                crate::get_metadata(program.metadata, c"variant_bitrate")
+            }
+            .and_then(|bitrate| bitrate.parse().ok()),
+            metadata: unsafe { crate::get_all_metadata(program.metadata) },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A chapter marker within an [InputSource].
+pub struct Chapter {
+    /// The position the chapter starts at.
+    pub start: Duration,
+    /// The position the chapter ends at.
+    pub end: Duration,
+    /// The title of the chapter if the container provides it.
+    pub title: Option<String>,
+}
+
+impl Chapter {
+    /// Creates a new [Chapter] using the given raw pointer.
+    unsafe fn from_raw(chapter: *const ffmpeg::AVChapter) -> Self {
+        assert!(!chapter.is_null());
+
+        let chapter = unsafe { &*chapter };
+        Self {
+            start: pts_to_duration(chapter.start, chapter.time_base),
+            end: pts_to_duration(chapter.end, chapter.time_base),
+            title: unsafe { crate::get_metadata(chapter.metadata, c"title") },
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq)]
+/// An encoded picture embedded within an [InputSource].
+pub struct AttachedPicture {
+    /// The index of the stream the picture belongs to.
+    pub stream_index: usize,
+    /// The name of the codec the picture is encoded with, i.e. `mjpeg` or `png`.
+    pub codec_name: String,
+    /// The raw encoded picture.
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Debug for AttachedPicture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachedPicture")
+            .field("stream_index", &self.stream_index)
+            .field("codec_name", &self.codec_name)
+            .field("data_len", &self.data.len())
+            .finish()
+    }
+}
+
+impl AttachedPicture {
+    /// Creates a new [AttachedPicture] from the given raw stream pointer.
+    ///
+    /// Returns `None` if the stream does not have the `ATTACHED_PIC` disposition.
+    unsafe fn from_raw(stream: *const ffmpeg::AVStream) -> Option<Self> {
+        assert!(!stream.is_null());
+
+        let stream = unsafe { &*stream };
+        if stream.disposition & ffmpeg::AV_DISPOSITION_ATTACHED_PIC as i32 == 0 {
+            return None;
+        }
+
+        let packet = &stream.attached_pic;
+        if packet.data.is_null() || packet.size <= 0 {
+            return None;
+        }
+        let data =
+            unsafe { std::slice::from_raw_parts(packet.data, packet.size as usize) };
+
+        let codec_id = unsafe { (*stream.codecpar).codec_id };
+        let codec_name =
+            unsafe { std::ffi::CStr::from_ptr(ffmpeg::avcodec_get_name(codec_id)) };
+
+        Some(Self {
+            stream_index: stream.index as usize,
+            codec_name: codec_name.to_string_lossy().into_owned(),
+            data: data.to_vec(),
+        })
+    }
+}
+
+#[derive(Clone, Eq, PartialEq)]
+/// A file attached to an [InputSource], see [InputSource::attachments].
+pub struct Attachment {
+    /// The name of the attached file.
+    ///
+    /// This is empty if the container does not provide one.
+    pub filename: String,
+    /// The MIME type of the attached file, i.e. `application/x-truetype-font`.
+    pub mime_type: Option<String>,
+    /// The raw file contents.
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Attachment")
+            .field("filename", &self.filename)
+            .field("mime_type", &self.mime_type)
+            .field("data_len", &self.data.len())
+            .finish()
+    }
+}
+
+impl Attachment {
+    /// Creates a new [Attachment] from the given raw stream pointer.
+    ///
+    /// Returns `None` if the stream is not an attachment stream.
+    unsafe fn from_raw(stream: *const ffmpeg::AVStream) -> Option<Self> {
+        assert!(!stream.is_null());
+
+        let stream = unsafe { &*stream };
+        let codec_params = unsafe { &*stream.codecpar };
+        if MediaType::from(codec_params.codec_type) != MediaType::Attachment {
+            return None;
+        }
+
+        // Attachments store the whole file as the codec extradata.
+        let data =
+            if codec_params.extradata.is_null() || codec_params.extradata_size <= 0 {
+                Vec::new()
+            } else {
+                unsafe {
+                    std::slice::from_raw_parts(
+                        codec_params.extradata,
+                        codec_params.extradata_size as usize,
+                    )
+                }
+                .to_vec()
+            };
+
+        let filename = unsafe { crate::get_metadata(stream.metadata, c"filename") };
+        let mime_type = unsafe { crate::get_metadata(stream.metadata, c"mimetype") };
+
+        Some(Self {
+            filename: filename.unwrap_or_default(),
+            mime_type,
+            data,
+        })
+    }
+}
+
+/// Set the discard flag of the stream so its packets are either kept or skipped.
+fn set_stream_discard(stream: &mut ffmpeg::AVStream, keep: bool) {
+    stream.discard = if keep {
+        ffmpeg::AVDISCARD_DEFAULT
+    } else {
+        ffmpeg::AVDISCARD_ALL
+    };
+}
+
+/// Reduce the min/max pairs of each block into `buckets` evenly sized pairs.
+///
+/// Silent audio, or audio without any samples, produces `(0.0, 0.0)` pairs.
+fn reduce_waveform_blocks(blocks: &[(f32, f32)], buckets: usize) -> Vec<(f32, f32)> {
+    if blocks.is_empty() {
+        return vec![(0.0, 0.0); buckets];
+    }
+
+    (0..buckets)
+        .map(|bucket| {
+            let start = bucket * blocks.len() / buckets;
+            let end = ((bucket + 1) * blocks.len() / buckets).max(start + 1);
+            blocks[start..end].iter().fold(
+                WAVEFORM_EMPTY_PEAK,
+                |(min, max), &(block_min, block_max)| {
+                    (min.min(block_min), max.max(block_max))
+                },
+            )
+        })
+        .collect()
+}
+
+/// Check the `source` has the same streams as the `first` source, as required
+/// to concatenate them.
+fn check_matching_streams(
+    first: &InputSource,
+    source: &InputSource,
+    index: usize,
+) -> Result<(), error::FFmpegError> {
+    if first.num_streams() != source.num_streams() {
+        return Err(error::FFmpegError::custom(format!(
+            "source {index} has {} streams but source 0 has {}",
+            source.num_streams(),
+            first.num_streams(),
+        )));
+    }
+
+    for (expected, stream) in first.iter_streams().zip(source.iter_streams()) {
+        if expected.media_type != stream.media_type
+            || expected.codec_name != stream.codec_name
+        {
+            return Err(error::FFmpegError::custom(format!(
+                "stream {} of source {index} is {:?} ({}) but source 0 has {:?} ({})",
+                stream.index,
+                stream.media_type,
+                stream.codec_name,
+                expected.media_type,
+                expected.codec_name,
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Allocate a new format context which aborts IO once `cancel` is set.
+///
+/// The `cancel` flag must outlive the returned context.
+fn alloc_format_context(
+    cancel: &Arc<AtomicBool>,
+) -> Result<*mut ffmpeg::AVFormatContext, error::FFmpegError> {
+    let ctx = unsafe { ffmpeg::avformat_alloc_context() };
+    if ctx.is_null() {
+        return Err(error::FFmpegError::custom(
+            "failed to allocate format context",
+        ));
+    }
+
+    unsafe {
+        (*ctx).interrupt_callback = ffmpeg::AVIOInterruptCB {
+            callback: Some(interrupt_callback),
+            opaque: Arc::as_ptr(cancel).cast_mut().cast(),
+        };
+    }
+
+    Ok(ctx)
+}
+
+/// Called periodically by FFmpeg during blocking IO, returning non-zero aborts the call.
+unsafe extern "C" fn interrupt_callback(opaque: *mut c_void) -> c_int {
+    let cancel = unsafe { &*opaque.cast::<AtomicBool>() };
+    cancel.load(Ordering::Relaxed) as c_int
+}
+
+/// An owned [ffmpeg::AVDictionary] of options passed to FFmpeg.
+///
+/// FFmpeg removes any options it consumes from the dictionary,
+/// leaving only the ones it did not recognise.
+struct Options {
+    dict: *mut ffmpeg::AVDictionary,
+}
+
+impl Options {
+    fn new(options: &[(&str, &str)]) -> Result<Self, error::FFmpegError> {
+        let mut this = Self {
+            dict: ptr::null_mut(),
+        };
+
+        for (key, value) in options {
+            let (Ok(key_cstr), Ok(value_cstr)) =
+                (CString::new(*key), CString::new(*value))
+            else {
+                return Err(error::FFmpegError::custom(format!(
+                    "option {key:?} contains a null terminator",
+                )));
+            };
+
+            let result = unsafe {
+                ffmpeg::av_dict_set(
+                    &raw mut this.dict,
+                    key_cstr.as_ptr(),
+                    value_cstr.as_ptr(),
+                    0,
+                )
+            };
+            error::convert_ff_result(result)?;
+        }
+
+        Ok(this)
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut *mut ffmpeg::AVDictionary {
+        &raw mut self.dict
+    }
+
+    /// Returns the keys of all options left in the dictionary.
+    fn keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        let mut entry = ptr::null();
+        loop {
+            entry = unsafe {
+                ffmpeg::av_dict_get(
+                    self.dict,
+                    c"".as_ptr(),
+                    entry,
+                    ffmpeg::AV_DICT_IGNORE_SUFFIX as i32,
+                )
+            };
+            if entry.is_null() {
+                break;
+            }
+
+            let key = unsafe { std::ffi::CStr::from_ptr((*entry).key) };
+            keys.push(key.to_string_lossy().into_owned());
+        }
+        keys
+    }
+}
+
+impl Drop for Options {
+    fn drop(&mut self) {
+        unsafe { ffmpeg::av_dict_free(&raw mut self.dict) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChannelLayout;
+    use crate::stream::{Fraction, Resolution};
+    use crate::test_support::{create_mkv_with_attachment, create_ts_with_programs};
+
+    #[test]
+    fn test_direct_file_open() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert_eq!(source.num_streams(), 2);
+        assert!(source.is_seekable());
+        assert_eq!(source.duration(), Duration::from_secs_f32(13.845000267));
+    }
+
+    #[test]
+    fn test_read_raw_packet() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+
+        let mut num_packets = [0; 2];
+        let mut first_video_packet = None;
+        while let Some(packet) = source.read_raw_packet().unwrap() {
+            assert_eq!(packet.data().len(), packet.size());
+            num_packets[packet.stream_index()] += 1;
+
+            if packet.stream_index() == 0 && first_video_packet.is_none() {
+                first_video_packet = Some(packet);
+            }
+        }
+
+        let first_video_packet = first_video_packet.expect("video packets exist");
+        assert!(first_video_packet.is_keyframe());
+        assert!(first_video_packet.dts().is_some());
+        assert!(num_packets[0] > 300);
+        assert!(num_packets[1] > 0);
+        assert!(source.read_raw_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_max_resolution_requires_known_resolution() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        // Mimic a stream which does not report its resolution.
+        for &stream in source.streams_mut() {
+            let codec_params = unsafe { &mut *(*stream).codecpar };
+            if codec_params.codec_type == ffmpeg::AVMEDIA_TYPE_VIDEO {
+                codec_params.width = 0;
+                codec_params.height = 0;
+            }
+        }
+
+        let result = crate::MediaPlayerBuilder::for_source(source)
+            .with_max_resolution(1280, 1280, crate::OnExceed::Downscale)
+            .build();
+        match result {
+            Err(crate::PlayerError::InvalidStream { index, expected }) => {
+                assert_eq!(index, 0);
+                assert_eq!(expected, MediaType::Video);
+            },
+            Err(other) => panic!("expected invalid stream error, got {other:?}"),
+            Ok(_) => panic!("expected invalid stream error"),
+        }
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+
+        let first_packet = source.read_raw_packet().unwrap().expect("packets exist");
+        let mut num_packets = 1;
+        while source.read_raw_packet().unwrap().is_some() {
+            num_packets += 1;
+        }
+
+        source.rewind().unwrap();
+
+        let packet = source.read_raw_packet().unwrap().expect("packets remain");
+        assert_eq!(packet.stream_index(), first_packet.stream_index());
+        assert_eq!(packet.pts(), first_packet.pts());
+        assert_eq!(packet.data(), first_packet.data());
+
+        let mut num_packets_rewound = 1;
+        while source.read_raw_packet().unwrap().is_some() {
+            num_packets_rewound += 1;
+        }
+        assert_eq!(num_packets_rewound, num_packets);
+    }
+
+    #[test]
+    fn test_generate_waveform() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        let first_packet = source.read_raw_packet().unwrap().expect("packets exist");
+        source.rewind().unwrap();
+
+        let waveform = source.generate_waveform(100).unwrap();
+        assert_eq!(waveform.len(), 100);
+        assert!(
+            waveform
+                .iter()
+                .all(|&(min, max)| -1.0 <= min && min <= max && max <= 1.0)
+        );
+        assert!(waveform.iter().any(|&(min, max)| max - min > 0.0));
+
+        // The source is rewound and all streams are read again.
+        let packet = source.read_raw_packet().unwrap().expect("packets remain");
+        assert_eq!(packet.stream_index(), first_packet.stream_index());
+        assert_eq!(packet.pts(), first_packet.pts());
+    }
+
+    #[test]
+    fn test_generate_waveform_after_reading() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        let expected = source.generate_waveform(100).unwrap();
+
+        // Part way through the source, the waveform must still cover all of it.
+        for _ in 0..200 {
+            source.read_raw_packet().unwrap().expect("packets remain");
+        }
+        let waveform = source.generate_waveform(100).unwrap();
+        assert_eq!(waveform, expected);
+    }
+
+    #[test]
+    fn test_reduce_waveform_blocks() {
+        let blocks = [(-0.5, 0.5), (-0.25, 0.75), (-1.0, 0.0), (0.0, 0.25)];
+        assert_eq!(
+            reduce_waveform_blocks(&blocks, 2),
+            vec![(-0.5, 0.75), (-1.0, 0.25)],
+        );
+        assert_eq!(reduce_waveform_blocks(&blocks, 1), vec![(-1.0, 0.75)]);
+        assert_eq!(
+            reduce_waveform_blocks(&blocks[..2], 4),
+            vec![(-0.5, 0.5), (-0.5, 0.5), (-0.25, 0.75), (-0.25, 0.75)],
+        );
+        assert_eq!(reduce_waveform_blocks(&[], 2), vec![(0.0, 0.0); 2]);
+    }
+
+    #[test]
+    fn test_bitstream_extractor() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        let mut extractor = source.bitstream_extractor(0, "h264_mp4toannexb").unwrap();
+        assert_eq!(extractor.stream_index(), 0);
+
+        let mut filtered = Vec::new();
+        while let Some(packet) = source.read_raw_packet().unwrap() {
+            let packets = extractor.filter(&packet).unwrap();
+            if packet.stream_index() != 0 {
+                assert!(packets.is_empty());
+            }
+            filtered.extend(packets);
+        }
+        filtered.extend(extractor.flush().unwrap());
+
+        assert!(filtered.len() > 300);
+        assert!(filtered.iter().all(|packet| packet.stream_index() == 0));
+        // Annex-B NAL units are delimited by start codes rather than a length prefix.
+        let data = filtered[0].data();
+        assert!(data.starts_with(&[0, 0, 0, 1]) || data.starts_with(&[0, 0, 1]));
+    }
+
+    #[test]
+    fn test_bitstream_extractor_unknown_filter() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert!(source.bitstream_extractor(0, "not_a_filter").is_err());
+        // The AAC stream is not supported by the H.264 filter.
+        assert!(source.bitstream_extractor(1, "h264_mp4toannexb").is_err());
+    }
+
+    #[test]
+    fn test_remux_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().join("remux.mkv");
+
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        source.remux_to(&output_path, &[1]).unwrap();
+
+        let remuxed = InputSource::open_file(&output_path).unwrap();
+
+        assert_eq!(remuxed.num_streams(), 1);
+        let stream = remuxed.stream(0);
+        assert_eq!(stream.media_type, MediaType::Audio);
+        assert_eq!(stream.codec_name, "aac");
+        assert_eq!(stream.sample_rate, Some(48_000));
+        let difference = stream.duration.abs_diff(source.stream(1).duration);
+        assert!(difference < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_remux_to_without_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().join("empty.mkv");
+
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        let result = source.remux_to(&output_path, &[]);
+        assert!(matches!(
+            result,
+            Err(error::PlayerError::NoAvailableStreams)
+        ));
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn test_remux_to_removes_output_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().join("video.wav");
+
+        // WAV cannot hold the H.264 video, so writing the header fails.
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert!(source.remux_to(&output_path, &[0]).is_err());
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn test_build_keyframe_index() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        let first_packet = source.read_raw_packet().unwrap().expect("packets exist");
+        source.rewind().unwrap();
+
+        let index = source.build_keyframe_index(0).unwrap();
+        assert_eq!(index.stream_index(), 0);
+        assert!(!index.is_empty());
+        assert!(
+            index
+                .keyframes()
+                .windows(2)
+                .all(|pair| pair[0].pts < pair[1].pts)
+        );
+
+        let first = index.keyframes()[0];
+        assert_eq!(index.find(Duration::ZERO), Some(&first));
+        let last = *index.keyframes().last().unwrap();
+        assert_eq!(index.find(Duration::from_secs(3600)), Some(&last));
+        for pair in index.keyframes().windows(2) {
+            let between = pair[0].pts + (pair[1].pts - pair[0].pts) / 2;
+            assert_eq!(index.find(between), Some(&pair[0]));
+            assert_eq!(index.find(pair[1].pts), Some(&pair[1]));
+        }
+
+        // The source is rewound and all streams are read again.
+        let packet = source.read_raw_packet().unwrap().expect("packets remain");
+        assert_eq!(packet.stream_index(), first_packet.stream_index());
+        assert_eq!(packet.pts(), first_packet.pts());
+    }
+
+    #[test]
+    fn test_estimate_bitrate() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+
+        let video_bitrate = source.estimate_bitrate(0).expect("estimate is available");
+        // The file is around 37 Mbps, almost entirely video.
+        assert!(
+            (10_000..80_000).contains(&video_bitrate),
+            "unexpected estimate {video_bitrate}"
+        );
+        assert_eq!(source.estimate_bitrate(0), Some(video_bitrate));
+
+        let audio_bitrate = source.estimate_bitrate(1).expect("estimate is available");
+        assert!(audio_bitrate < 1_000, "unexpected estimate {audio_bitrate}");
+        assert_eq!(source.estimate_bitrate(2), None);
+
+        // The source is rewound after probing.
+        let packet = source.read_raw_packet().unwrap().expect("packets remain");
+        assert!(packet.dts().unwrap_or_default() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_estimate_bitrate_after_reading() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        let mut expected = InputSource::open_file("../media/test.mp4").unwrap();
+        for _ in 0..10 {
+            source.read_raw_packet().unwrap().expect("packets remain");
+            expected.read_raw_packet().unwrap().expect("packets remain");
+        }
+
+        // Probing would lose the read position, so nothing is read.
+        assert_eq!(source.estimate_bitrate(0), None);
+        let packet = source.read_raw_packet().unwrap().expect("packets remain");
+        let expected_packet =
+            expected.read_raw_packet().unwrap().expect("packets remain");
+        assert_eq!(packet.stream_index(), expected_packet.stream_index());
+        assert_eq!(packet.pts(), expected_packet.pts());
+
+        // The refusal is not cached, the estimate is available once rewound.
+        source.rewind().unwrap();
+        assert!(source.estimate_bitrate(0).is_some());
+    }
+
+    #[test]
+    fn test_estimate_gop() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+
+        let video_gop = source.estimate_gop(0, 4).expect("estimate is available");
+        assert!(video_gop > 1, "unexpected estimate {video_gop}");
+        assert_eq!(source.estimate_gop(0, 1), Some(video_gop));
+
+        // Every AAC packet is a keyframe.
+        assert_eq!(source.estimate_gop(1, 4), Some(1));
+        assert_eq!(source.estimate_gop(2, 4), None);
+
+        // The source is rewound after probing.
+        let packet = source.read_raw_packet().unwrap().expect("packets remain");
+        assert!(packet.dts().unwrap_or_default() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_set_stream_enabled() {
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        source.set_stream_enabled(0, false).unwrap();
+
+        let mut num_packets = 0;
+        while let Some(packet) = source.read_raw_packet().unwrap() {
+            assert_eq!(packet.stream_index(), 1);
+            num_packets += 1;
+        }
+        assert!(num_packets > 0);
+
+        let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+        source.set_stream_enabled(0, false).unwrap();
+        source.set_stream_enabled(1, false).unwrap();
+        assert!(source.read_raw_packet().unwrap().is_none());
+
+        assert!(matches!(
+            source.set_stream_enabled(2, true),
+            Err(error::PlayerError::InvalidStream { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_concat_sources() {
+        let sources = vec![
+            InputSource::open_file("../media/test.mp4").unwrap(),
+            InputSource::open_file("../media/test.mp4").unwrap(),
+        ];
+        let mut source = InputSource::concat(sources).unwrap();
+        assert_eq!(source.url().as_str(), "concat:");
+        assert_eq!(source.num_streams(), 2);
+
+        let expected = Duration::from_secs_f32(13.845000267) * 2;
+        let difference = source.duration().abs_diff(expected);
+        assert!(
+            difference < Duration::from_millis(100),
+            "duration should cover both sources, got {:?}",
+            source.duration(),
+        );
+
+        let time_base = unsafe { (*source.streams()[1]).time_base };
+        let mut audio_pts = Vec::new();
+        let mut packet = MediaPacket::new().unwrap();
+        loop {
+            packet.reset();
+            match source.read_packet(&mut packet) {
+                Ok(()) => {},
+                Err(err) if err.is_eof() => break,
+                Err(err) => panic!("failed to read packet: {err}"),
+            }
+            if packet.stream_index == 1 && packet.pts != ffmpeg::AV_NOPTS_VALUE {
+                audio_pts.push(packet.pts);
+            }
+        }
+
+        assert!(
+            audio_pts.windows(2).all(|pts| pts[0] < pts[1]),
+            "audio timestamps should keep increasing into the second source",
+        );
+        // Both sources have the same packets, the second offset by the first's duration.
+        assert_eq!(audio_pts.len() % 2, 0);
+        let (first, second) = audio_pts.split_at(audio_pts.len() / 2);
+        for (&first_pts, &second_pts) in first.iter().zip(second) {
+            let offset = pts_to_duration(second_pts - first_pts, time_base);
+            assert!(
+                offset.abs_diff(Duration::from_secs_f32(13.845000267))
+                    < Duration::from_millis(100),
+                "unexpected offset of the second source: {offset:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn test_concat_mismatched_sources() {
+        let err = InputSource::concat(Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("at least one source"));
+
+        let sources = vec![
+            InputSource::open_file("../media/test.mp4").unwrap(),
+            InputSource::open_file("../media/subtitles.mp4").unwrap(),
+        ];
+        let err = InputSource::concat(sources).unwrap_err();
+        assert!(
+            err.to_string().contains("source 1"),
+            "unexpected error: {err}"
+        );
+
+        let data = std::fs::read("../media/test.mp4").unwrap();
+        let sources = vec![
+            InputSource::open_file("../media/test.mp4").unwrap(),
+            InputSource::open_from_memory(data).unwrap(),
+        ];
+        let err = InputSource::concat(sources).unwrap_err();
+        assert!(err.to_string().contains("not opened from a URL"));
+    }
+
+    #[test]
+    fn test_format_name() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert_eq!(source.format_name(), "mov,mp4,m4a,3gp,3g2,mj2");
+        assert_eq!(
+            source.format_long_name().as_deref(),
+            Some("QuickTime / MOV")
+        );
+    }
+
+    #[test]
+    fn test_iter_all_streams() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        let streams: Vec<StreamInfo> = source.iter_all_streams().collect();
+        assert_eq!(streams.len(), source.num_streams());
+        // The file only has audio and video, so both iterators are the same.
+        assert_eq!(source.iter_streams().count(), streams.len());
+    }
+
+    #[test]
+    fn test_iter_all_streams_with_attachment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("all-streams.mkv");
+        create_mkv_with_attachment(&path, b"not really a font");
+        let source = InputSource::open_file(&path).unwrap();
+
+        let media_types: Vec<MediaType> = source
+            .iter_all_streams()
+            .map(|stream| stream.media_type)
+            .collect();
+        assert_eq!(media_types, [MediaType::Audio, MediaType::Attachment]);
+
+        let media_types: Vec<MediaType> = source
+            .iter_streams()
+            .map(|stream| stream.media_type)
+            .collect();
+        assert_eq!(media_types, [MediaType::Audio]);
+    }
+
+    #[test]
+    fn test_attachments() {
+        // Neither test file has attachments.
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert!(source.attachments().is_empty());
+    }
+
+    #[test]
+    fn test_attachments_with_font() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("attachments.mkv");
+        create_mkv_with_attachment(&path, b"not really a font");
+        let source = InputSource::open_file(&path).unwrap();
+
+        let attachments = source.attachments();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].filename, "font.ttf");
+        assert_eq!(
+            attachments[0].mime_type.as_deref(),
+            Some("application/x-truetype-font")
+        );
+        assert_eq!(attachments[0].data, b"not really a font");
+    }
+
+    #[test]
+    fn test_probe() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        let info = source.probe();
+        assert_eq!(info.url, source.url().as_str());
+        assert_eq!(info.format_name, source.format_name());
+        assert_eq!(info.duration, source.duration());
+        assert_eq!(info.streams.len(), 2);
+        assert_eq!(info.streams[0].media_type, MediaType::Video);
+        assert_eq!(info.streams[1].media_type, MediaType::Audio);
+        assert_eq!(info.chapters, source.chapters());
+        assert_eq!(info.programs, source.programs());
+        assert_eq!(info.metadata, source.metadata());
+    }
+
+    #[test]
+    fn test_open_url_with_options() {
+        let path = Path::new("../media/test.mp4").canonicalize().unwrap();
+        let url: url::Url = format!("file://{}", path.display()).parse().unwrap();
+
+        let source =
+            InputSource::open_url_with_options(url.clone(), &[("probesize", "5000000")])
+                .unwrap();
+        assert_eq!(source.num_streams(), 2);
+
+        let err = InputSource::open_url_with_options(
+            url,
+            &[("probesize", "5000000"), ("not_a_real_option", "1")],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not_a_real_option"));
+        assert!(!err.to_string().contains("probesize"));
+    }
+
+    #[test]
+    fn test_open_url_with_probe() {
+        let path = Path::new("../media/test.mp4").canonicalize().unwrap();
+        let url: url::Url = format!("file://{}", path.display()).parse().unwrap();
+
+        let source = InputSource::open_url_with_probe(
+            url.clone(),
+            Some(64 * 1024),
+            Some(Duration::from_millis(500)),
+        )
+        .unwrap();
+        assert_eq!(source.as_ctx().probesize, 64 * 1024);
+        assert_eq!(source.as_ctx().max_analyze_duration, 500_000);
+        assert_eq!(source.num_streams(), 2);
+
+        let source = InputSource::open_url_with_probe(url, None, None).unwrap();
+        assert_eq!(source.num_streams(), 2);
+    }
+
+    #[test]
+    fn test_open_url_cancelled() {
+        let path = Path::new("../media/test.mp4").canonicalize().unwrap();
+        let url: url::Url = format!("file://{}", path.display()).parse().unwrap();
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let err = InputSource::open_url_with_cancel(url.clone(), cancel).unwrap_err();
+        assert!(matches!(err, crate::PlayerError::Cancelled));
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let source = InputSource::open_url_with_cancel(url, cancel.clone()).unwrap();
+        assert!(Arc::ptr_eq(&cancel, &source.cancel_handle()));
+    }
+
+    #[test]
+    fn test_chapters_empty() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert!(source.chapters().is_empty());
+    }
+
+    /// Encode an ID3v2.3 frame with the given ID and body.
+    fn id3_frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    /// Create a silent mono MP3 starting with an ID3v2.3 tag made up of `frames`.
+    fn create_tagged_mp3(frames: &[Vec<u8>]) -> Vec<u8> {
+        let tag: Vec<u8> = frames.concat();
+        let size = tag.len() as u32;
+        // The tag size is a syncsafe integer using 7 bits of each byte.
+        let syncsafe = [21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8);
+
+        let mut data = b"ID3\x03\x00\x00".to_vec();
+        data.extend_from_slice(&syncsafe);
+        data.extend_from_slice(&tag);
+
+        // MPEG-1 layer 3 at 128kbps and 44.1kHz, each frame is 417 bytes long.
+        // Zeroed side info means every frame decodes to silence.
+        for _ in 0..50 {
+            let mut frame = vec![0; 417];
+            frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+            data.extend_from_slice(&frame);
+        }
+        data
+    }
+
+    /// Encode an ID3v2 `CHAP` frame spanning `start..end` milliseconds.
+    fn id3_chapter(element_id: &str, start: u32, end: u32, title: &str) -> Vec<u8> {
+        let mut body = element_id.as_bytes().to_vec();
+        body.push(0);
+        body.extend_from_slice(&start.to_be_bytes());
+        body.extend_from_slice(&end.to_be_bytes());
+        // The byte offsets are unused.
+        body.extend_from_slice(&[0xFF; 8]);
+
+        let mut title_body = vec![0];
+        title_body.extend_from_slice(title.as_bytes());
+        body.extend_from_slice(&id3_frame(b"TIT2", &title_body));
+        id3_frame(b"CHAP", &body)
+    }
+
+    #[test]
+    fn test_chapters() {
+        let data = create_tagged_mp3(&[
+            id3_chapter("chp0", 0, 500, "Intro"),
+            id3_chapter("chp1", 500, 1000, "Outro"),
+        ]);
+        let source = InputSource::open_from_memory(data).unwrap();
+        assert_eq!(source.format_name(), "mp3");
+
+        let chapters = source.chapters();
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    start: Duration::ZERO,
+                    end: Duration::from_millis(500),
+                    title: Some("Intro".to_string()),
+                },
+                Chapter {
+                    start: Duration::from_millis(500),
+                    end: Duration::from_millis(1000),
+                    title: Some("Outro".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_best_program_stream_shared_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("programs.ts");
+        create_ts_with_programs(&path);
+        let source = InputSource::open_file(&path).unwrap();
+
+        let programs = source.programs();
+        assert_eq!(programs.len(), 2);
+        let program_streams = |id: i32, media_type: MediaType| -> Vec<usize> {
+            let program = programs.iter().find(|program| program.id == id).unwrap();
+            program
+                .stream_indices
+                .iter()
+                .copied()
+                .filter(|&index| source.stream(index).media_type == media_type)
+                .collect()
+        };
+        let first_video = program_streams(1, MediaType::Video);
+        let second_video = program_streams(2, MediaType::Video);
+        let shared_audio = program_streams(2, MediaType::Audio);
+        assert_eq!(first_video.len(), 1);
+        assert_eq!(second_video.len(), 1);
+        assert_ne!(first_video, second_video);
+        assert_eq!(shared_audio, program_streams(1, MediaType::Audio));
+
+        // The shared audio is listed first, so FFmpeg would search the first program.
+        let second_program =
+            &programs.iter().find(|p| p.id == 2).unwrap().stream_indices;
+        let video = source
+            .find_best_program_stream(MediaType::Video, None, Some(second_program))
+            .unwrap()
+            .unwrap();
+        assert_eq!(video.index, second_video[0]);
+        let audio = source
+            .find_best_program_stream(MediaType::Audio, None, Some(second_program))
+            .unwrap()
+            .unwrap();
+        assert_eq!(audio.index, shared_audio[0]);
+        let subtitle = source
+            .find_best_program_stream(MediaType::Subtitle, None, Some(second_program))
+            .unwrap();
+        assert!(subtitle.is_none());
+    }
+
+    #[test]
+    fn test_programs_empty() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert!(source.programs().is_empty());
+    }
+
+    #[test]
+    fn test_attached_pictures_empty() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        assert!(source.attached_pictures().is_empty());
+    }
+
+    #[test]
+    fn test_attached_pictures() {
+        // A 1x1 greyscale PNG.
+        const PNG: [u8; 67] = [
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D,
+            0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+            0x08, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x7E, 0x9B, 0x55, 0x00, 0x00, 0x00,
+            0x0A, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0x63, 0x60, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x01, 0x48, 0xAF, 0xA4, 0x71, 0x00, 0x00, 0x00, 0x00, 0x49,
+            0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+        ];
+
+        // Text encoding, MIME type, picture type (front cover) and empty description.
+        let mut body = vec![0];
+        body.extend_from_slice(b"image/png\0");
+        body.extend_from_slice(&[0x03, 0]);
+        body.extend_from_slice(&PNG);
+
+        let data = create_tagged_mp3(&[id3_frame(b"APIC", &body)]);
+        let source = InputSource::open_from_memory(data).unwrap();
+
+        let pictures = source.attached_pictures();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].codec_name, "png");
+        assert_eq!(pictures[0].data, PNG);
+
+        let stream = source.stream(pictures[0].stream_index);
+        assert_eq!(stream.media_type, MediaType::Video);
+    }
+
+    #[test]
+    fn test_open_from_memory() {
+        let data = std::fs::read("../media/test.mp4").unwrap();
+        let source = InputSource::open_from_memory(data).unwrap();
+        assert_eq!(source.num_streams(), 2);
+        assert_eq!(source.duration(), Duration::from_secs_f32(13.845000267));
+        assert_eq!(source.url().as_str(), "memory:");
+
+        let stream = source
+            .find_best_stream(MediaType::Video, None)
+            .expect("video stream exists with known decoder")
+            .expect("video stream exists");
+        assert_eq!(stream.codec_name, "h264");
+    }
+
+    #[test]
+    fn test_open_reader() {
+        let file = std::fs::File::open("../media/test.mp4").unwrap();
+        let source = InputSource::open_reader(std::io::BufReader::new(file)).unwrap();
         assert_eq!(source.num_streams(), 2);
         assert_eq!(source.duration(), Duration::from_secs_f32(13.845000267));
+        assert_eq!(source.url().as_str(), "reader:");
+        assert!(source.is_seekable());
     }
 
     #[test]
@@ -353,7 +2558,20 @@ mod tests {
             })
         );
         assert_eq!(stream.framerate, Fraction::new(25, 1));
+        assert_eq!(stream.framerate.to_string(), "25.00 fps");
+        let resolution = stream.resolution.unwrap();
+        assert_eq!(resolution.to_string(), "1920x1080");
+        assert!((resolution.aspect_ratio() - 16.0 / 9.0).abs() < 1e-6);
+        assert!((resolution.megapixels() - 2.0736).abs() < 1e-6);
+        assert_eq!(stream.pixel_format.as_deref(), Some("yuv420p"));
+        assert!(stream.profile.is_some());
+        assert!(stream.level > 0);
         assert_eq!(stream.media_type, MediaType::Video);
+        assert_eq!(stream.sample_aspect_ratio, (1, 1));
+        assert_eq!(stream.display_aspect_ratio(), Some((16, 9)));
+        // MP4 stores the H.264 parameter sets in an `avcC` record, starting at version 1.
+        let extradata = stream.extradata().expect("video stream has extradata");
+        assert_eq!(extradata[0], 1);
 
         let stream = source
             .find_best_stream(MediaType::Audio, None)
@@ -363,7 +2581,14 @@ mod tests {
         assert_eq!(stream.codec_name, "aac");
         assert_eq!(stream.bitrate, Some(253));
         assert_eq!(stream.framerate, Fraction::new(0, 0));
+        assert_eq!(stream.pixel_format, None);
         assert_eq!(stream.media_type, MediaType::Audio);
+        assert_eq!(stream.display_aspect_ratio(), None);
+        assert_eq!(stream.sample_rate, Some(48_000));
+        assert_eq!(stream.channel_layout, Some(ChannelLayout::Stereo));
+        // The `AudioSpecificConfig` begins with the AAC-LC object type.
+        let extradata = stream.extradata().expect("audio stream has extradata");
+        assert_eq!(extradata[0] >> 3, 2);
 
         let stream = source
             .find_best_stream(MediaType::Subtitle, None)
@@ -0,0 +1,243 @@
+use std::ffi::c_void;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+use wgpu::hal::api::{Dx12, Vulkan};
+use windows::Win32::Foundation::{BOOL, CloseHandle, HANDLE};
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11_BIND_SHADER_RESOURCE,
+    D3D11_BOX,
+    D3D11_QUERY_DESC,
+    D3D11_QUERY_EVENT,
+    D3D11_RESOURCE_MISC_SHARED,
+    D3D11_RESOURCE_MISC_SHARED_NTHANDLE,
+    D3D11_TEXTURE2D_DESC,
+    D3D11_USAGE_DEFAULT,
+    ID3D11Device,
+    ID3D11DeviceContext,
+    ID3D11Texture2D,
+};
+use windows::Win32::Graphics::Direct3D12::ID3D12Resource;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_SAMPLE_DESC;
+use windows::Win32::Graphics::Dxgi::{DXGI_SHARED_RESOURCE_READ, IDXGIResource1};
+use windows::core::{Interface, PCWSTR};
+
+use crate::error;
+
+/// `AVD3D11VADeviceContext` from `libavutil/hwcontext_d3d11va.h`.
+#[repr(C)]
+#[allow(dead_code)]
+struct AVD3D11VADeviceContext {
+    device: *mut c_void,
+    device_context: *mut c_void,
+    video_device: *mut c_void,
+    video_context: *mut c_void,
+    lock: Option<unsafe extern "C" fn(*mut c_void)>,
+    unlock: Option<unsafe extern "C" fn(*mut c_void)>,
+    lock_ctx: *mut c_void,
+}
+
+impl AVD3D11VADeviceContext {
+    /// Run `f` while holding the lock FFmpeg uses for the immediate context.
+    fn with_lock<T>(&self, f: impl FnOnce() -> T) -> T {
+        if let Some(lock) = self.lock {
+            unsafe { lock(self.lock_ctx) };
+        }
+        let result = f();
+        if let Some(unlock) = self.unlock {
+            unsafe { unlock(self.lock_ctx) };
+        }
+        result
+    }
+}
+
+/// A NT handle to a shared resource, closed on drop.
+struct SharedHandle(HANDLE);
+
+impl Drop for SharedHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// Import a `AV_PIX_FMT_D3D11` frame by copying its slice of the decoder's
+/// texture array into a shared texture on the GPU, then opening the shared
+/// texture on the wgpu device.
+///
+/// wgpu has no Direct3D 11 backend, so the texture is opened with either the
+/// Direct3D 12 or Vulkan backend.
+///
+/// # Safety
+///
+/// `frame` must be a valid `AV_PIX_FMT_D3D11` frame.
+pub(super) unsafe fn import(
+    frame: &ffmpeg::AVFrame,
+    device: &wgpu::Device,
+) -> Result<wgpu::Texture, error::FFmpegError> {
+    let frames_ctx = unsafe {
+        &*(*frame.hw_frames_ctx)
+            .data
+            .cast::<ffmpeg::AVHWFramesContext>()
+    };
+    let device_ctx = unsafe {
+        &*(*frames_ctx.device_ctx)
+            .hwctx
+            .cast::<AVD3D11VADeviceContext>()
+    };
+
+    let format = super::texture_format(frames_ctx.sw_format, device)?;
+
+    let d3d11_device = unsafe { ID3D11Device::from_raw_borrowed(&device_ctx.device) };
+    let context =
+        unsafe { ID3D11DeviceContext::from_raw_borrowed(&device_ctx.device_context) };
+    let source_ptr = frame.data[0].cast::<c_void>();
+    let source = unsafe { ID3D11Texture2D::from_raw_borrowed(&source_ptr) };
+    let (Some(d3d11_device), Some(context), Some(source)) =
+        (d3d11_device, context, source)
+    else {
+        return Err(error::FFmpegError::custom(
+            "D3D11 frame is missing its texture",
+        ));
+    };
+    let array_index = frame.data[1] as usize as u32;
+
+    let mut source_desc = D3D11_TEXTURE2D_DESC::default();
+    unsafe { source.GetDesc(&mut source_desc) };
+
+    // Planar YUV textures must have an even size.
+    let width = (frame.width as u32 + 1) & !1;
+    let height = (frame.height as u32 + 1) & !1;
+    let shared_desc = D3D11_TEXTURE2D_DESC {
+        Width: width,
+        Height: height,
+        MipLevels: 1,
+        ArraySize: 1,
+        Format: source_desc.Format,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Usage: D3D11_USAGE_DEFAULT,
+        BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+        CPUAccessFlags: 0,
+        MiscFlags: (D3D11_RESOURCE_MISC_SHARED.0 | D3D11_RESOURCE_MISC_SHARED_NTHANDLE.0)
+            as u32,
+    };
+    let mut shared = None;
+    unsafe { d3d11_device.CreateTexture2D(&shared_desc, None, Some(&mut shared)) }
+        .map_err(|err| windows_error("creating shared texture", err))?;
+    let shared = shared
+        .ok_or_else(|| error::FFmpegError::custom("failed to create shared texture"))?;
+
+    let mut query = None;
+    let query_desc = D3D11_QUERY_DESC {
+        Query: D3D11_QUERY_EVENT,
+        MiscFlags: 0,
+    };
+    unsafe { d3d11_device.CreateQuery(&query_desc, Some(&mut query)) }
+        .map_err(|err| windows_error("creating query", err))?;
+    let query =
+        query.ok_or_else(|| error::FFmpegError::custom("failed to create query"))?;
+
+    let source_box = D3D11_BOX {
+        left: 0,
+        top: 0,
+        front: 0,
+        right: width,
+        bottom: height,
+        back: 1,
+    };
+    device_ctx.with_lock(|| unsafe {
+        context.CopySubresourceRegion(
+            &shared,
+            0,
+            0,
+            0,
+            0,
+            source,
+            array_index,
+            Some(&source_box),
+        );
+        context.End(&query);
+        context.Flush();
+    });
+
+    // Wait for the copy, as the decoder reuses the surface once the frame is freed.
+    loop {
+        let mut is_done = BOOL(0);
+        device_ctx
+            .with_lock(|| unsafe {
+                context.GetData(
+                    &query,
+                    Some((&raw mut is_done).cast()),
+                    size_of::<BOOL>() as u32,
+                    0,
+                )
+            })
+            .map_err(|err| windows_error("waiting for copy", err))?;
+        if is_done.as_bool() {
+            break;
+        }
+        std::thread::yield_now();
+    }
+
+    let resource: IDXGIResource1 = shared
+        .cast()
+        .map_err(|err| windows_error("sharing texture", err))?;
+    let handle = unsafe {
+        resource.CreateSharedHandle(None, DXGI_SHARED_RESOURCE_READ.0, PCWSTR::null())
+    }
+    .map_err(|err| windows_error("sharing texture", err))?;
+    let handle = SharedHandle(handle);
+
+    let desc = super::texture_descriptor(width, height, format);
+
+    if let Some(hal_device) = unsafe { device.as_hal::<Dx12>() } {
+        let mut resource: Option<ID3D12Resource> = None;
+        unsafe {
+            hal_device
+                .raw_device()
+                .OpenSharedHandle(handle.0, &mut resource)
+        }
+        .map_err(|err| windows_error("opening shared texture", err))?;
+        let resource = resource.ok_or_else(|| {
+            error::FFmpegError::custom("failed to open shared texture")
+        })?;
+        drop(hal_device);
+
+        let hal_texture = unsafe {
+            wgpu::hal::dx12::Device::texture_from_raw(
+                resource,
+                desc.format,
+                desc.dimension,
+                desc.size,
+                desc.mip_level_count,
+                desc.sample_count,
+            )
+        };
+        return Ok(unsafe {
+            device.create_texture_from_hal::<Dx12>(hal_texture, &desc)
+        });
+    }
+
+    if let Some(hal_device) = unsafe { device.as_hal::<Vulkan>() } {
+        let hal_desc = super::hal_texture_descriptor(&desc);
+        let hal_texture =
+            unsafe { hal_device.texture_from_d3d11_shared_handle(handle.0, &hal_desc) }
+                .map_err(|err| {
+                    error::FFmpegError::custom(format!("opening shared texture: {err}"))
+                })?;
+        drop(hal_device);
+
+        return Ok(unsafe {
+            device.create_texture_from_hal::<Vulkan>(hal_texture, &desc)
+        });
+    }
+
+    Err(error::FFmpegError::custom(
+        "device does not use the Direct3D 12 or Vulkan backend",
+    ))
+}
+
+fn windows_error(context: &str, err: windows::core::Error) -> error::FFmpegError {
+    error::FFmpegError::custom(format!("{context}: {err}"))
+}
@@ -0,0 +1,116 @@
+#[cfg(windows)]
+mod d3d11;
+#[cfg(target_vendor = "apple")]
+mod videotoolbox;
+#[cfg(unix)]
+mod vulkan;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::error;
+
+/// Import the device surface of a hardware frame into a [wgpu::Texture] without
+/// copying it through system memory.
+///
+/// Returns `None` if the surface cannot be shared with the backend of `device`,
+/// in which case the frame must be downloaded and uploaded instead.
+pub(crate) fn import_hw_frame(
+    frame: &ffmpeg::AVFrame,
+    device: &wgpu::Device,
+) -> Option<wgpu::Texture> {
+    debug_assert!(!frame.hw_frames_ctx.is_null());
+
+    let result = match frame.format {
+        #[cfg(unix)]
+        ffmpeg::AV_PIX_FMT_VULKAN => unsafe { vulkan::import(frame, device) },
+        #[cfg(windows)]
+        ffmpeg::AV_PIX_FMT_D3D11 => unsafe { d3d11::import(frame, device) },
+        #[cfg(target_vendor = "apple")]
+        ffmpeg::AV_PIX_FMT_VIDEOTOOLBOX => unsafe {
+            videotoolbox::import(frame, device)
+        },
+        _ => Err(error::FFmpegError::custom(
+            "hardware pixel format has no zero-copy import",
+        )),
+    };
+
+    match result {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            tracing::debug!(error = %err, "hardware frame import failed, copying instead");
+            None
+        },
+    }
+}
+
+#[cfg(any(unix, windows))]
+/// Returns the texture format matching the `sw_format` of a hardware frame,
+/// erroring if the format has no wgpu equivalent or the device lacks the
+/// feature required to use it.
+fn texture_format(
+    sw_format: ffmpeg::AVPixelFormat,
+    device: &wgpu::Device,
+) -> Result<wgpu::TextureFormat, error::FFmpegError> {
+    let format = match sw_format {
+        ffmpeg::AV_PIX_FMT_NV12 => wgpu::TextureFormat::NV12,
+        ffmpeg::AV_PIX_FMT_RGBA => wgpu::TextureFormat::Rgba8Unorm,
+        ffmpeg::AV_PIX_FMT_BGRA => wgpu::TextureFormat::Bgra8Unorm,
+        _ => {
+            return Err(error::FFmpegError::custom(format!(
+                "software format {sw_format:?} has no matching wgpu texture format"
+            )));
+        },
+    };
+
+    let required = format.required_features();
+    if !device.features().contains(required) {
+        return Err(error::FFmpegError::custom(format!(
+            "texture format {format:?} requires the {required:?} device features"
+        )));
+    }
+
+    Ok(format)
+}
+
+#[cfg(any(unix, windows))]
+/// The descriptor of a texture imported from a hardware frame, which can only
+/// be sampled by shaders.
+fn texture_descriptor(
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::TextureDescriptor<'static> {
+    wgpu::TextureDescriptor {
+        label: Some("libav-player video frame"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    }
+}
+
+#[cfg(any(unix, windows))]
+/// Convert the descriptor from [texture_descriptor] into the descriptor used by
+/// the wgpu-hal backends.
+fn hal_texture_descriptor<'a>(
+    desc: &wgpu::TextureDescriptor<'a>,
+) -> wgpu::hal::TextureDescriptor<'a> {
+    wgpu::hal::TextureDescriptor {
+        label: desc.label,
+        size: desc.size,
+        mip_level_count: desc.mip_level_count,
+        sample_count: desc.sample_count,
+        dimension: desc.dimension,
+        format: desc.format,
+        usage: wgpu::TextureUses::RESOURCE,
+        memory_flags: wgpu::hal::MemoryFlags::empty(),
+        view_formats: Vec::new(),
+    }
+}
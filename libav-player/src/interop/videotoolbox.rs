@@ -0,0 +1,100 @@
+use std::ffi::c_void;
+
+use metal::foreign_types::ForeignType;
+use metal::objc::{msg_send, sel, sel_impl};
+use rusty_ffmpeg::ffi as ffmpeg;
+use wgpu::hal::api::Metal;
+
+use crate::error;
+
+/// `kCVPixelFormatType_32BGRA` from `CoreVideo/CVPixelBuffer.h`.
+const PIXEL_FORMAT_32_BGRA: u32 = u32::from_be_bytes(*b"BGRA");
+
+#[link(name = "CoreVideo", kind = "framework")]
+unsafe extern "C" {
+    fn CVPixelBufferGetPixelFormatType(pixel_buffer: *mut c_void) -> u32;
+    fn CVPixelBufferGetIOSurface(pixel_buffer: *mut c_void) -> *mut c_void;
+}
+
+/// Import a `AV_PIX_FMT_VIDEOTOOLBOX` frame by creating a Metal texture backed
+/// by the IOSurface of its pixel buffer.
+///
+/// Metal has no multi-planar YUV texture formats, so only BGRA pixel buffers
+/// can be imported.
+///
+/// # Safety
+///
+/// `frame` must be a valid `AV_PIX_FMT_VIDEOTOOLBOX` frame.
+pub(super) unsafe fn import(
+    frame: &ffmpeg::AVFrame,
+    device: &wgpu::Device,
+) -> Result<wgpu::Texture, error::FFmpegError> {
+    let pixel_buffer = frame.data[3].cast::<c_void>();
+    if unsafe { CVPixelBufferGetPixelFormatType(pixel_buffer) } != PIXEL_FORMAT_32_BGRA {
+        return Err(error::FFmpegError::custom(
+            "only BGRA pixel buffers can be imported into Metal",
+        ));
+    }
+
+    let surface = unsafe { CVPixelBufferGetIOSurface(pixel_buffer) };
+    if surface.is_null() {
+        return Err(error::FFmpegError::custom(
+            "pixel buffer is not backed by an IOSurface",
+        ));
+    }
+
+    let Some(hal_device) = (unsafe { device.as_hal::<Metal>() }) else {
+        return Err(error::FFmpegError::custom(
+            "device does not use the Metal backend",
+        ));
+    };
+
+    let width = frame.width as u32;
+    let height = frame.height as u32;
+    let raw_texture = {
+        let raw_device = hal_device.raw_device().lock();
+        let descriptor = metal::TextureDescriptor::new();
+        descriptor.set_texture_type(metal::MTLTextureType::D2);
+        descriptor.set_pixel_format(metal::MTLPixelFormat::BGRA8Unorm);
+        descriptor.set_width(width as metal::NSUInteger);
+        descriptor.set_height(height as metal::NSUInteger);
+        descriptor.set_storage_mode(metal::MTLStorageMode::Shared);
+        descriptor.set_usage(metal::MTLTextureUsage::ShaderRead);
+
+        let device_ref: &metal::DeviceRef = &raw_device;
+        let descriptor_ref: &metal::TextureDescriptorRef = &descriptor;
+        let texture: *mut metal::MTLTexture = unsafe {
+            msg_send![
+                device_ref,
+                newTextureWithDescriptor: descriptor_ref
+                iosurface: surface
+                plane: 0 as metal::NSUInteger
+            ]
+        };
+        if texture.is_null() {
+            return Err(error::FFmpegError::custom(
+                "failed to create texture from IOSurface",
+            ));
+        }
+        unsafe { metal::Texture::from_ptr(texture) }
+    };
+    drop(hal_device);
+
+    let desc = super::texture_descriptor(width, height, wgpu::TextureFormat::Bgra8Unorm);
+    let hal_texture = unsafe {
+        wgpu::hal::metal::Device::texture_from_raw(
+            raw_texture,
+            desc.format,
+            metal::MTLTextureType::D2,
+            1,
+            1,
+            wgpu::hal::CopyExtent {
+                width,
+                height,
+                depth: 1,
+            },
+        )
+    };
+
+    Ok(unsafe { device.create_texture_from_hal::<Metal>(hal_texture, &desc) })
+}
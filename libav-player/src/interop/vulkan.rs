@@ -0,0 +1,348 @@
+use std::ffi::{c_int, c_void};
+use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd};
+
+use ash::vk;
+use rusty_ffmpeg::ffi as ffmpeg;
+use wgpu::hal::api::Vulkan;
+
+use crate::error;
+
+const NUM_DATA_POINTERS: usize = ffmpeg::AV_NUM_DATA_POINTERS as usize;
+
+/// The leading fields of `AVVulkanDeviceContext` from `libavutil/hwcontext_vulkan.h`.
+#[repr(C)]
+#[allow(dead_code)]
+struct AVVulkanDeviceContext {
+    alloc: *const c_void,
+    get_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+    inst: vk::Instance,
+    phys_dev: vk::PhysicalDevice,
+    act_dev: vk::Device,
+}
+
+/// The leading fields of `AVVulkanFramesContext` from `libavutil/hwcontext_vulkan.h`.
+#[repr(C)]
+#[allow(dead_code)]
+struct AVVulkanFramesContext {
+    tiling: vk::ImageTiling,
+    usage: vk::ImageUsageFlags,
+    create_pnext: *mut c_void,
+    alloc_pnext: [*mut c_void; NUM_DATA_POINTERS],
+    flags: c_int,
+    format: [vk::Format; NUM_DATA_POINTERS],
+    nb_layers: c_int,
+    lock_frame:
+        Option<unsafe extern "C" fn(*mut ffmpeg::AVHWFramesContext, *mut AVVkFrame)>,
+    unlock_frame:
+        Option<unsafe extern "C" fn(*mut ffmpeg::AVHWFramesContext, *mut AVVkFrame)>,
+}
+
+/// The leading fields of `AVVkFrame` from `libavutil/hwcontext_vulkan.h`.
+#[repr(C)]
+#[allow(dead_code)]
+struct AVVkFrame {
+    img: [vk::Image; NUM_DATA_POINTERS],
+    tiling: vk::ImageTiling,
+    mem: [vk::DeviceMemory; NUM_DATA_POINTERS],
+    size: [usize; NUM_DATA_POINTERS],
+    flags: vk::MemoryPropertyFlags,
+    access: [vk::AccessFlags; NUM_DATA_POINTERS],
+    layout: [vk::ImageLayout; NUM_DATA_POINTERS],
+    sem: [vk::Semaphore; NUM_DATA_POINTERS],
+    sem_value: [u64; NUM_DATA_POINTERS],
+}
+
+/// A reference to a frame, keeping its image from being reused by the decoder
+/// while a texture aliasing its memory is alive.
+struct FrameRef(*mut ffmpeg::AVFrame);
+
+// The reference is only ever freed, which FFmpeg allows from any thread.
+unsafe impl Send for FrameRef {}
+unsafe impl Sync for FrameRef {}
+
+impl Drop for FrameRef {
+    fn drop(&mut self) {
+        unsafe { ffmpeg::av_frame_free(&raw mut self.0) };
+    }
+}
+
+/// Import a `AV_PIX_FMT_VULKAN` frame by exporting the memory of its image as
+/// an opaque file descriptor and binding it to a new image on the wgpu device.
+///
+/// The texture has the size of the frame pool, which can be larger than the
+/// frame itself when the decoder pads the coded size.
+///
+/// # Safety
+///
+/// `frame` must be a valid `AV_PIX_FMT_VULKAN` frame.
+pub(super) unsafe fn import(
+    frame: &ffmpeg::AVFrame,
+    device: &wgpu::Device,
+) -> Result<wgpu::Texture, error::FFmpegError> {
+    let frames_ctx_ptr = unsafe {
+        (*frame.hw_frames_ctx)
+            .data
+            .cast::<ffmpeg::AVHWFramesContext>()
+    };
+    let frames_ctx = unsafe { &*frames_ctx_ptr };
+    let vk_frames_ctx = unsafe { &*frames_ctx.hwctx.cast::<AVVulkanFramesContext>() };
+    let vk_device_ctx = unsafe {
+        &*(*frames_ctx.device_ctx)
+            .hwctx
+            .cast::<AVVulkanDeviceContext>()
+    };
+    let vk_frame_ptr = frame.data[0].cast::<AVVkFrame>();
+    let vk_frame = unsafe { &*vk_frame_ptr };
+
+    // Frames with an image per plane cannot be represented by a single texture.
+    if vk_frames_ctx.format[1] != vk::Format::UNDEFINED
+        || vk_frames_ctx.nb_layers > 1
+        || vk_frame.mem[1] != vk::DeviceMemory::null()
+    {
+        return Err(error::FFmpegError::custom(
+            "only single image Vulkan frames can be imported",
+        ));
+    }
+
+    let format = super::texture_format(frames_ctx.sw_format, device)?;
+    let desc = super::texture_descriptor(
+        frames_ctx.width as u32,
+        frames_ctx.height as u32,
+        format,
+    );
+
+    let Some(hal_device) = (unsafe { device.as_hal::<Vulkan>() }) else {
+        return Err(error::FFmpegError::custom(
+            "device does not use the Vulkan backend",
+        ));
+    };
+    if !hal_device
+        .enabled_device_extensions()
+        .contains(&ash::khr::external_memory_fd::NAME)
+    {
+        return Err(error::FFmpegError::custom(
+            "device does not support importing memory from a file descriptor",
+        ));
+    }
+
+    let static_fn = ash::StaticFn {
+        get_instance_proc_addr: vk_device_ctx.get_proc_addr,
+    };
+    let ff_instance = unsafe { ash::Instance::load(&static_fn, vk_device_ctx.inst) };
+    let ff_device =
+        unsafe { ash::Device::load(ff_instance.fp_v1_0(), vk_device_ctx.act_dev) };
+
+    // Opaque memory handles can only be shared between devices using the same
+    // physical device and driver.
+    let instance = hal_device.shared_instance().raw_instance();
+    let physical_device = hal_device.raw_physical_device();
+    let is_same_device = unsafe {
+        device_uuids(&ff_instance, vk_device_ctx.phys_dev)
+            == device_uuids(instance, physical_device)
+    };
+    if !is_same_device {
+        return Err(error::FFmpegError::custom(
+            "FFmpeg and wgpu use different Vulkan physical devices",
+        ));
+    }
+
+    if let Some(lock_frame) = vk_frames_ctx.lock_frame {
+        unsafe { lock_frame(frames_ctx_ptr, vk_frame_ptr) };
+    }
+    let exported = unsafe { export_memory(&ff_instance, &ff_device, vk_frame) };
+    if let Some(unlock_frame) = vk_frames_ctx.unlock_frame {
+        unsafe { unlock_frame(frames_ctx_ptr, vk_frame_ptr) };
+    }
+    let fd = exported?;
+
+    let frame_ref = unsafe { ffmpeg::av_frame_clone(frame) };
+    if frame_ref.is_null() {
+        return Err(error::FFmpegError::custom("failed to reference frame"));
+    }
+    let frame_ref = FrameRef(frame_ref);
+
+    let raw_device = hal_device.raw_device().clone();
+
+    // Video usages need a video profile, which the wgpu device does not have.
+    let usage = vk_frames_ctx.usage
+        & (vk::ImageUsageFlags::SAMPLED
+            | vk::ImageUsageFlags::STORAGE
+            | vk::ImageUsageFlags::TRANSFER_SRC
+            | vk::ImageUsageFlags::TRANSFER_DST);
+    let mut flags = vk::ImageCreateFlags::ALIAS;
+    if format.is_multi_planar_format() {
+        flags |=
+            vk::ImageCreateFlags::MUTABLE_FORMAT | vk::ImageCreateFlags::EXTENDED_USAGE;
+    }
+
+    let mut external_info = vk::ExternalMemoryImageCreateInfo::default()
+        .handle_types(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD);
+    let image_info = vk::ImageCreateInfo::default()
+        .push_next(&mut external_info)
+        .flags(flags)
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk_frames_ctx.format[0])
+        .extent(vk::Extent3D {
+            width: desc.size.width,
+            height: desc.size.height,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk_frame.tiling)
+        .usage(usage | vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED);
+    let image = unsafe { raw_device.create_image(&image_info, None) }
+        .map_err(|err| vulkan_error("creating image", err))?;
+
+    let memory = unsafe {
+        import_memory(
+            instance,
+            physical_device,
+            &raw_device,
+            image,
+            fd,
+            vk_frame.size[0],
+        )
+    };
+    let memory = match memory {
+        Ok(memory) => memory,
+        Err(err) => {
+            unsafe { raw_device.destroy_image(image, None) };
+            return Err(err);
+        },
+    };
+
+    let drop_device = raw_device.clone();
+    let drop_callback: wgpu::hal::DropCallback = Box::new(move || unsafe {
+        drop_device.destroy_image(image, None);
+        drop_device.free_memory(memory, None);
+        drop(frame_ref);
+    });
+
+    let hal_desc = super::hal_texture_descriptor(&desc);
+    let hal_texture =
+        unsafe { hal_device.texture_from_raw(image, &hal_desc, Some(drop_callback)) };
+    drop(hal_device);
+
+    Ok(unsafe { device.create_texture_from_hal::<Vulkan>(hal_texture, &desc) })
+}
+
+/// Wait for the decoder to finish writing the frame, then export the memory
+/// backing its image.
+unsafe fn export_memory(
+    ff_instance: &ash::Instance,
+    ff_device: &ash::Device,
+    vk_frame: &AVVkFrame,
+) -> Result<OwnedFd, error::FFmpegError> {
+    // FFmpeg only exports memory when the extension is enabled on its device.
+    let get_memory_fd = unsafe {
+        ff_instance
+            .get_device_proc_addr(ff_device.handle(), c"vkGetMemoryFdKHR".as_ptr())
+    };
+    if get_memory_fd.is_none() {
+        return Err(error::FFmpegError::custom(
+            "FFmpeg Vulkan device cannot export memory",
+        ));
+    }
+
+    let wait_info = vk::SemaphoreWaitInfo::default()
+        .semaphores(&vk_frame.sem[..1])
+        .values(&vk_frame.sem_value[..1]);
+    unsafe { ff_device.wait_semaphores(&wait_info, u64::MAX) }
+        .map_err(|err| vulkan_error("waiting for frame", err))?;
+
+    let fd_device = ash::khr::external_memory_fd::Device::new(ff_instance, ff_device);
+    let fd_info = vk::MemoryGetFdInfoKHR::default()
+        .memory(vk_frame.mem[0])
+        .handle_type(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD);
+    let fd = unsafe { fd_device.get_memory_fd(&fd_info) }
+        .map_err(|err| vulkan_error("exporting memory", err))?;
+
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Import the exported memory and bind it to `image`, taking ownership of `fd`
+/// on success.
+unsafe fn import_memory(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    device: &ash::Device,
+    image: vk::Image,
+    fd: OwnedFd,
+    allocation_size: usize,
+) -> Result<vk::DeviceMemory, error::FFmpegError> {
+    let mut dedicated_requirements = vk::MemoryDedicatedRequirements::default();
+    let mut requirements =
+        vk::MemoryRequirements2::default().push_next(&mut dedicated_requirements);
+    let requirements_info = vk::ImageMemoryRequirementsInfo2::default().image(image);
+    unsafe {
+        device.get_image_memory_requirements2(&requirements_info, &mut requirements)
+    };
+    let memory_type_bits = requirements.memory_requirements.memory_type_bits;
+
+    // Match FFmpeg, which uses a dedicated allocation whenever it is preferred.
+    let use_dedicated = dedicated_requirements.prefers_dedicated_allocation == vk::TRUE
+        || dedicated_requirements.requires_dedicated_allocation == vk::TRUE;
+
+    let memory_properties =
+        unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    let memory_type_index = memory_properties
+        .memory_types_as_slice()
+        .iter()
+        .enumerate()
+        .find(|&(index, memory_type)| {
+            memory_type_bits & (1 << index) != 0
+                && memory_type
+                    .property_flags
+                    .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        })
+        .map(|(index, _)| index)
+        .ok_or_else(|| {
+            error::FFmpegError::custom("no device local memory type for the image")
+        })?;
+
+    let mut import_info = vk::ImportMemoryFdInfoKHR::default()
+        .handle_type(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD)
+        .fd(fd.as_raw_fd());
+    let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::default().image(image);
+    let mut allocate_info = vk::MemoryAllocateInfo::default()
+        .allocation_size(allocation_size as u64)
+        .memory_type_index(memory_type_index as u32)
+        .push_next(&mut import_info);
+    if use_dedicated {
+        allocate_info = allocate_info.push_next(&mut dedicated_info);
+    }
+
+    let memory = unsafe { device.allocate_memory(&allocate_info, None) }
+        .map_err(|err| vulkan_error("importing memory", err))?;
+    // A successful import transfers ownership of the descriptor to the driver.
+    let _ = fd.into_raw_fd();
+
+    if let Err(err) = unsafe { device.bind_image_memory(image, memory, 0) } {
+        unsafe { device.free_memory(memory, None) };
+        return Err(vulkan_error("binding memory", err));
+    }
+
+    Ok(memory)
+}
+
+/// Returns the device and driver UUIDs of the physical device.
+unsafe fn device_uuids(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> ([u8; vk::UUID_SIZE], [u8; vk::UUID_SIZE]) {
+    let mut id_properties = vk::PhysicalDeviceIDProperties::default();
+    let mut properties =
+        vk::PhysicalDeviceProperties2::default().push_next(&mut id_properties);
+    unsafe {
+        instance.get_physical_device_properties2(physical_device, &mut properties)
+    };
+    (id_properties.device_uuid, id_properties.driver_uuid)
+}
+
+fn vulkan_error(context: &str, err: vk::Result) -> error::FFmpegError {
+    error::FFmpegError::custom(format!("{context}: {err}"))
+}
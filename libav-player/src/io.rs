@@ -0,0 +1,181 @@
+use std::ffi::{c_int, c_void};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::panic::{self, AssertUnwindSafe};
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::error;
+
+/// The size of the buffer FFmpeg reads into before parsing.
+const IO_BUFFER_SIZE: usize = 32 * 1024;
+
+const SEEK_SET: c_int = 0;
+const SEEK_CUR: c_int = 1;
+const SEEK_END: c_int = 2;
+
+/// A reader which can be bridged into FFmpeg's IO system.
+pub(crate) trait MediaReader: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> MediaReader for T {}
+
+type BoxedReader = Box<dyn MediaReader>;
+
+/// A custom [ffmpeg::AVIOContext] which reads data from a Rust reader
+/// rather than a URL FFmpeg opens itself.
+///
+/// The context must outlive the [ffmpeg::AVFormatContext] it is attached to.
+pub(crate) struct CustomIo {
+    ctx: *mut ffmpeg::AVIOContext,
+    reader: *mut BoxedReader,
+}
+
+impl CustomIo {
+    /// Create a new IO context reading from the given buffer.
+    pub(crate) fn from_memory(data: Vec<u8>) -> Result<Self, error::FFmpegError> {
+        Self::from_reader(Cursor::new(data))
+    }
+
+    /// Create a new IO context reading from the given reader.
+    ///
+    /// If the reader is unable to report its current position, it is treated
+    /// as non-seekable and FFmpeg will only read the stream linearly.
+    pub(crate) fn from_reader<R: MediaReader + 'static>(
+        reader: R,
+    ) -> Result<Self, error::FFmpegError> {
+        let mut reader: BoxedReader = Box::new(reader);
+        let is_seekable = reader.stream_position().is_ok();
+        let reader = Box::into_raw(Box::new(reader));
+
+        let buffer = unsafe { ffmpeg::av_malloc(IO_BUFFER_SIZE) };
+        if buffer.is_null() {
+            drop(unsafe { Box::from_raw(reader) });
+            return Err(error::FFmpegError::custom("failed to allocate IO buffer"));
+        }
+
+        let ctx = unsafe {
+            ffmpeg::avio_alloc_context(
+                buffer.cast(),
+                IO_BUFFER_SIZE as c_int,
+                0,
+                reader.cast(),
+                Some(read_packet),
+                None,
+                if is_seekable { Some(seek) } else { None },
+            )
+        };
+        if ctx.is_null() {
+            unsafe { ffmpeg::av_free(buffer) };
+            drop(unsafe { Box::from_raw(reader) });
+            return Err(error::FFmpegError::custom("failed to allocate IO context"));
+        }
+
+        if !is_seekable {
+            tracing::debug!("reader is not seekable, only linear reads are possible");
+        }
+
+        Ok(Self { ctx, reader })
+    }
+
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *mut ffmpeg::AVIOContext {
+        self.ctx
+    }
+}
+
+impl Drop for CustomIo {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.ctx.is_null() {
+                // FFmpeg may have replaced the buffer we originally provided,
+                // so we must free whatever the context currently holds.
+                ffmpeg::av_freep((&raw mut (*self.ctx).buffer).cast());
+                ffmpeg::avio_context_free(&raw mut self.ctx);
+            }
+            drop(Box::from_raw(self.reader));
+        }
+    }
+}
+
+unsafe extern "C" fn read_packet(
+    opaque: *mut c_void,
+    buf: *mut u8,
+    buf_size: c_int,
+) -> c_int {
+    let reader = unsafe { &mut *opaque.cast::<BoxedReader>() };
+    let buf = unsafe { std::slice::from_raw_parts_mut(buf, buf_size as usize) };
+
+    // Unwinding across the FFI boundary would abort, so user reader panics
+    // are reported to FFmpeg as an IO error instead.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        loop {
+            match reader.read(buf) {
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                other => break other,
+            }
+        }
+    }));
+
+    match result {
+        Ok(Ok(0)) => ffmpeg::AVERROR_EOF,
+        Ok(Ok(n)) => n as c_int,
+        // Readers with a timeout, i.e. sockets, report it via either kind
+        // depending on the platform, both are surfaced as a recoverable timeout.
+        Ok(Err(err)) if err.kind() == std::io::ErrorKind::TimedOut => {
+            -(ffmpeg::ETIMEDOUT as c_int)
+        },
+        Ok(Err(err)) if err.kind() == std::io::ErrorKind::WouldBlock => {
+            -(ffmpeg::EAGAIN as c_int)
+        },
+        Ok(Err(err)) => {
+            tracing::debug!(error = %err, "reader returned an error");
+            -(ffmpeg::EIO as c_int)
+        },
+        Err(_) => {
+            tracing::error!("reader panicked while reading");
+            -(ffmpeg::EIO as c_int)
+        },
+    }
+}
+
+unsafe extern "C" fn seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let reader = unsafe { &mut *opaque.cast::<BoxedReader>() };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        if whence & ffmpeg::AVSEEK_SIZE as c_int != 0 {
+            return stream_len(reader.as_mut());
+        }
+
+        let position = match whence & !(ffmpeg::AVSEEK_FORCE as c_int) {
+            SEEK_SET => SeekFrom::Start(offset as u64),
+            SEEK_CUR => SeekFrom::Current(offset),
+            SEEK_END => SeekFrom::End(offset),
+            _ => return Err(std::io::ErrorKind::InvalidInput.into()),
+        };
+        reader.seek(position)
+    }));
+
+    match result {
+        Ok(Ok(position)) => position as i64,
+        Ok(Err(err)) if err.kind() == std::io::ErrorKind::InvalidInput => {
+            -(ffmpeg::EINVAL as i64)
+        },
+        Ok(Err(err)) => {
+            tracing::debug!(error = %err, "reader failed to seek");
+            -(ffmpeg::EIO as i64)
+        },
+        Err(_) => {
+            tracing::error!("reader panicked while seeking");
+            -(ffmpeg::EIO as i64)
+        },
+    }
+}
+
+/// Returns the total length of the stream, restoring the original position afterward.
+fn stream_len(reader: &mut dyn MediaReader) -> std::io::Result<u64> {
+    let position = reader.stream_position()?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    if position != len {
+        reader.seek(SeekFrom::Start(position))?;
+    }
+    Ok(len)
+}
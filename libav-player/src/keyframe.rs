@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The location of a single keyframe within an [InputSource](crate::InputSource).
+pub struct Keyframe {
+    /// The presentation timestamp of the keyframe.
+    pub pts: Duration,
+    /// The byte offset of the keyframe's packet within the source.
+    pub byte_pos: u64,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The keyframes of a single stream, sorted by their PTS.
+///
+/// This is built once via [InputSource::build_keyframe_index](crate::InputSource::build_keyframe_index)
+/// and used to jump straight to the nearest keyframe via
+/// [MediaPlayer::seek_to_keyframe](crate::MediaPlayer::seek_to_keyframe),
+/// which amortizes the cost of seeking for tools which seek repeatedly.
+pub struct KeyframeIndex {
+    stream_index: usize,
+    keyframes: Vec<Keyframe>,
+}
+
+impl KeyframeIndex {
+    pub(crate) fn new(stream_index: usize, mut keyframes: Vec<Keyframe>) -> Self {
+        // Packets are stored in decode order, which may not match the PTS order.
+        keyframes.sort_by_key(|keyframe| keyframe.pts);
+        keyframes.dedup_by_key(|keyframe| keyframe.pts);
+        Self {
+            stream_index,
+            keyframes,
+        }
+    }
+
+    #[inline]
+    /// Returns the index of the stream the keyframes belong to.
+    pub fn stream_index(&self) -> usize {
+        self.stream_index
+    }
+
+    #[inline]
+    /// Returns all keyframes sorted by their PTS.
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    #[inline]
+    /// Returns the number of keyframes in the index.
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    #[inline]
+    /// Returns if the index does not contain any keyframes.
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Returns the last keyframe at or before the given position.
+    ///
+    /// Positions before the first keyframe return the first keyframe,
+    /// `None` is only returned if the index is empty.
+    pub fn find(&self, position: Duration) -> Option<&Keyframe> {
+        let following = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.pts <= position);
+        self.keyframes.get(following.saturating_sub(1))
+    }
+}
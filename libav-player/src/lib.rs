@@ -1,29 +1,79 @@
 mod accelerator;
+mod bitstream;
+mod capabilities;
+mod channel;
 mod codec;
+mod color;
 mod error;
 mod filter;
+mod handle;
 mod input;
+#[cfg(feature = "wgpu")]
+mod interop;
+mod io;
+mod keyframe;
+mod logging;
+mod loudness;
+mod packet;
 mod player;
+mod remux;
+mod resample;
 mod stream;
+#[cfg(test)]
+mod test_support;
 
+use std::ptr;
 use std::time::Duration;
 
 use rusty_ffmpeg::ffi as ffmpeg;
 
-pub use self::accelerator::{Accelerator, AcceleratorConfig};
-pub use self::error::{FFmpegError, PlayerError, Result};
-pub use self::input::InputSource;
+pub use self::accelerator::{Accelerator, AcceleratorConfig, DeviceInfo};
+pub use self::bitstream::BitstreamExtractor;
+pub use self::capabilities::{
+    CodecDescriptor,
+    FFmpegBuildInfo,
+    FormatDescriptor,
+    LibraryVersion,
+    ffmpeg_info,
+    supported_decoders,
+    supported_input_formats,
+};
+pub use self::channel::{ChannelLayout, ChannelPosition};
+pub use self::color::{
+    ColorPrimaries,
+    ColorRange,
+    ColorSpace,
+    ColorTransfer,
+    Hdr10Metadata,
+};
+pub use self::error::{FFmpegError, FFmpegErrorKind, PlayerError, Result};
+pub use self::handle::PlayerHandle;
+pub use self::input::{
+    AttachedPicture,
+    Attachment,
+    Chapter,
+    InputSource,
+    MediaInfo,
+    ProgramInfo,
+};
+pub use self::keyframe::{Keyframe, KeyframeIndex};
+pub use self::logging::init_logging;
+pub use self::loudness::LoudnessStats;
+pub use self::packet::{Packet, PacketInfo};
 pub use self::player::{
     AudioFrame,
     DecodedFrame,
     Frame,
     MediaPlayer,
     MediaPlayerBuilder,
+    PlayerStatistics,
     SubtitleFrame,
     VideoFrame,
 };
+pub use self::stream::{Fraction, Resolution, StreamDisposition, StreamInfo};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MediaType {
     /// Video data.
     Video,
@@ -66,6 +116,7 @@ impl From<ffmpeg::AVMediaType> for MediaType {
 }
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The pixel format describes how image data is organized and represented.
 ///
 /// This is used as a _target_ for rendering output, it is normally recommended
@@ -93,6 +144,21 @@ pub enum OutputPixelFormat {
     /// This format is more bandwidth intensive than RGBA and NV12, high FPS videos can
     /// have significant compute costs.
     P010le,
+    /// A tightly packed 8-bit RGB format without an alpha channel.
+    ///
+    /// This is mostly for CPU-side consumers like image encoders which expect
+    /// 3 bytes per pixel, it has the same costs as RGBA.
+    Rgb24,
+    /// Like [OutputPixelFormat::Rgba] but with the red and blue channels swapped.
+    ///
+    /// Some UI toolkits and platform surfaces use this as their native format,
+    /// avoiding an extra swizzle when uploading the frame.
+    Bgra,
+    /// Planar 8-bit YUV 4:2:0, also known as I420.
+    ///
+    /// This has the same bandwidth costs as NV12 but stores the U and V
+    /// components in separate planes, which some software renderers expect.
+    Yuv420p,
 }
 
 impl OutputPixelFormat {
@@ -101,6 +167,9 @@ impl OutputPixelFormat {
             ffmpeg::AV_PIX_FMT_NV12 => Some(Self::Nv12),
             ffmpeg::AV_PIX_FMT_RGBA => Some(Self::Rgba),
             ffmpeg::AV_PIX_FMT_P010LE => Some(Self::P010le),
+            ffmpeg::AV_PIX_FMT_RGB24 => Some(Self::Rgb24),
+            ffmpeg::AV_PIX_FMT_BGRA => Some(Self::Bgra),
+            ffmpeg::AV_PIX_FMT_YUV420P => Some(Self::Yuv420p),
             _ => None,
         }
     }
@@ -110,6 +179,9 @@ impl OutputPixelFormat {
             OutputPixelFormat::Nv12 => ffmpeg::AV_PIX_FMT_NV12,
             OutputPixelFormat::Rgba => ffmpeg::AV_PIX_FMT_RGBA,
             OutputPixelFormat::P010le => ffmpeg::AV_PIX_FMT_P010LE,
+            OutputPixelFormat::Rgb24 => ffmpeg::AV_PIX_FMT_RGB24,
+            OutputPixelFormat::Bgra => ffmpeg::AV_PIX_FMT_BGRA,
+            OutputPixelFormat::Yuv420p => ffmpeg::AV_PIX_FMT_YUV420P,
         };
         unsafe {
             let descriptor = ffmpeg::av_pix_fmt_desc_get(av_pix_fmt);
@@ -122,11 +194,126 @@ impl OutputPixelFormat {
             OutputPixelFormat::Nv12 => "nv12",
             OutputPixelFormat::Rgba => "rgba",
             OutputPixelFormat::P010le => "p010le",
+            OutputPixelFormat::Rgb24 => "rgb24",
+            OutputPixelFormat::Bgra => "bgra",
+            OutputPixelFormat::Yuv420p => "yuv420p",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// How the video is scaled to the resolution set via
+/// [MediaPlayerBuilder::with_target_resolution].
+pub enum ScalingMode {
+    #[default]
+    /// Scale the video to fit within the target resolution while preserving
+    /// the aspect ratio.
+    ///
+    /// The output may be smaller than the target in one dimension, leaving
+    /// it to the renderer to letterbox the frame.
+    Fit,
+    /// Scale the video to cover the target resolution while preserving
+    /// the aspect ratio, cropping any edges that overflow.
+    Fill,
+    /// Scale the video to exactly the target resolution, ignoring the aspect ratio.
+    Stretch,
+}
+
+impl ScalingMode {
+    /// Returns the resolution of the frames produced when scaling the `source`
+    /// resolution to the `target` resolution.
+    ///
+    /// This mirrors the rounding FFmpeg's `scale` filter performs, keeping both
+    /// dimensions even so the output can be chroma subsampled.
+    pub(crate) fn output_resolution(
+        &self,
+        source: Resolution,
+        target: Resolution,
+    ) -> Resolution {
+        if *self != ScalingMode::Fit || source.width == 0 || source.height == 0 {
+            return target;
+        }
+
+        let rescale = |a: usize, b: usize, c: usize| (a * b + c / 2) / c;
+        let width = rescale(target.height, source.width, source.height * 2) * 2;
+        let height = rescale(target.width, source.height, source.width * 2) * 2;
+
+        Resolution {
+            width: width.min(target.width) / 2 * 2,
+            height: height.min(target.height) / 2 * 2,
+        }
+    }
+
+    pub(crate) fn to_filter_args(&self, target: Resolution) -> String {
+        let Resolution { width, height } = target;
+        match self {
+            ScalingMode::Fit => format!(
+                "w={width}:h={height}:force_original_aspect_ratio=decrease:\
+                force_divisible_by=2"
+            ),
+            ScalingMode::Fill => format!(
+                "w={width}:h={height}:force_original_aspect_ratio=increase:\
+                force_divisible_by=2"
+            ),
+            ScalingMode::Stretch => format!("w={width}:h={height}"),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// What to do when the video exceeds the resolution set via
+/// [MediaPlayerBuilder::with_max_resolution].
+pub enum OnExceed {
+    #[default]
+    /// Fail to build the player with [PlayerError::ResolutionTooLarge].
+    Error,
+    /// Scale the video down to fit within the maximum resolution.
+    Downscale,
+}
+
+/// Returns the target resolution and [ScalingMode] which keeps the output of
+/// scaling the `source` resolution within `max`.
+///
+/// Returns `None` if the output already fits within `max`.
+pub(crate) fn cap_target_resolution(
+    source: Resolution,
+    target: Option<(Resolution, ScalingMode)>,
+    max: Resolution,
+) -> Option<(Resolution, ScalingMode)> {
+    let output = match target {
+        Some((target, mode)) => mode.output_resolution(source, target),
+        None => source,
+    };
+    if output.width <= max.width && output.height <= max.height {
+        return None;
+    }
+
+    let capped = match target {
+        None => (max, ScalingMode::Fit),
+        Some((target, ScalingMode::Fit)) => {
+            let target = Resolution {
+                width: target.width.min(max.width),
+                height: target.height.min(max.height),
+            };
+            (target, ScalingMode::Fit)
+        },
+        Some((target, mode)) => {
+            // Both modes produce exactly the target resolution, so it is shrunk
+            // uniformly to keep the same aspect ratio.
+            let factor = (max.width as f64 / target.width as f64)
+                .min(max.height as f64 / target.height as f64);
+            let target = Resolution {
+                width: ((target.width as f64 * factor) as usize).max(1),
+                height: ((target.height as f64 * factor) as usize).max(1),
+            };
+            (target, mode)
+        },
+    };
+    Some(capped)
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The audio sample format.
 pub enum SampleFormat {
     /// Unsigned 8 bit.
@@ -206,6 +393,178 @@ impl SampleFormat {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// The picture type of a decoded video frame.
+pub enum PictureType {
+    #[default]
+    /// The picture type is unknown or not set.
+    None,
+    /// Intra coded, the frame can be decoded without referencing other frames.
+    I,
+    /// Predicted from previous frames.
+    P,
+    /// Bi-directionally predicted from previous and future frames.
+    B,
+    /// S(GMC)-VOP MPEG-4.
+    S,
+    /// Switching intra.
+    SI,
+    /// Switching predicted.
+    SP,
+    /// BI type.
+    BI,
+}
+
+impl PictureType {
+    pub(crate) fn from_av_picture_type(pict_type: ffmpeg::AVPictureType) -> Self {
+        match pict_type {
+            ffmpeg::AV_PICTURE_TYPE_I => Self::I,
+            ffmpeg::AV_PICTURE_TYPE_P => Self::P,
+            ffmpeg::AV_PICTURE_TYPE_B => Self::B,
+            ffmpeg::AV_PICTURE_TYPE_S => Self::S,
+            ffmpeg::AV_PICTURE_TYPE_SI => Self::SI,
+            ffmpeg::AV_PICTURE_TYPE_SP => Self::SP,
+            ffmpeg::AV_PICTURE_TYPE_BI => Self::BI,
+            _ => Self::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// Controls if playback restarts from the beginning once the end of the media is reached.
+///
+/// The PTS of frames keeps increasing across loops, with each loop being offset by
+/// the duration of the [InputSource], so they can be used as a monotonic clock.
+/// Seeking resets the offset, so frames are relative to the source again.
+pub enum LoopMode {
+    #[default]
+    /// Play the media once.
+    Off,
+    /// Play the media the given number of times in total.
+    Count(u32),
+    /// Loop the media until the player is dropped.
+    Forever,
+}
+
+impl LoopMode {
+    /// Returns if playback should restart after `loops_completed` loops have
+    /// already been played.
+    pub(crate) fn should_restart(&self, loops_completed: u32) -> bool {
+        match self {
+            LoopMode::Off => false,
+            LoopMode::Count(count) => loops_completed.saturating_add(1) < *count,
+            LoopMode::Forever => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// Bounds how much media [MediaPlayer::process_next_frame] produces before
+/// returning [PlayerError::EndOfStream], set via [MediaPlayer::set_decode_limit].
+///
+/// The limit is re-armed whenever it is set or the player seeks.
+pub enum DecodeLimit {
+    #[default]
+    /// Produce frames until the end of the media.
+    None,
+    /// Produce at most the given number of frames, counting frames of all types.
+    Frames(u64),
+    /// Produce frames until their PTS is the given duration past the PTS of the
+    /// first frame produced after the limit was armed.
+    Duration(Duration),
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// Controls where playback lands when seeking.
+pub enum SeekMode {
+    #[default]
+    /// Seek to the nearest key frame at or before the target position.
+    Backward,
+    /// Seek to the nearest key frame at or after the target position.
+    Forward,
+    /// Seek to the nearest key frame before the target position, then decode and
+    /// discard frames until the target position is reached.
+    ///
+    /// This is the most accurate mode but can be expensive when key frames are sparse.
+    Exact,
+    /// Seek to the nearest frame to the target position, even if it is not a key frame.
+    ///
+    /// This may produce visual artifacts until the next key frame.
+    Any,
+}
+
+impl SeekMode {
+    pub(crate) fn to_av_seek_flags(&self) -> i32 {
+        match self {
+            SeekMode::Backward | SeekMode::Exact => ffmpeg::AVSEEK_FLAG_BACKWARD as i32,
+            SeekMode::Forward => 0,
+            SeekMode::Any => ffmpeg::AVSEEK_FLAG_ANY as i32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// The number of threads used when decoding video in software.
+pub enum ThreadCount {
+    #[default]
+    /// Let FFmpeg pick the number of threads, this is typically the number of CPU cores.
+    ///
+    /// This sets `thread_count` to `0` on the decoder.
+    Auto,
+    /// Use a fixed number of threads.
+    Count(usize),
+}
+
+impl ThreadCount {
+    pub(crate) fn to_av_thread_count(self) -> i32 {
+        match self {
+            ThreadCount::Auto => 0,
+            ThreadCount::Count(count) => count.min(i32::MAX as usize) as i32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// The layout of decoded audio samples.
+pub struct AudioFormat {
+    /// The format of each sample.
+    pub sample_format: SampleFormat,
+    /// The number of samples per second for each channel.
+    pub sample_rate: u32,
+    /// The number of audio channels.
+    pub channels: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Describes how the video decoder was actually set up.
+pub struct VideoDecoderInfo {
+    /// The hardware accelerator in use, `None` if decoding in software.
+    pub accelerator: Option<Accelerator>,
+    /// The software pixel format the hardware decoder produces frames in.
+    ///
+    /// This is `None` when decoding in software, if the format is not one
+    /// of the supported [OutputPixelFormat]s, or if the decoder has not
+    /// negotiated a format yet (which happens once the first packet is decoded.)
+    pub hw_pix_fmt: Option<OutputPixelFormat>,
+    /// If the video is being decoded on hardware.
+    pub is_hardware: bool,
+    /// The accelerators from the [AcceleratorConfig] which were attempted, in order,
+    /// but could not be used, i.e. because the device is missing or does not
+    /// support the codec.
+    pub failed_accelerators: Vec<Accelerator>,
+}
+
+impl VideoDecoderInfo {
+    /// Returns if the decoder fell back to software decoding after
+    /// all the configured accelerators failed.
+    ///
+    /// Unlike checking [VideoDecoderInfo::is_hardware], this is `false` if no
+    /// accelerators were configured in the first place.
+    pub fn is_software_fallback(&self) -> bool {
+        !self.is_hardware && !self.failed_accelerators.is_empty()
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 /// The subtitle encoding type.
 pub enum SubtitleFormat {
@@ -215,18 +574,33 @@ pub enum SubtitleFormat {
     Ass,
     /// The subtitle is a bitmap image.
     Bitmap,
+    /// The subtitle has no content or the type is not known,
+    /// i.e. a subtitle which clears the previous one.
+    Unknown,
 }
 
 impl SubtitleFormat {
     #[inline]
-    pub(crate) fn try_from_subtitle_format(fmt: ffmpeg::AVSubtitleType) -> Option<Self> {
+    pub(crate) fn from_av_subtitle_type(fmt: ffmpeg::AVSubtitleType) -> Self {
         match fmt {
-            ffmpeg::SUBTITLE_TEXT => Some(Self::Text),
-            ffmpeg::SUBTITLE_ASS => Some(Self::Ass),
-            ffmpeg::SUBTITLE_BITMAP => Some(Self::Bitmap),
-            _ => None,
+            ffmpeg::SUBTITLE_TEXT => Self::Text,
+            ffmpeg::SUBTITLE_ASS => Self::Ass,
+            ffmpeg::SUBTITLE_BITMAP => Self::Bitmap,
+            _ => Self::Unknown,
         }
     }
+
+    #[inline]
+    /// Returns if the subtitle contains text, either plain or ASS.
+    pub fn is_text(&self) -> bool {
+        matches!(self, Self::Text | Self::Ass)
+    }
+
+    #[inline]
+    /// Returns if the subtitle contains bitmap images.
+    pub fn is_bitmap(&self) -> bool {
+        matches!(self, Self::Bitmap)
+    }
 }
 
 pub(crate) fn join_pixel_formats(formats: &[OutputPixelFormat]) -> String {
@@ -242,11 +616,592 @@ pub(crate) fn join_pixel_formats(formats: &[OutputPixelFormat]) -> String {
     output
 }
 
-pub(crate) fn pts_to_duration(ts: i64, time_base: ffmpeg::AVRational) -> Duration {
-    if ts == ffmpeg::AV_NOPTS_VALUE {
-        Duration::ZERO
+/// Returns every entry within a FFmpeg metadata dictionary.
+///
+/// # Safety
+/// The dictionary must be a valid pointer or null.
+pub(crate) unsafe fn get_all_metadata(
+    dict: *const ffmpeg::AVDictionary,
+) -> std::collections::BTreeMap<String, String> {
+    let mut metadata = std::collections::BTreeMap::new();
+    if dict.is_null() {
+        return metadata;
+    }
+
+    let mut entry = ptr::null();
+    loop {
+        entry = unsafe {
+            ffmpeg::av_dict_get(
+                dict,
+                c"".as_ptr(),
+                entry,
+                ffmpeg::AV_DICT_IGNORE_SUFFIX as i32,
+            )
+        };
+        if entry.is_null() {
+            break;
+        }
+
+        let (key, value) = unsafe {
+            (
+                std::ffi::CStr::from_ptr((*entry).key),
+                std::ffi::CStr::from_ptr((*entry).value),
+            )
+        };
+        metadata.insert(
+            key.to_string_lossy().into_owned(),
+            value.to_string_lossy().into_owned(),
+        );
+    }
+    metadata
+}
+
+/// Returns the value of the given key within a FFmpeg metadata dictionary.
+///
+/// # Safety
+/// The dictionary must be a valid pointer or null.
+pub(crate) unsafe fn get_metadata(
+    dict: *const ffmpeg::AVDictionary,
+    key: &std::ffi::CStr,
+) -> Option<String> {
+    if dict.is_null() {
+        return None;
+    }
+
+    let entry = unsafe { ffmpeg::av_dict_get(dict, key.as_ptr(), ptr::null(), 0) };
+    if entry.is_null() {
+        return None;
+    }
+
+    let value = unsafe { std::ffi::CStr::from_ptr((*entry).value) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+/// Returns the clockwise rotation in degrees described by a 3x3 display matrix,
+/// normalised to the range `0.0..360.0`.
+///
+/// Returns `0.0` if the matrix is degenerate.
+pub(crate) unsafe fn display_matrix_rotation(matrix: *const i32) -> f64 {
+    // FFmpeg reports the counter-clockwise rotation.
+    let rotation = -unsafe { ffmpeg::av_display_rotation_get(matrix) };
+    if rotation.is_nan() {
+        0.0
     } else {
-        let secs = (ts as f64 * time_base.num as f64) / time_base.den as f64;
-        Duration::from_secs_f64(secs)
+        // Avoid reporting `-0.0` or values like `359.9999` caused by rounding.
+        let rotation = rotation.rem_euclid(360.0);
+        if (360.0 - rotation).abs() < 1e-6 {
+            0.0
+        } else {
+            rotation + 0.0
+        }
+    }
+}
+
+/// The internal FFmpeg time base, timestamps in this base are in microseconds.
+pub(crate) const AV_TIME_BASE_Q: ffmpeg::AVRational = ffmpeg::AVRational {
+    num: 1,
+    den: ffmpeg::AV_TIME_BASE as i32,
+};
+
+/// Converts a timestamp in the given time base to a [Duration].
+///
+/// Returns [Duration::ZERO] if the timestamp is `AV_NOPTS_VALUE`, negative
+/// or the time base is invalid.
+pub(crate) fn pts_to_duration(ts: i64, time_base: ffmpeg::AVRational) -> Duration {
+    if ts == ffmpeg::AV_NOPTS_VALUE || time_base.den == 0 {
+        return Duration::ZERO;
+    }
+
+    let secs = (ts as f64 * time_base.num as f64) / time_base.den as f64;
+    Duration::try_from_secs_f64(secs).unwrap_or(Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaling_mode_output_resolution() {
+        let source = Resolution {
+            width: 1920,
+            height: 1080,
+        };
+        let target = Resolution {
+            width: 1280,
+            height: 1280,
+        };
+
+        let fit = ScalingMode::Fit.output_resolution(source, target);
+        assert_eq!(
+            fit,
+            Resolution {
+                width: 1280,
+                height: 720
+            }
+        );
+        assert_eq!(ScalingMode::Fill.output_resolution(source, target), target);
+        assert_eq!(
+            ScalingMode::Stretch.output_resolution(source, target),
+            target
+        );
+
+        let odd = ScalingMode::Fit.output_resolution(
+            source,
+            Resolution {
+                width: 1000,
+                height: 1000,
+            },
+        );
+        assert_eq!(
+            odd,
+            Resolution {
+                width: 1000,
+                height: 562
+            }
+        );
+    }
+
+    #[test]
+    fn test_filter_options_output_target() {
+        let max = Resolution {
+            width: 1280,
+            height: 1280,
+        };
+        let options = crate::filter::VideoFilterOptions {
+            crop: None,
+            target_resolution: None,
+            max_resolution: Some(max),
+        };
+
+        let small = Resolution {
+            width: 640,
+            height: 360,
+        };
+        assert_eq!(options.output_target(small), None);
+
+        // A frame exceeding the limit after the resolution changes mid-stream.
+        let large = Resolution {
+            width: 3840,
+            height: 2160,
+        };
+        assert_eq!(options.output_target(large), Some((max, ScalingMode::Fit)));
+
+        let target = Some((small, ScalingMode::Fill));
+        let options = crate::filter::VideoFilterOptions {
+            target_resolution: target,
+            ..options
+        };
+        assert_eq!(options.output_target(large), target);
+
+        let options = crate::filter::VideoFilterOptions {
+            max_resolution: None,
+            ..options
+        };
+        assert_eq!(options.output_target(large), target);
+    }
+
+    #[test]
+    fn test_cap_target_resolution() {
+        let source = Resolution {
+            width: 1920,
+            height: 1080,
+        };
+        let max = Resolution {
+            width: 1280,
+            height: 1280,
+        };
+
+        assert_eq!(cap_target_resolution(source, None, source), None);
+        assert_eq!(
+            cap_target_resolution(source, None, max),
+            Some((max, ScalingMode::Fit))
+        );
+
+        let small = Resolution {
+            width: 640,
+            height: 360,
+        };
+        assert_eq!(
+            cap_target_resolution(source, Some((small, ScalingMode::Fill)), max),
+            None
+        );
+
+        let large = Resolution {
+            width: 4000,
+            height: 2000,
+        };
+        let fit = cap_target_resolution(source, Some((large, ScalingMode::Fit)), max);
+        assert_eq!(
+            fit,
+            Some((
+                Resolution {
+                    width: 1280,
+                    height: 1280
+                },
+                ScalingMode::Fit
+            ))
+        );
+
+        let stretch =
+            cap_target_resolution(source, Some((large, ScalingMode::Stretch)), max);
+        assert_eq!(
+            stretch,
+            Some((
+                Resolution {
+                    width: 1280,
+                    height: 640
+                },
+                ScalingMode::Stretch
+            ))
+        );
+    }
+
+    #[test]
+    fn test_subtitle_format_from_av_subtitle_type() {
+        // (type, expected format, is text, is bitmap)
+        let cases = [
+            (ffmpeg::SUBTITLE_NONE, SubtitleFormat::Unknown, false, false),
+            (ffmpeg::SUBTITLE_BITMAP, SubtitleFormat::Bitmap, false, true),
+            (ffmpeg::SUBTITLE_TEXT, SubtitleFormat::Text, true, false),
+            (ffmpeg::SUBTITLE_ASS, SubtitleFormat::Ass, true, false),
+        ];
+
+        for (subtitle_type, expected, is_text, is_bitmap) in cases {
+            let format = SubtitleFormat::from_av_subtitle_type(subtitle_type);
+            assert_eq!(format, expected);
+            assert_eq!(format.is_text(), is_text, "{format:?}");
+            assert_eq!(format.is_bitmap(), is_bitmap, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_output_pixel_format_filter_names() {
+        let formats = [
+            OutputPixelFormat::Nv12,
+            OutputPixelFormat::Rgba,
+            OutputPixelFormat::P010le,
+            OutputPixelFormat::Rgb24,
+            OutputPixelFormat::Bgra,
+            OutputPixelFormat::Yuv420p,
+        ];
+
+        for format in formats {
+            let descriptor = format.descriptor().expect("descriptor should exist");
+            let name = unsafe { std::ffi::CStr::from_ptr(descriptor.name) };
+            assert_eq!(name.to_str().unwrap(), format.to_filter_name());
+        }
+
+        assert_eq!(
+            join_pixel_formats(&[OutputPixelFormat::Bgra, OutputPixelFormat::Rgb24]),
+            "bgra|rgb24",
+        );
+    }
+
+    const ALL_ACCELERATORS: [Accelerator; 9] = [
+        Accelerator::Vaapi,
+        Accelerator::Vdpau,
+        Accelerator::Cuda,
+        Accelerator::Qsv,
+        Accelerator::Vulkan,
+        Accelerator::Dxva2,
+        Accelerator::D3D11,
+        Accelerator::D3D12,
+        Accelerator::VideoToolbox,
+    ];
+
+    #[test]
+    fn test_accelerator_available_and_probe() {
+        let available = Accelerator::available();
+        for (i, accelerator) in available.iter().enumerate() {
+            assert!(
+                !available[..i].contains(accelerator),
+                "{accelerator:?} listed twice"
+            );
+            assert_eq!(
+                Accelerator::try_from_av_hw_device_type(
+                    accelerator.to_av_hw_device_type()
+                ),
+                Some(*accelerator),
+            );
+        }
+
+        // Accelerators FFmpeg was built without can never be initialised.
+        for accelerator in ALL_ACCELERATORS {
+            if !available.contains(&accelerator) {
+                assert!(!accelerator.probe(), "{accelerator:?} should not probe");
+            }
+        }
+    }
+
+    #[test]
+    fn test_accelerator_list_devices() {
+        // These accelerators do not support enumeration and use the default device.
+        for accelerator in [
+            Accelerator::Vdpau,
+            Accelerator::Qsv,
+            Accelerator::Dxva2,
+            Accelerator::D3D11,
+            Accelerator::D3D12,
+            Accelerator::VideoToolbox,
+        ] {
+            assert_eq!(accelerator.list_devices().unwrap(), Vec::new());
+        }
+
+        // Ordinals are probed in order and stop at the first missing device.
+        for accelerator in [Accelerator::Cuda, Accelerator::Vulkan] {
+            let devices = accelerator.list_devices().unwrap();
+            for (ordinal, device) in devices.iter().enumerate() {
+                assert_eq!(device.identifier, ordinal.to_string());
+            }
+        }
+
+        let devices = Accelerator::Vaapi.list_devices().unwrap();
+        if cfg!(target_os = "linux") {
+            assert!(devices.is_sorted_by(|a, b| a.identifier < b.identifier));
+            for device in devices {
+                assert!(
+                    device.identifier.starts_with("/dev/dri/renderD"),
+                    "unexpected device {device:?}"
+                );
+            }
+        } else {
+            assert!(devices.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_thread_count_to_av_thread_count() {
+        assert_eq!(ThreadCount::default(), ThreadCount::Auto);
+        assert_eq!(ThreadCount::Auto.to_av_thread_count(), 0);
+        assert_eq!(ThreadCount::Count(4).to_av_thread_count(), 4);
+        assert_eq!(
+            ThreadCount::Count(usize::MAX).to_av_thread_count(),
+            i32::MAX
+        );
+    }
+
+    #[test]
+    fn test_accelerator_supports_unknown_codec() {
+        for accelerator in Accelerator::available() {
+            assert!(!accelerator.supports_codec("not-a-codec"));
+        }
+
+        let mut config = AcceleratorConfig::default();
+        config.set_accelerators_for_codec("h264", &[]);
+        assert_eq!(config.best_accelerator_for("h264"), None);
+        assert_eq!(config.best_accelerator_for("not-a-codec"), None);
+    }
+
+    #[test]
+    fn test_accelerator_supports_codec() {
+        for accelerator in Accelerator::available() {
+            // QSV uses its own decoders rather than accelerating the native one.
+            if accelerator != Accelerator::Qsv {
+                assert!(
+                    accelerator.supports_codec("h264"),
+                    "{accelerator:?} should support h264"
+                );
+            }
+            // FFmpeg has no hardware decoding for audio codecs.
+            assert!(!accelerator.supports_codec("aac"));
+        }
+    }
+
+    #[test]
+    fn test_ffmpeg_info() {
+        let info = ffmpeg_info();
+        assert_eq!(
+            info.libavcodec_version.major,
+            ffmpeg::LIBAVCODEC_VERSION_MAJOR,
+        );
+        assert_eq!(
+            info.libavformat_version.major,
+            ffmpeg::LIBAVFORMAT_VERSION_MAJOR,
+        );
+        assert_eq!(
+            info.libavutil_version.major,
+            ffmpeg::LIBAVUTIL_VERSION_MAJOR
+        );
+        assert!(!info.version.is_empty());
+
+        let version = LibraryVersion {
+            major: 61,
+            minor: 19,
+            micro: 100,
+        };
+        assert_eq!(version.to_string(), "61.19.100");
+    }
+
+    #[test]
+    fn test_supported_decoders_and_formats() {
+        let decoders = supported_decoders();
+        let expected = [("h264", MediaType::Video), ("aac", MediaType::Audio)];
+        for (name, media_type) in expected {
+            assert!(
+                decoders
+                    .iter()
+                    .any(|codec| codec.name == name && codec.media_type == media_type),
+                "missing {name} decoder",
+            );
+        }
+
+        let formats = supported_input_formats();
+        assert!(
+            formats
+                .iter()
+                .any(|format| format.extensions.iter().any(|ext| ext == "mp4"))
+        );
+    }
+
+    #[test]
+    fn test_send_types() {
+        fn assert_send<T: Send>() {}
+
+        assert_send::<InputSource>();
+        assert_send::<MediaPlayer>();
+        assert_send::<DecodedFrame>();
+        assert_send::<VideoFrame>();
+        assert_send::<AudioFrame>();
+        assert_send::<SubtitleFrame>();
+        assert_send::<Packet>();
+    }
+
+    #[test]
+    fn test_display_matrix_rotation() {
+        for degrees in [0.0, 90.0, 180.0, 270.0] {
+            let mut matrix = [0i32; 9];
+            // FFmpeg expects the counter-clockwise rotation.
+            unsafe { ffmpeg::av_display_rotation_set(matrix.as_mut_ptr(), -degrees) };
+            let rotation = unsafe { display_matrix_rotation(matrix.as_ptr()) };
+            assert!(
+                (rotation - degrees).abs() < 1e-3,
+                "expected {degrees} got {rotation}"
+            );
+        }
+
+        let degenerate = [0i32; 9];
+        assert_eq!(unsafe { display_matrix_rotation(degenerate.as_ptr()) }, 0.0);
+    }
+
+    #[test]
+    fn test_pts_to_duration() {
+        let time_base = ffmpeg::AVRational { num: 1, den: 1000 };
+        assert_eq!(
+            pts_to_duration(1500, time_base),
+            Duration::from_millis(1500)
+        );
+        // `AV_NOPTS_VALUE` is `i64::MIN`.
+        assert_eq!(pts_to_duration(i64::MIN, time_base), Duration::ZERO);
+        assert_eq!(pts_to_duration(-1024, time_base), Duration::ZERO);
+
+        let invalid = ffmpeg::AVRational { num: 0, den: 0 };
+        assert_eq!(pts_to_duration(1500, invalid), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_player_error_from_errno() {
+        let to_player_error =
+            |errno: i32| PlayerError::from(error::FFmpegError::from_raw_errno(errno));
+
+        assert!(matches!(
+            to_player_error(-(ffmpeg::ETIMEDOUT as i32)),
+            PlayerError::NetworkTimeout(_)
+        ));
+        assert!(matches!(
+            to_player_error(ffmpeg::AVERROR_INVALIDDATA),
+            PlayerError::InvalidData(_)
+        ));
+        assert!(matches!(
+            to_player_error(-(ffmpeg::EIO as i32)),
+            PlayerError::Io(_)
+        ));
+        assert!(matches!(
+            to_player_error(-(ffmpeg::EACCES as i32)),
+            PlayerError::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            to_player_error(ffmpeg::AVERROR_EXIT),
+            PlayerError::Cancelled
+        ));
+
+        let err = to_player_error(ffmpeg::AVERROR_INVALIDDATA);
+        let raw = err.ffmpeg_error().expect("raw FFmpeg error is kept");
+        assert_eq!(raw.errno(), ffmpeg::AVERROR_INVALIDDATA);
+
+        let err = to_player_error(ffmpeg::AVERROR_UNKNOWN);
+        assert!(matches!(err, PlayerError::FFmpegError(_)));
+        assert!(PlayerError::EndOfStream.ffmpeg_error().is_none());
+    }
+
+    #[test]
+    fn test_ffmpeg_error_context() {
+        let err = error::FFmpegError::from_raw_errno(ffmpeg::AVERROR_INVALIDDATA);
+        assert_eq!(err.context(), None);
+
+        let err = err.with_context("opening video stream 0");
+        assert_eq!(err.context(), Some("opening video stream 0"));
+        assert_eq!(err.errno(), ffmpeg::AVERROR_INVALIDDATA);
+        assert!(err.to_string().contains("opening video stream 0"));
+        assert!(err.to_string().contains(err.message()));
+
+        let player_error = PlayerError::from(err);
+        let cloned = player_error.clone();
+        assert_eq!(cloned.to_string(), player_error.to_string());
+    }
+
+    #[test]
+    fn test_ffmpeg_error_kind() {
+        let kind = |errno: i32| error::FFmpegError::from_raw_errno(errno).kind();
+
+        assert_eq!(kind(ffmpeg::AVERROR_EOF), FFmpegErrorKind::Eof);
+        assert_eq!(kind(-(ffmpeg::EAGAIN as i32)), FFmpegErrorKind::Again);
+        assert_eq!(
+            kind(-(ffmpeg::ENOSYS as i32)),
+            FFmpegErrorKind::NotImplemented
+        );
+        assert_eq!(
+            kind(ffmpeg::AVERROR_INVALIDDATA),
+            FFmpegErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind(ffmpeg::AVERROR_STREAM_NOT_FOUND),
+            FFmpegErrorKind::StreamNotFound
+        );
+        assert_eq!(kind(-(ffmpeg::ETIMEDOUT as i32)), FFmpegErrorKind::Timeout);
+        assert_eq!(
+            kind(ffmpeg::AVERROR_UNKNOWN),
+            FFmpegErrorKind::Other(ffmpeg::AVERROR_UNKNOWN)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let source = InputSource::open_file("../media/test.mp4").unwrap();
+        let stream = source.stream(0);
+        assert!(stream.extradata().is_some());
+
+        let json = serde_json::to_string(&stream).unwrap();
+        let decoded: StreamInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{decoded:?}"), format!("{stream:?}"));
+        assert_eq!(decoded.extradata(), stream.extradata());
+        // The codec is skipped, as it is only meaningful within the process.
+        assert_eq!(decoded.codec_id, ffmpeg::AV_CODEC_ID_NONE);
+
+        let statistics = PlayerStatistics {
+            num_video_frames_decoded: 12,
+            num_audio_frames_decoded: 20,
+            packet_read_total: 40,
+            packet_read_time: Duration::from_millis(3),
+            frames_decoded_total: 32,
+            frames_decoded_time: Duration::from_millis(25),
+            dropped_frames: 1,
+            decode_fps: 29.5,
+            average_decode_latency: Duration::from_micros(850),
+            ..PlayerStatistics::default()
+        };
+        let json = serde_json::to_string(&statistics).unwrap();
+        let decoded: PlayerStatistics = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{decoded:?}"), format!("{statistics:?}"));
     }
 }
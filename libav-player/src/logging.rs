@@ -0,0 +1,98 @@
+use std::ffi::{CStr, c_char, c_int, c_void};
+use std::sync::Once;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+/// The maximum length of a single log line, longer lines are truncated.
+const MAX_LINE_LENGTH: usize = 1024;
+
+static INSTALL_LOG_CALLBACK: Once = Once::new();
+
+/// Forward the FFmpeg log messages to [tracing] rather than stderr.
+///
+/// Messages are emitted with the `ffmpeg` target at the level mapped from the
+/// FFmpeg log level, with the class name of the component which produced the
+/// message, i.e. `AVCodecContext`, as the `class` field.
+///
+/// Messages above the level set via `av_log_set_level` are still discarded,
+/// which is [AV_LOG_INFO](ffmpeg::AV_LOG_INFO) by default.
+///
+/// This can safely be called multiple times and from multiple threads,
+/// the callback is only installed once.
+pub fn init_logging() {
+    INSTALL_LOG_CALLBACK.call_once(|| unsafe {
+        ffmpeg::av_log_set_callback(Some(log_callback));
+    });
+}
+
+unsafe extern "C" fn log_callback(
+    avcl: *mut c_void,
+    level: c_int,
+    fmt: *const c_char,
+    vl: ffmpeg::va_list,
+) {
+    if level < 0 || level > unsafe { ffmpeg::av_log_get_level() } {
+        return;
+    }
+
+    let mut line = [0 as c_char; MAX_LINE_LENGTH];
+    // The class is emitted as a field, so the `[class @ ptr]` prefix is skipped.
+    let mut print_prefix = 0;
+    let result = unsafe {
+        ffmpeg::av_log_format_line2(
+            avcl,
+            level,
+            fmt,
+            vl,
+            line.as_mut_ptr(),
+            MAX_LINE_LENGTH as c_int,
+            &raw mut print_prefix,
+        )
+    };
+    if result < 0 {
+        return;
+    }
+
+    let message = unsafe { CStr::from_ptr(line.as_ptr()) }.to_string_lossy();
+    let message = message.trim_end();
+    if message.is_empty() {
+        return;
+    }
+
+    let class = unsafe { class_name(avcl) };
+    match level as u32 {
+        ..=ffmpeg::AV_LOG_ERROR => {
+            tracing::error!(target: "ffmpeg", class, "{message}");
+        },
+        ..=ffmpeg::AV_LOG_WARNING => {
+            tracing::warn!(target: "ffmpeg", class, "{message}");
+        },
+        ..=ffmpeg::AV_LOG_INFO => {
+            tracing::info!(target: "ffmpeg", class, "{message}");
+        },
+        ..=ffmpeg::AV_LOG_DEBUG => {
+            tracing::debug!(target: "ffmpeg", class, "{message}");
+        },
+        _ => {
+            tracing::trace!(target: "ffmpeg", class, "{message}");
+        },
+    }
+}
+
+/// Returns the name of the [ffmpeg::AVClass] of the log context, if any.
+///
+/// # Safety
+/// The `avcl` must be null or point to a struct whose first field is a
+/// pointer to an [ffmpeg::AVClass], as required by `av_log`.
+unsafe fn class_name<'a>(avcl: *mut c_void) -> Option<&'a str> {
+    if avcl.is_null() {
+        return None;
+    }
+
+    let class = unsafe { *avcl.cast::<*const ffmpeg::AVClass>() };
+    if class.is_null() || unsafe { (*class).class_name.is_null() } {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr((*class).class_name) }.to_str().ok()
+}
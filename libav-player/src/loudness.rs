@@ -0,0 +1,297 @@
+use std::ffi::{CStr, CString, c_char};
+use std::{mem, ptr};
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::error;
+
+/// The `ebur128` options, `metadata` attaches the running measurements to each
+/// frame so they can be read back from the sink.
+const EBUR128_ARGS: &CStr = c"peak=true:metadata=1";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The EBU R128 loudness of the audio analysed so far.
+pub struct LoudnessStats {
+    /// The integrated loudness in LUFS.
+    ///
+    /// This is gated as described by EBU R128, so it reads `-70.0` until
+    /// enough non-silent audio has been analysed.
+    pub integrated_lufs: f64,
+    /// The loudness range in LU, describing the variation in loudness.
+    pub loudness_range: f64,
+    /// The highest true peak across all channels in dBTP.
+    ///
+    /// This is negative infinity if the audio is silent.
+    pub true_peak: f64,
+}
+
+/// Measures the loudness of decoded audio frames using FFmpeg's `ebur128` filter.
+///
+/// The filter graph is configured lazily from the first frame it receives and
+/// will be rebuilt if the layout of the incoming audio changes mid-stream,
+/// which restarts the measurements.
+pub(crate) struct LoudnessAnalyzer {
+    filter_graph: *mut ffmpeg::AVFilterGraph,
+    buffer_src_ctx: *mut ffmpeg::AVFilterContext,
+    buffer_sink_ctx: *mut ffmpeg::AVFilterContext,
+    output: *mut ffmpeg::AVFrame,
+    input_format: i32,
+    input_sample_rate: i32,
+    input_layout: ffmpeg::AVChannelLayout,
+    stats: Option<LoudnessStats>,
+}
+
+impl LoudnessAnalyzer {
+    pub(crate) fn new() -> Result<Self, error::FFmpegError> {
+        let output = unsafe { ffmpeg::av_frame_alloc() };
+        if output.is_null() {
+            return Err(error::FFmpegError::custom("failed to allocate frame"));
+        }
+
+        Ok(Self {
+            filter_graph: ptr::null_mut(),
+            buffer_src_ctx: ptr::null_mut(),
+            buffer_sink_ctx: ptr::null_mut(),
+            output,
+            input_format: ffmpeg::AV_SAMPLE_FMT_NONE,
+            input_sample_rate: 0,
+            input_layout: unsafe { mem::zeroed() },
+            stats: None,
+        })
+    }
+
+    #[inline]
+    /// Returns the latest measurements, `None` until the filter has produced any.
+    pub(crate) fn stats(&self) -> Option<LoudnessStats> {
+        self.stats
+    }
+
+    /// Feed the frame through the filter and update the measurements.
+    ///
+    /// The filter takes its own reference to the frame, so the frame itself
+    /// is left untouched.
+    pub(crate) fn analyze(
+        &mut self,
+        frame: &ffmpeg::AVFrame,
+    ) -> Result<(), error::FFmpegError> {
+        if self.needs_configure(frame) {
+            self.configure(frame)?;
+        }
+
+        let result = unsafe {
+            ffmpeg::av_buffersrc_add_frame_flags(
+                self.buffer_src_ctx,
+                ptr::from_ref(frame).cast_mut(),
+                ffmpeg::AV_BUFFERSRC_FLAG_KEEP_REF as i32,
+            )
+        };
+        error::convert_ff_result(result)?;
+
+        loop {
+            let result = unsafe {
+                ffmpeg::av_buffersink_get_frame(self.buffer_sink_ctx, self.output)
+            };
+            match error::convert_ff_result(result) {
+                Ok(_) => {},
+                Err(err) if err.needs_data() || err.is_eof() => return Ok(()),
+                Err(err) => return Err(err),
+            }
+
+            self.read_metadata();
+            unsafe { ffmpeg::av_frame_unref(self.output) };
+        }
+    }
+
+    /// Update the measurements from the metadata of the output frame.
+    ///
+    /// The filter only attaches metadata once every 100ms of audio, frames
+    /// without any are skipped.
+    fn read_metadata(&mut self) {
+        let metadata = unsafe { (*self.output).metadata };
+        let integrated_lufs = unsafe { metadata_value(metadata, c"lavfi.r128.I") };
+        let loudness_range = unsafe { metadata_value(metadata, c"lavfi.r128.LRA") };
+        let true_peak = unsafe { metadata_value(metadata, c"lavfi.r128.true_peak") };
+
+        if let (Some(integrated_lufs), Some(loudness_range), Some(true_peak)) =
+            (integrated_lufs, loudness_range, true_peak)
+        {
+            self.stats = Some(LoudnessStats {
+                integrated_lufs,
+                loudness_range,
+                // The filter reports the peak as a linear amplitude.
+                true_peak: 20.0 * true_peak.log10(),
+            });
+        }
+    }
+
+    fn needs_configure(&self, frame: &ffmpeg::AVFrame) -> bool {
+        self.filter_graph.is_null()
+            || frame.format != self.input_format
+            || frame.sample_rate != self.input_sample_rate
+            || unsafe {
+                ffmpeg::av_channel_layout_compare(&frame.ch_layout, &self.input_layout)
+                    != 0
+            }
+    }
+
+    fn configure(&mut self, frame: &ffmpeg::AVFrame) -> Result<(), error::FFmpegError> {
+        self.reset();
+
+        if let Err(err) = self.create_filter_graph(frame) {
+            self.reset();
+            return Err(err);
+        }
+
+        let result = unsafe {
+            ffmpeg::av_channel_layout_copy(&mut self.input_layout, &frame.ch_layout)
+        };
+        if let Err(err) = error::convert_ff_result(result) {
+            self.reset();
+            return Err(err);
+        }
+        self.input_format = frame.format;
+        self.input_sample_rate = frame.sample_rate;
+
+        tracing::debug!(
+            input_sample_rate = frame.sample_rate,
+            input_channels = frame.ch_layout.nb_channels,
+            "configured loudness analyzer",
+        );
+
+        Ok(())
+    }
+
+    fn create_filter_graph(
+        &mut self,
+        frame: &ffmpeg::AVFrame,
+    ) -> Result<(), error::FFmpegError> {
+        self.filter_graph = unsafe { ffmpeg::avfilter_graph_alloc() };
+        if self.filter_graph.is_null() {
+            return Err(error::FFmpegError::custom(
+                "failed to allocate filter graph",
+            ));
+        }
+
+        let buffer_src_args = CString::new(buffer_src_args(frame)?).unwrap();
+        let buffer_src = unsafe { ffmpeg::avfilter_get_by_name(c"abuffer".as_ptr()) };
+        let ebur128 = unsafe { ffmpeg::avfilter_get_by_name(c"ebur128".as_ptr()) };
+        let buffer_sink =
+            unsafe { ffmpeg::avfilter_get_by_name(c"abuffersink".as_ptr()) };
+        if ebur128.is_null() {
+            return Err(error::FFmpegError::custom(
+                "ebur128 filter is not available",
+            ));
+        }
+
+        let mut ebur128_ctx = ptr::null_mut();
+        unsafe {
+            let result = ffmpeg::avfilter_graph_create_filter(
+                &raw mut self.buffer_src_ctx,
+                buffer_src,
+                c"in".as_ptr(),
+                buffer_src_args.as_ptr(),
+                ptr::null_mut(),
+                self.filter_graph,
+            );
+            error::convert_ff_result(result)?;
+
+            let result = ffmpeg::avfilter_graph_create_filter(
+                &raw mut ebur128_ctx,
+                ebur128,
+                c"ebur128".as_ptr(),
+                EBUR128_ARGS.as_ptr(),
+                ptr::null_mut(),
+                self.filter_graph,
+            );
+            error::convert_ff_result(result)?;
+
+            let result = ffmpeg::avfilter_graph_create_filter(
+                &raw mut self.buffer_sink_ctx,
+                buffer_sink,
+                c"out".as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                self.filter_graph,
+            );
+            error::convert_ff_result(result)?;
+
+            let result = ffmpeg::avfilter_link(self.buffer_src_ctx, 0, ebur128_ctx, 0);
+            error::convert_ff_result(result)?;
+            let result = ffmpeg::avfilter_link(ebur128_ctx, 0, self.buffer_sink_ctx, 0);
+            error::convert_ff_result(result)?;
+
+            let result =
+                ffmpeg::avfilter_graph_config(self.filter_graph, ptr::null_mut());
+            error::convert_ff_result(result)?;
+        }
+
+        Ok(())
+    }
+
+    /// Free the filter graph, discarding any measurements.
+    ///
+    /// The graph will be reconfigured on the next call to [LoudnessAnalyzer::analyze].
+    pub(crate) fn reset(&mut self) {
+        if !self.filter_graph.is_null() {
+            unsafe { ffmpeg::avfilter_graph_free(&raw mut self.filter_graph) };
+        }
+        // The filter contexts are owned by the graph.
+        self.buffer_src_ctx = ptr::null_mut();
+        self.buffer_sink_ctx = ptr::null_mut();
+        unsafe { ffmpeg::av_channel_layout_uninit(&mut self.input_layout) };
+        self.input_format = ffmpeg::AV_SAMPLE_FMT_NONE;
+        self.input_sample_rate = 0;
+        self.stats = None;
+    }
+}
+
+impl Drop for LoudnessAnalyzer {
+    fn drop(&mut self) {
+        self.reset();
+        unsafe { ffmpeg::av_frame_free(&raw mut self.output) };
+    }
+}
+
+/// Returns the `abuffer` arguments describing the format of the frame.
+fn buffer_src_args(frame: &ffmpeg::AVFrame) -> Result<String, error::FFmpegError> {
+    let sample_fmt = unsafe { ffmpeg::av_get_sample_fmt_name(frame.format) };
+    if sample_fmt.is_null() {
+        return Err(error::FFmpegError::custom("unknown audio sample format"));
+    }
+    let sample_fmt = unsafe { CStr::from_ptr(sample_fmt) }.to_string_lossy();
+
+    let mut layout = [0 as c_char; 64];
+    let result = unsafe {
+        ffmpeg::av_channel_layout_describe(
+            &frame.ch_layout,
+            layout.as_mut_ptr(),
+            layout.len(),
+        )
+    };
+    error::convert_ff_result(result)?;
+    let layout = unsafe { CStr::from_ptr(layout.as_ptr()) }.to_string_lossy();
+
+    Ok(format!(
+        "time_base=1/{rate}:sample_rate={rate}:sample_fmt={sample_fmt}:\
+        channel_layout={layout}",
+        rate = frame.sample_rate,
+    ))
+}
+
+/// Returns the value of the metadata entry parsed as a float, if present.
+///
+/// # Safety
+/// The `metadata` must be null or a valid dictionary.
+unsafe fn metadata_value(
+    metadata: *const ffmpeg::AVDictionary,
+    key: &CStr,
+) -> Option<f64> {
+    let entry = unsafe { ffmpeg::av_dict_get(metadata, key.as_ptr(), ptr::null(), 0) };
+    if entry.is_null() {
+        return None;
+    }
+
+    let value = unsafe { CStr::from_ptr((*entry).value) };
+    value.to_str().ok()?.trim().parse().ok()
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::{error, pts_to_duration};
+
+/// A raw, still encoded, packet read from an [InputSource](crate::InputSource).
+///
+/// See [InputSource::read_raw_packet](crate::InputSource::read_raw_packet).
+pub struct Packet {
+    inner: MediaPacket,
+    time_base: ffmpeg::AVRational,
+}
+
+impl std::fmt::Debug for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Packet")
+            .field("stream_index", &self.stream_index())
+            .field("pts", &self.pts())
+            .field("dts", &self.dts())
+            .field("is_keyframe", &self.is_keyframe())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+impl Packet {
+    /// Wrap the packet read from a stream with the given `time_base`.
+    pub(crate) fn new(inner: MediaPacket, time_base: ffmpeg::AVRational) -> Self {
+        Self { inner, time_base }
+    }
+
+    #[inline]
+    /// Returns the index of the stream the packet belongs to.
+    pub fn stream_index(&self) -> usize {
+        self.inner.stream_index as usize
+    }
+
+    /// Returns the presentation timestamp of the packet.
+    ///
+    /// Returns `None` if the timestamp is unknown.
+    pub fn pts(&self) -> Option<Duration> {
+        self.to_duration(self.inner.pts)
+    }
+
+    /// Returns the decoding timestamp of the packet.
+    ///
+    /// Returns `None` if the timestamp is unknown.
+    pub fn dts(&self) -> Option<Duration> {
+        self.to_duration(self.inner.dts)
+    }
+
+    #[inline]
+    /// Returns if the packet contains a keyframe.
+    pub fn is_keyframe(&self) -> bool {
+        self.inner.flags & ffmpeg::AV_PKT_FLAG_KEY as i32 != 0
+    }
+
+    #[inline]
+    /// Returns the size of the packet data in bytes.
+    pub fn size(&self) -> usize {
+        self.inner.size.max(0) as usize
+    }
+
+    /// Returns the encoded packet data.
+    pub fn data(&self) -> &[u8] {
+        if self.inner.data.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.inner.data, self.size()) }
+    }
+
+    #[inline]
+    pub(crate) fn as_av_packet(&self) -> &ffmpeg::AVPacket {
+        &self.inner
+    }
+
+    fn to_duration(&self, ts: i64) -> Option<Duration> {
+        ts_to_duration(ts, self.time_base)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// The timing of a packet about to be sent to one of the player's decoders.
+///
+/// See [MediaPlayerBuilder::on_packet](crate::MediaPlayerBuilder::on_packet).
+pub struct PacketInfo {
+    /// The index of the stream the packet belongs to.
+    pub stream_index: usize,
+    /// The presentation timestamp of the packet, `None` if unknown.
+    pub pts: Option<Duration>,
+    /// The decoding timestamp of the packet, `None` if unknown.
+    pub dts: Option<Duration>,
+    /// The size of the packet data in bytes.
+    pub size: usize,
+    /// If the packet contains a keyframe.
+    pub is_keyframe: bool,
+}
+
+impl PacketInfo {
+    pub(crate) fn new(packet: &ffmpeg::AVPacket, time_base: ffmpeg::AVRational) -> Self {
+        Self {
+            stream_index: packet.stream_index as usize,
+            pts: ts_to_duration(packet.pts, time_base),
+            dts: ts_to_duration(packet.dts, time_base),
+            size: packet.size.max(0) as usize,
+            is_keyframe: packet.flags & ffmpeg::AV_PKT_FLAG_KEY as i32 != 0,
+        }
+    }
+}
+
+fn ts_to_duration(ts: i64, time_base: ffmpeg::AVRational) -> Option<Duration> {
+    if ts == ffmpeg::AV_NOPTS_VALUE {
+        None
+    } else {
+        Some(pts_to_duration(ts, time_base))
+    }
+}
+
+pub(crate) struct MediaPacket {
+    ptr: *mut ffmpeg::AVPacket,
+}
+
+impl MediaPacket {
+    pub(crate) fn new() -> Result<Self, error::FFmpegError> {
+        let packet = unsafe { ffmpeg::av_packet_alloc() };
+        if packet.is_null() {
+            Err(error::FFmpegError::custom("failed to allocate packet"))
+        } else {
+            Ok(Self { ptr: packet })
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        unsafe { ffmpeg::av_packet_unref(self.ptr) }
+    }
+}
+
+// SAFETY: The packet is uniquely owned and FFmpeg allows packets to be
+//         unreferenced and freed from any thread.
+unsafe impl Send for MediaPacket {}
+
+impl std::ops::Deref for MediaPacket {
+    type Target = ffmpeg::AVPacket;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl std::ops::DerefMut for MediaPacket {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl Drop for MediaPacket {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            self.reset();
+            unsafe { ffmpeg::av_packet_free(&raw mut self.ptr) };
+        }
+    }
+}
@@ -1,33 +1,83 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fmt::Formatter;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::Duration;
 use std::{mem, ptr};
-use std::borrow::Cow;
 
 use rusty_ffmpeg::ffi as ffmpeg;
 
 use crate::codec::{AudioDecoder, Decoder, SubtitleDecoder, VideoDecoder};
+use crate::filter::{CropRect, VideoFilterOptions};
+use crate::loudness::LoudnessAnalyzer;
+use crate::packet::{MediaPacket, PacketInfo};
 use crate::stream::StreamInfo;
 use crate::{
     AcceleratorConfig,
+    AudioFormat,
+    ChannelLayout,
+    ColorPrimaries,
+    ColorRange,
+    ColorSpace,
+    ColorTransfer,
+    DecodeLimit,
+    FFmpegErrorKind,
+    Hdr10Metadata,
     InputSource,
+    KeyframeIndex,
+    LoopMode,
+    LoudnessStats,
     MediaType,
+    OnExceed,
     OutputPixelFormat,
+    PictureType,
+    PlayerHandle,
+    Resolution,
     SampleFormat,
+    ScalingMode,
+    SeekMode,
     SubtitleFormat,
+    ThreadCount,
+    VideoDecoderInfo,
     error,
     pts_to_duration,
 };
 
 const EAGAIN: i32 = -(ffmpeg::EAGAIN as i32);
+/// How far behind the playback clock a video frame can be before it is dropped
+/// by [MediaPlayer::process_next_frame_realtime].
+const MAX_VIDEO_FRAME_LATENESS: Duration = Duration::from_millis(100);
+/// How far ahead of the last extracted frame [MediaPlayer::extract_frames] will
+/// decode forward rather than seeking, keyframes are typically a few seconds apart.
+const MAX_FORWARD_DECODE_DISTANCE: Duration = Duration::from_secs(2);
+
+type PacketCallback = Box<dyn FnMut(PacketInfo) + Send>;
+type DecoderSelectedCallback = Box<dyn FnOnce(VideoDecoderInfo) + Send>;
 
 /// The builder for creating new [MediaPlayer] state machines.
 pub struct MediaPlayerBuilder {
     source: InputSource,
     target_pixel_formats: Vec<OutputPixelFormat>,
     accelerator_config: AcceleratorConfig,
+    decode_threads: ThreadCount,
+    crop: Option<CropRect>,
+    target_resolution: Option<(Resolution, ScalingMode)>,
+    max_resolution: Option<(Resolution, OnExceed)>,
+    target_audio_format: Option<AudioFormat>,
+    audio_downmix: Option<ChannelLayout>,
+    reset_stats_on_seek: bool,
+    loudness_analysis: bool,
+    skip_corrupt: bool,
+    prefetch_capacity: usize,
+    loop_mode: LoopMode,
     stream_index_video: Option<usize>,
     stream_index_audio: Option<usize>,
     stream_index_subtitle: Option<usize>,
+    program: Option<i32>,
+    only_media_type: Option<MediaType>,
+    on_packet: Option<PacketCallback>,
+    on_decoder_selected: Option<DecoderSelectedCallback>,
 }
 
 impl MediaPlayerBuilder {
@@ -38,9 +88,24 @@ impl MediaPlayerBuilder {
             source,
             target_pixel_formats: vec![OutputPixelFormat::Nv12],
             accelerator_config: AcceleratorConfig::default(),
+            decode_threads: ThreadCount::default(),
+            crop: None,
+            target_resolution: None,
+            max_resolution: None,
+            target_audio_format: None,
+            audio_downmix: None,
+            reset_stats_on_seek: false,
+            loudness_analysis: false,
+            skip_corrupt: false,
+            prefetch_capacity: 0,
+            loop_mode: LoopMode::Off,
             stream_index_video: None,
             stream_index_audio: None,
             stream_index_subtitle: None,
+            program: None,
+            only_media_type: None,
+            on_packet: None,
+            on_decoder_selected: None,
         }
     }
 
@@ -76,6 +141,187 @@ impl MediaPlayerBuilder {
         self
     }
 
+    /// Set the number of threads used when decoding video in software.
+    ///
+    /// This only applies when no hardware accelerator is active, by default
+    /// [ThreadCount::Auto] is used which lets FFmpeg pick the number of threads
+    /// based on the number of CPU cores.
+    pub fn with_decode_threads(mut self, threads: ThreadCount) -> Self {
+        self.decode_threads = threads;
+        self
+    }
+
+    /// Crop the video frames to the region starting at `x` and `y` pixels with
+    /// the given `width` and `height`, i.e. to remove hard-coded black bars.
+    ///
+    /// The crop is applied to the source frames before any scaling set via
+    /// [MediaPlayerBuilder::with_target_resolution].
+    ///
+    /// [MediaPlayerBuilder::build] returns [error::PlayerError::InvalidCrop] if the region
+    /// does not fit within the resolution of the selected video stream.
+    pub fn with_crop(mut self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        self.crop = Some(CropRect {
+            x,
+            y,
+            width,
+            height,
+        });
+        self
+    }
+
+    /// Scale the video frames to the given resolution using the [ScalingMode].
+    ///
+    /// When a hardware accelerator supports it, scaling is done on the device
+    /// before the frames are downloaded, which can significantly reduce the
+    /// bandwidth needed for previews of high resolution videos.
+    ///
+    /// As [ScalingMode::Fit] may produce frames smaller than the requested
+    /// resolution, the actual resolution can be retrieved via
+    /// [MediaPlayer::output_resolution].
+    pub fn with_target_resolution(
+        mut self,
+        width: usize,
+        height: usize,
+        mode: ScalingMode,
+    ) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "target resolution must be greater than zero"
+        );
+        self.target_resolution = Some((Resolution { width, height }, mode));
+        self
+    }
+
+    /// Limit the resolution of the video frames to at most `width` x `height`,
+    /// i.e. to guard against untrusted inputs with absurdly large dimensions.
+    ///
+    /// The limit is checked against the resolution of the selected video stream
+    /// once any crop and [MediaPlayerBuilder::with_target_resolution] scaling
+    /// is applied, if it is exceeded the [OnExceed] decides what happens:
+    ///
+    /// - [OnExceed::Error] makes [MediaPlayerBuilder::build] return
+    ///   [error::PlayerError::ResolutionTooLarge].
+    /// - [OnExceed::Downscale] scales the frames down to fit within the limit while
+    ///   preserving the aspect ratio, if a target resolution is set, it is shrunk
+    ///   to fit while keeping its [ScalingMode].
+    ///
+    /// The resolution can change mid-stream, so the limit is also applied to every
+    /// decoded frame, with [OnExceed::Error] making [MediaPlayer::process_next_frame]
+    /// return [error::PlayerError::ResolutionTooLarge]. If the stream does not report
+    /// its resolution, [MediaPlayerBuilder::build] returns
+    /// [error::PlayerError::InvalidStream] rather than decoding it unchecked.
+    ///
+    /// NOTE: The decoder still allocates frames at the source resolution when
+    ///       downscaling, use [OnExceed::Error] to avoid decoding the video at all.
+    pub fn with_max_resolution(
+        mut self,
+        width: usize,
+        height: usize,
+        on_exceed: OnExceed,
+    ) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "max resolution must be greater than zero"
+        );
+        self.max_resolution = Some((Resolution { width, height }, on_exceed));
+        self
+    }
+
+    /// Resample all decoded audio into the given sample format, sample rate
+    /// and number of channels.
+    ///
+    /// This is useful when feeding a fixed audio backend which expects a
+    /// predictable output, by default audio frames are returned in whatever
+    /// format the decoder produces.
+    ///
+    /// The negotiated output can be retrieved via [MediaPlayer::audio_format].
+    pub fn with_target_audio_format(
+        mut self,
+        sample_format: SampleFormat,
+        sample_rate: u32,
+        channels: u32,
+    ) -> Self {
+        assert!(sample_rate > 0, "sample rate must be greater than zero");
+        assert!(channels > 0, "channels must be greater than zero");
+        self.target_audio_format = Some(AudioFormat {
+            sample_format,
+            sample_rate,
+            channels,
+        });
+        self
+    }
+
+    /// Remix all decoded audio into the given [ChannelLayout], i.e. downmixing
+    /// 5.1 or 7.1 audio to [ChannelLayout::Stereo] for playback on stereo hardware.
+    ///
+    /// FFmpeg's standard downmix coefficients are used, if the source already
+    /// uses the layout this is a no-op.
+    ///
+    /// When combined with [MediaPlayerBuilder::with_target_audio_format] the
+    /// layout takes priority over the number of target channels.
+    pub fn with_audio_downmix(mut self, layout: ChannelLayout) -> Self {
+        assert!(
+            layout.num_channels() > 0,
+            "layout must have at least one channel"
+        );
+        self.audio_downmix = Some(layout);
+        self
+    }
+
+    /// Reset the [PlayerStatistics] every time the player seeks, so the timings
+    /// only reflect playback from the new position.
+    ///
+    /// By default, statistics accumulate for the lifetime of the player.
+    pub fn with_reset_stats_on_seek(mut self, enabled: bool) -> Self {
+        self.reset_stats_on_seek = enabled;
+        self
+    }
+
+    /// Skip packets the decoders reject as corrupt rather than failing playback.
+    ///
+    /// Each skipped packet is counted in [PlayerStatistics::corrupt_packets_skipped].
+    /// As later video frames may reference the corrupt one, video packets are
+    /// dropped until the next keyframe so the decoder resyncs cleanly.
+    ///
+    /// By default, corrupt data is returned as [error::PlayerError::InvalidData].
+    pub fn skip_corrupt(mut self, enabled: bool) -> Self {
+        self.skip_corrupt = enabled;
+        self
+    }
+
+    /// Measure the EBU R128 loudness of the audio as it is decoded, the running
+    /// measurements are available via [MediaPlayer::loudness].
+    ///
+    /// The decoded audio frames are passed through FFmpeg's `ebur128` filter
+    /// alongside being returned, so the audio output is unaffected.
+    pub fn with_loudness_analysis(mut self) -> Self {
+        self.loudness_analysis = true;
+        self
+    }
+
+    /// Keep up to `capacity` decoded frames queued ahead of the frame being
+    /// presented, which smooths out any jitter in decoding times.
+    ///
+    /// When enabled, [MediaPlayer::process_next_frame] tops up the queue before
+    /// returning the oldest queued frame, and [MediaPlayer::try_next_frame] can be
+    /// used to take a queued frame without decoding anything.
+    ///
+    /// By default, this is `0` and frames are decoded on demand.
+    pub fn with_prefetch(mut self, capacity: usize) -> Self {
+        self.prefetch_capacity = capacity;
+        self
+    }
+
+    /// Restart playback from the beginning once the end of the media is reached,
+    /// according to the [LoopMode].
+    ///
+    /// [error::PlayerError::EndOfStream] is only returned once the final loop ends,
+    /// see [LoopMode] for how the frame PTS behaves across loops.
+    pub fn with_loop(mut self, mode: LoopMode) -> Self {
+        self.loop_mode = mode;
+        self
+    }
+
     /// Select a specific video stream to output.
     pub fn with_video_stream(mut self, stream_index: Option<usize>) -> Self {
         if let Some(index) = stream_index {
@@ -118,17 +364,126 @@ impl MediaPlayerBuilder {
         self
     }
 
+    /// Only decode the audio stream, skipping the setup of the video and subtitle
+    /// decoders entirely, i.e. for music players or transcription.
+    ///
+    /// Packets of every other stream are discarded by the demuxer, so only
+    /// [DecodedFrame::Audio] frames are produced. This takes priority over any
+    /// stream selected via [MediaPlayerBuilder::with_video_stream] or
+    /// [MediaPlayerBuilder::with_subtitle_stream].
+    pub fn audio_only(mut self) -> Self {
+        self.only_media_type = Some(MediaType::Audio);
+        self
+    }
+
+    /// Only decode the video stream, skipping the setup of the audio and subtitle
+    /// decoders entirely, i.e. for thumbnailers or video analysis.
+    ///
+    /// Packets of every other stream are discarded by the demuxer, so only
+    /// [DecodedFrame::Video] frames are produced and audio codecs the player
+    /// cannot decode do not prevent it being built. This takes priority over any
+    /// stream selected via [MediaPlayerBuilder::with_audio_stream] or
+    /// [MediaPlayerBuilder::with_subtitle_stream].
+    pub fn video_only(mut self) -> Self {
+        self.only_media_type = Some(MediaType::Video);
+        self
+    }
+
+    /// Only play streams from the program with the given ID, i.e. to pin a specific
+    /// variant of an HLS master playlist, see [InputSource::programs].
+    ///
+    /// Streams selected via [MediaPlayerBuilder::with_video_stream] and its siblings
+    /// take priority over the program. This is a no-op for containers without
+    /// any programs, which includes most local files.
+    pub fn with_program(mut self, id: i32) -> Self {
+        let programs = self.source.programs();
+        if programs.is_empty() {
+            return self;
+        }
+
+        assert!(
+            programs.iter().any(|program| program.id == id),
+            "program specified does not exist"
+        );
+        self.program = Some(id);
+        self
+    }
+
+    /// Call `callback` with the timing of every packet before it is sent to
+    /// one of the decoders.
+    ///
+    /// This is intended for debugging A/V sync issues, i.e. to see how the
+    /// packets of each stream are interleaved within the source. Packets of
+    /// streams which are not being decoded are not passed to the callback.
+    ///
+    /// The callback is called on the thread driving the player, so it should
+    /// return quickly to avoid stalling decoding.
+    pub fn on_packet(mut self, callback: Box<dyn FnMut(PacketInfo) + Send>) -> Self {
+        self.on_packet = Some(callback);
+        self
+    }
+
+    /// Call `callback` once the video decoder has been opened, describing which
+    /// accelerator was selected and which accelerators failed before it.
+    ///
+    /// This allows applications to warn users when hardware decoding was
+    /// requested but the player fell back to software, see
+    /// [VideoDecoderInfo::is_software_fallback].
+    ///
+    /// The callback is called before [MediaPlayerBuilder::build] returns, so
+    /// [VideoDecoderInfo::hw_pix_fmt] has not been negotiated yet.
+    /// It is not called if there is no video stream being decoded.
+    pub fn on_decoder_selected(
+        mut self,
+        callback: Box<dyn FnOnce(VideoDecoderInfo) + Send>,
+    ) -> Self {
+        self.on_decoder_selected = Some(callback);
+        self
+    }
+
     /// Create the [MediaPlayer] using the set config.
     pub fn build(mut self) -> crate::Result<MediaPlayer> {
-        let video_stream = self
-            .source
-            .find_best_stream(MediaType::Video, self.stream_index_video)?;
-        let audio_stream = self
-            .source
-            .find_best_stream(MediaType::Audio, self.stream_index_audio)?;
-        let subtitle_stream = self
-            .source
-            .find_best_stream(MediaType::Subtitle, self.stream_index_subtitle)?;
+        // Programs can share streams, i.e. the audio of HLS variants, so the
+        // streams are picked from the program's own list.
+        let program_streams = self.program.and_then(|id| {
+            self.source
+                .programs()
+                .into_iter()
+                .find(|program| program.id == id)
+                .map(|program| program.stream_indices)
+        });
+
+        let only_media_type = self.only_media_type;
+        let is_wanted =
+            |media_type| only_media_type.is_none_or(|only| only == media_type);
+
+        let video_stream = if is_wanted(MediaType::Video) {
+            self.source.find_best_program_stream(
+                MediaType::Video,
+                self.stream_index_video,
+                program_streams.as_deref(),
+            )?
+        } else {
+            None
+        };
+        let audio_stream = if is_wanted(MediaType::Audio) {
+            self.source.find_best_program_stream(
+                MediaType::Audio,
+                self.stream_index_audio,
+                program_streams.as_deref(),
+            )?
+        } else {
+            None
+        };
+        let subtitle_stream = if is_wanted(MediaType::Subtitle) {
+            self.source.find_best_program_stream(
+                MediaType::Subtitle,
+                self.stream_index_subtitle,
+                program_streams.as_deref(),
+            )?
+        } else {
+            None
+        };
 
         if video_stream.is_none() && audio_stream.is_none() && subtitle_stream.is_none()
         {
@@ -142,6 +497,62 @@ impl MediaPlayerBuilder {
             "setting up player",
         );
 
+        if let (Some(crop), Some(stream)) = (self.crop, video_stream.as_ref()) {
+            let resolution = stream.resolution.unwrap_or_default();
+            if !crop.fits_within(resolution) {
+                return Err(error::PlayerError::InvalidCrop {
+                    x: crop.x,
+                    y: crop.y,
+                    width: crop.width,
+                    height: crop.height,
+                    resolution,
+                });
+            }
+        }
+
+        if let (Some((max, on_exceed)), Some(stream)) =
+            (self.max_resolution, video_stream.as_ref())
+        {
+            let source = match (self.crop, stream.resolution) {
+                (Some(crop), _) => crop.resolution(),
+                (None, Some(resolution))
+                    if resolution.width > 0 && resolution.height > 0 =>
+                {
+                    resolution
+                },
+                // The limit cannot be checked, so the stream must not be decoded.
+                (None, _) => {
+                    return Err(error::PlayerError::InvalidStream {
+                        index: stream.index,
+                        expected: MediaType::Video,
+                    });
+                },
+            };
+            let capped =
+                crate::cap_target_resolution(source, self.target_resolution, max);
+            match (capped, on_exceed) {
+                (None, _) => {},
+                (Some(_), OnExceed::Error) => {
+                    let resolution = match self.target_resolution {
+                        Some((target, mode)) => mode.output_resolution(source, target),
+                        None => source,
+                    };
+                    return Err(error::PlayerError::ResolutionTooLarge {
+                        resolution,
+                        max,
+                    });
+                },
+                (Some(capped), OnExceed::Downscale) => {
+                    tracing::debug!(
+                        source = %source,
+                        max = %max,
+                        "downscaling video to fit the max resolution",
+                    );
+                    self.target_resolution = Some(capped);
+                },
+            }
+        }
+
         let decoder_video = video_stream
             .as_ref()
             .map(|stream| {
@@ -149,6 +560,15 @@ impl MediaPlayerBuilder {
                     stream.index,
                     &self.accelerator_config,
                     self.target_pixel_formats,
+                    VideoFilterOptions {
+                        crop: self.crop,
+                        target_resolution: self.target_resolution,
+                        max_resolution: self
+                            .max_resolution
+                            .filter(|(_, on_exceed)| *on_exceed == OnExceed::Downscale)
+                            .map(|(max, _)| max),
+                    },
+                    self.decode_threads,
                 )?;
                 Ok::<_, error::FFmpegError>(TaggedDecoder {
                     stream: stream.clone(),
@@ -157,10 +577,20 @@ impl MediaPlayerBuilder {
             })
             .transpose()?;
 
+        if let (Some(video), Some(on_decoder_selected)) =
+            (decoder_video.as_ref(), self.on_decoder_selected.take())
+        {
+            on_decoder_selected(video.decoder.info());
+        }
+
         let decoder_audio = audio_stream
             .as_ref()
             .map(|stream| {
-                let decoder = self.source.open_audio_stream(stream.index)?;
+                let decoder = self.source.open_audio_stream(
+                    stream.index,
+                    self.target_audio_format,
+                    self.audio_downmix,
+                )?;
                 Ok::<_, error::FFmpegError>(TaggedDecoder {
                     stream: stream.clone(),
                     decoder,
@@ -179,20 +609,33 @@ impl MediaPlayerBuilder {
             })
             .transpose()?;
 
-        // To avoid doing unnecessary work, discard everything but the data we care about.
-        self.source.keep_streams(|stream| {
-            Some(stream.index) == video_stream.as_ref().map(|info| info.index)
-                || Some(stream.index) == audio_stream.as_ref().map(|info| info.index)
-                || Some(stream.index) == subtitle_stream.as_ref().map(|info| info.index)
-        });
+        let audio_streams = self
+            .source
+            .iter_streams()
+            .filter(|stream| stream.media_type == MediaType::Audio)
+            .collect();
+
+        let loudness = if self.loudness_analysis && decoder_audio.is_some() {
+            Some(LoudnessAnalyzer::new()?)
+        } else {
+            None
+        };
 
-        Ok(MediaPlayer {
+        let mut player = MediaPlayer {
             source: self.source,
 
             decoder_video,
             decoder_audio,
             decoder_subtitle,
 
+            audio_streams,
+            target_audio_format: self.target_audio_format,
+            audio_downmix: self.audio_downmix,
+            max_resolution: self
+                .max_resolution
+                .filter(|(_, on_exceed)| *on_exceed == OnExceed::Error)
+                .map(|(max, _)| max),
+
             packet: MediaPacket::new()?,
             frame_video: MediaRawFrame::new()?,
             frame_video_ready: None,
@@ -202,9 +645,44 @@ impl MediaPlayerBuilder {
             frame_subtitle_ready: None,
 
             end_of_packet_stream: false,
+            discard_until: None,
+
+            frame_pool: FramePool::default(),
+            prefetch_queue: VecDeque::with_capacity(self.prefetch_capacity),
+            prefetch_capacity: self.prefetch_capacity,
+
+            position_video: None,
+            position_audio: None,
+            position_base: Duration::ZERO,
+
+            loop_mode: self.loop_mode,
+            loops_completed: 0,
+            loop_offset: Duration::ZERO,
+
+            next_display_index: 0,
 
             statistics: PlayerStatistics::default(),
-        })
+            reset_stats_on_seek: self.reset_stats_on_seek,
+
+            loudness,
+
+            skip_corrupt: self.skip_corrupt,
+            video_needs_keyframe: false,
+
+            is_paused: false,
+
+            decode_limit: DecodeLimit::None,
+            decode_limit_frames: 0,
+            decode_limit_start: None,
+            decode_limit_reached: false,
+
+            on_packet: self.on_packet,
+        };
+
+        // To avoid doing unnecessary work, discard everything but the data we care about.
+        player.discard_unused_streams();
+
+        Ok(player)
     }
 }
 
@@ -221,6 +699,16 @@ pub struct MediaPlayer {
     decoder_audio: Option<TaggedDecoder<AudioDecoder>>,
     decoder_subtitle: Option<TaggedDecoder<SubtitleDecoder>>,
 
+    /// All audio streams within the source which can be switched to.
+    audio_streams: Vec<StreamInfo>,
+    /// The format audio is resampled into, kept for re-opening audio decoders.
+    target_audio_format: Option<AudioFormat>,
+    /// The layout audio is remixed into, kept for re-opening audio decoders.
+    audio_downmix: Option<ChannelLayout>,
+    /// Video frames exceeding this resolution are rejected, set when using
+    /// [OnExceed::Error].
+    max_resolution: Option<Resolution>,
+
     packet: MediaPacket,
     /// A frame holding video data.
     frame_video: MediaRawFrame,
@@ -242,28 +730,409 @@ pub struct MediaPlayer {
     frame_subtitle_ready: Option<i64>,
 
     end_of_packet_stream: bool,
+    /// If `Some`, frames before this position are discarded
+    /// as part of an exact seek.
+    discard_until: Option<Duration>,
+
+    /// Recycled frames which have been released by the user.
+    frame_pool: FramePool,
+    /// Frames decoded ahead of time, in the order they should be presented.
+    prefetch_queue: VecDeque<DecodedFrame>,
+    /// The maximum number of frames to decode ahead of time, `0` disables prefetching.
+    prefetch_capacity: usize,
+
+    /// The PTS of the last video frame produced.
+    position_video: Option<Duration>,
+    /// The PTS of the last audio frame produced.
+    position_audio: Option<Duration>,
+    /// The position to report before any frames are produced, i.e. after a seek.
+    position_base: Duration,
+
+    loop_mode: LoopMode,
+    /// The number of times playback has restarted from the beginning.
+    loops_completed: u32,
+    /// The offset applied to the PTS of frames so they increase across loops.
+    loop_offset: Duration,
+
+    /// The [VideoFrame::display_index] of the next video frame produced.
+    next_display_index: u64,
 
     statistics: PlayerStatistics,
+    /// If the statistics should be reset when seeking.
+    reset_stats_on_seek: bool,
+
+    /// Measures the loudness of the audio frames produced, if enabled.
+    loudness: Option<LoudnessAnalyzer>,
+
+    /// If packets rejected as corrupt are skipped instead of returning an error.
+    skip_corrupt: bool,
+    /// Set after a corrupt video packet, video packets are dropped until a keyframe.
+    video_needs_keyframe: bool,
+
+    /// Set via [MediaPlayer::pause], no frames are produced until [MediaPlayer::play].
+    is_paused: bool,
+
+    decode_limit: DecodeLimit,
+    /// The number of frames produced since the decode limit was armed.
+    decode_limit_frames: u64,
+    /// The PTS of the first frame produced since the decode limit was armed.
+    decode_limit_start: Option<Duration>,
+    /// Set once the decode limit is reached, until it is re-armed.
+    decode_limit_reached: bool,
+
+    /// Called with the timing of each packet before it is sent to a decoder.
+    on_packet: Option<PacketCallback>,
 }
 
+// SAFETY: The player uniquely owns all of its FFmpeg contexts and frames, which
+//         FFmpeg allows to be used from any thread as long as they are not
+//         used concurrently.
+unsafe impl Send for MediaPlayer {}
+
 impl MediaPlayer {
+    /// Move the player onto a dedicated thread, returning a [PlayerHandle] to
+    /// control it and receive the decoded frames.
+    ///
+    /// This avoids blocking the caller's thread, i.e. a GUI event loop, while decoding.
+    pub fn spawn(self) -> PlayerHandle {
+        PlayerHandle::spawn(self)
+    }
+
     #[inline]
     /// Returns a read-only view of the current player statistics.
     pub fn statistics(&self) -> &PlayerStatistics {
         &self.statistics
     }
 
+    /// Reset the player statistics back to zero.
+    ///
+    /// This is done automatically when seeking if enabled via
+    /// [MediaPlayerBuilder::with_reset_stats_on_seek].
+    pub fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+
+    /// Returns a flag which aborts any in-flight reads from the [InputSource] when set.
+    ///
+    /// This can be set from another thread to unblock a player stuck waiting on a
+    /// network stream, the blocked call will return [error::PlayerError::Cancelled].
+    pub fn cancel_handle(&self) -> Arc<AtomicBool> {
+        self.source.cancel_handle()
+    }
+
+    /// Returns the format of the audio frames produced by the player.
+    ///
+    /// If a target format was set via [MediaPlayerBuilder::with_target_audio_format]
+    /// this is the format all audio is resampled into, otherwise it is the format
+    /// the decoder produces.
+    ///
+    /// Returns `None` if there is no audio stream being decoded.
+    pub fn audio_format(&self) -> Option<AudioFormat> {
+        self.decoder_audio
+            .as_ref()
+            .and_then(|audio| audio.decoder.output_format())
+    }
+
+    #[inline]
+    /// Returns all audio streams available within the [InputSource].
+    ///
+    /// Any of these streams can be selected via [MediaPlayer::switch_audio_stream].
+    pub fn available_audio_streams(&self) -> &[StreamInfo] {
+        &self.audio_streams
+    }
+
+    /// Switch the audio output to the stream at the given index.
+    ///
+    /// The existing audio decoder is torn down and any audio frame already
+    /// decoded is dropped, audio from the new stream will be produced
+    /// starting from the current read position of the [InputSource].
+    ///
+    /// Returns [error::PlayerError::InvalidStream] if the index does not refer
+    /// to an audio stream. If the index is invalid or the new decoder fails to
+    /// open, the current audio stream is left playing.
+    pub fn switch_audio_stream(&mut self, index: usize) -> crate::Result<()> {
+        let Some(stream) = self
+            .audio_streams
+            .iter()
+            .find(|stream| stream.index == index)
+            .cloned()
+        else {
+            return Err(error::PlayerError::InvalidStream {
+                index,
+                expected: MediaType::Audio,
+            });
+        };
+
+        if self.decoder_audio.as_ref().map(|audio| audio.stream.index) == Some(index) {
+            return Ok(());
+        }
+
+        tracing::info!(stream = ?stream, "switching audio stream");
+
+        // Open the new decoder first, so the current stream keeps playing if it fails.
+        let decoder = self.source.open_audio_stream(
+            stream.index,
+            self.target_audio_format,
+            self.audio_downmix,
+        )?;
+
+        self.decoder_audio = Some(TaggedDecoder { stream, decoder });
+        self.frame_audio_ready = None;
+        self.prefetch_queue
+            .retain(|frame| !matches!(frame, DecodedFrame::Audio(_)));
+        self.discard_unused_streams();
+
+        if let Some(loudness) = self.loudness.as_mut() {
+            loudness.reset();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the EBU R128 loudness of the audio produced so far.
+    ///
+    /// The measurements accumulate across seeks and loops, and restart when
+    /// switching audio stream via [MediaPlayer::switch_audio_stream].
+    ///
+    /// Returns `None` if loudness analysis was not enabled via
+    /// [MediaPlayerBuilder::with_loudness_analysis], there is no audio stream,
+    /// or not enough audio has been decoded yet.
+    pub fn loudness(&self) -> Option<LoudnessStats> {
+        self.loudness.as_ref().and_then(|loudness| loudness.stats())
+    }
+
+    /// Returns the ASS header of the subtitle stream being decoded, containing
+    /// the `[Script Info]` and `[V4+ Styles]` sections.
+    ///
+    /// Renderers such as libass need this before any events to display the
+    /// subtitles with the correct styles, see [SubtitleFrame::text_raw] for the events.
+    /// Text subtitles which are not ASS are converted by FFmpeg, which provides
+    /// a default header for them.
+    ///
+    /// Returns `None` if there is no subtitle stream being decoded
+    /// or if it is a bitmap subtitle stream.
+    pub fn subtitle_header(&self) -> Option<String> {
+        self.decoder_subtitle
+            .as_ref()
+            .and_then(|subtitle| subtitle.decoder.header())
+    }
+
+    /// Returns information about how the video decoder was set up, including
+    /// which hardware accelerator was selected, if any.
+    ///
+    /// Returns `None` if there is no video stream being decoded.
+    pub fn video_decoder_info(&self) -> Option<VideoDecoderInfo> {
+        self.decoder_video
+            .as_ref()
+            .map(|video| video.decoder.info())
+    }
+
+    /// Returns the resolution of the video frames produced by the player once
+    /// any cropping and scaling set via [MediaPlayerBuilder::with_crop] and
+    /// [MediaPlayerBuilder::with_target_resolution] is applied.
+    ///
+    /// Returns `None` if there is no video stream being decoded.
+    pub fn output_resolution(&self) -> Option<Resolution> {
+        self.decoder_video
+            .as_ref()
+            .map(|video| video.decoder.output_resolution())
+    }
+
     /// Seek to a target position in the [InputSource].
+    ///
+    /// This is equivalent to calling [MediaPlayer::seek_with] with [SeekMode::Backward].
     pub fn seek(&mut self, position: Duration) -> crate::Result<()> {
-        tracing::info!(position = ?position, "seeking playback");
-        self.source.seek(position).map_err(error::PlayerError::from)
+        self.seek_with(position, SeekMode::Backward)
+    }
+
+    /// Seek to a target position in the [InputSource] using the given [SeekMode].
+    ///
+    /// When using [SeekMode::Exact], any frames decoded before the target position
+    /// will be discarded by [MediaPlayer::process_next_frame].
+    pub fn seek_with(
+        &mut self,
+        position: Duration,
+        mode: SeekMode,
+    ) -> crate::Result<()> {
+        tracing::info!(position = ?position, mode = ?mode, "seeking playback");
+        if !self.is_seekable() {
+            return Err(error::PlayerError::NotSeekable);
+        }
+        self.source.seek(position, mode)?;
+        self.reset_after_seek();
+
+        self.position_video = None;
+        self.position_audio = None;
+        self.position_base = position;
+
+        if mode == SeekMode::Exact {
+            self.discard_until = Some(position);
+        }
+
+        Ok(())
+    }
+
+    /// Limit how much media [MediaPlayer::process_next_frame] produces before
+    /// returning [error::PlayerError::EndOfStream], see [DecodeLimit].
+    ///
+    /// This is convenient for bounded scans such as generating a preview of
+    /// the first few seconds. Setting the limit, even to the same value, or
+    /// seeking re-arms it so counting starts again from the next frame.
+    pub fn set_decode_limit(&mut self, limit: DecodeLimit) {
+        self.decode_limit = limit;
+        self.rearm_decode_limit();
+    }
+
+    fn rearm_decode_limit(&mut self) {
+        self.decode_limit_frames = 0;
+        self.decode_limit_start = None;
+        self.decode_limit_reached = self.decode_limit == DecodeLimit::Frames(0);
+    }
+
+    /// Counts the frame towards the decode limit, returning `false` if the
+    /// frame is past the limit and must not be produced.
+    fn within_decode_limit(&mut self, frame: &DecodedFrame) -> bool {
+        let is_within = match self.decode_limit {
+            DecodeLimit::None => true,
+            DecodeLimit::Frames(max) => {
+                self.decode_limit_frames += 1;
+                if self.decode_limit_frames == max {
+                    // The frame is the last one, so the next call returns
+                    // immediately rather than decoding a frame to discard.
+                    self.decode_limit_reached = true;
+                }
+                self.decode_limit_frames <= max
+            },
+            DecodeLimit::Duration(limit) => {
+                let start = *self.decode_limit_start.get_or_insert(frame.pts());
+                frame.pts() < start + limit
+            },
+        };
+
+        if !is_within {
+            self.decode_limit_reached = true;
+        }
+        is_within
+    }
+
+    /// Restart playback from the beginning of the [InputSource].
+    ///
+    /// Unlike [MediaPlayer::seek], this also resets the loop count of
+    /// [MediaPlayerBuilder::with_loop], so it can be used to replay the media
+    /// once [error::PlayerError::EndOfStream] is returned without re-opening the source.
+    pub fn restart(&mut self) -> crate::Result<()> {
+        tracing::info!("restarting playback");
+        if !self.is_seekable() {
+            return Err(error::PlayerError::NotSeekable);
+        }
+        self.source.rewind()?;
+        self.reset_after_seek();
+
+        self.position_video = None;
+        self.position_audio = None;
+        self.position_base = Duration::ZERO;
+        self.loops_completed = 0;
+
+        Ok(())
+    }
+
+    /// Seek to a byte offset in the [InputSource].
+    ///
+    /// This is a fallback for sources which cannot seek by timestamp, typically
+    /// raw or streamed formats where [MediaPlayer::duration] is zero. Decoders are
+    /// flushed the same way as [MediaPlayer::seek_with] and [MediaPlayer::position]
+    /// is only updated once the next frame is produced.
+    ///
+    /// Returns an error if the format does not support byte seeking.
+    pub fn seek_bytes(&mut self, offset: i64) -> crate::Result<()> {
+        tracing::info!(offset = offset, "seeking playback to byte offset");
+        if !self.is_seekable() {
+            return Err(error::PlayerError::NotSeekable);
+        }
+        self.source.seek_bytes(offset)?;
+        self.reset_after_seek();
+        Ok(())
+    }
+
+    /// Seek to the last keyframe at or before `position` using an index built via
+    /// [InputSource::build_keyframe_index], avoiding the container having to search
+    /// for the keyframe on every seek.
+    ///
+    /// The keyframe is jumped to via its byte offset, or via its exact timestamp
+    /// for formats such as MP4 which only support seeking by timestamp. If the
+    /// index is empty this is the same as [MediaPlayer::seek].
+    ///
+    /// Returns [error::PlayerError::InvalidStream] if the index was not built for
+    /// the video stream being played.
+    pub fn seek_to_keyframe(
+        &mut self,
+        index: &KeyframeIndex,
+        position: Duration,
+    ) -> crate::Result<()> {
+        let video_stream = self.decoder_video.as_ref().map(|video| video.stream.index);
+        if video_stream != Some(index.stream_index()) {
+            return Err(error::PlayerError::InvalidStream {
+                index: index.stream_index(),
+                expected: MediaType::Video,
+            });
+        }
+
+        let Some(keyframe) = index.find(position) else {
+            return self.seek(position);
+        };
+
+        tracing::info!(
+            position = ?position,
+            keyframe = ?keyframe,
+            "seeking playback to keyframe",
+        );
+        if !self.is_seekable() {
+            return Err(error::PlayerError::NotSeekable);
+        }
+        if self.source.supports_byte_seek() {
+            self.source.seek_bytes(keyframe.byte_pos as i64)?;
+        } else {
+            // The timestamp is rounded down when converted for the seek, so searching
+            // forward ensures we land on this keyframe rather than the one before.
+            self.source.seek(keyframe.pts, SeekMode::Forward)?;
+        }
+        self.reset_after_seek();
+
+        self.position_video = None;
+        self.position_audio = None;
+        self.position_base = keyframe.pts;
+
+        Ok(())
     }
 
-    /// Begin the media decoding.
+    /// Flush the decoders and discard any state from before the seek.
+    fn reset_after_seek(&mut self) {
+        self.flush_decoder_buffers();
+
+        // Any frames already decoded are from the old position.
+        self.frame_video_ready = None;
+        self.frame_audio_ready = None;
+        self.frame_subtitle_ready = None;
+        self.prefetch_queue.clear();
+
+        self.loop_offset = Duration::ZERO;
+        self.end_of_packet_stream = false;
+        self.discard_until = None;
+        self.next_display_index = 0;
+        self.video_needs_keyframe = false;
+        self.rearm_decode_limit();
+
+        if self.reset_stats_on_seek {
+            self.reset_statistics();
+        }
+    }
+
+    /// Begin the media decoding, resuming playback if the player was paused.
     pub fn play(&mut self) -> crate::Result<()> {
         tracing::info!("starting playback");
+        self.is_paused = false;
         if let Err(err) = self.source.play() {
-            if err.errno() == -38 {
+            if err.kind() == FFmpegErrorKind::NotImplemented {
                 Ok(())
             } else {
                 Err(err.into())
@@ -275,12 +1144,17 @@ impl MediaPlayer {
 
     /// Pause the media decoding.
     ///
-    /// NOTE: This only really applies to network-based streams, if you continue
-    /// to poll `process_next_frame` you will continue to get frames.
+    /// While paused, [MediaPlayer::process_next_frame] returns
+    /// [error::PlayerError::Paused] without decoding anything, regardless of the
+    /// type of source. Network streams are additionally told to stop sending data.
+    ///
+    /// Frames can still be extracted explicitly while paused, see
+    /// [MediaPlayer::frame_at] and [MediaPlayer::extract_frames].
     pub fn pause(&mut self) -> crate::Result<()> {
         tracing::info!("pausing playback");
+        self.is_paused = true;
         if let Err(err) = self.source.pause() {
-            if err.errno() == -38 {
+            if err.kind() == FFmpegErrorKind::NotImplemented {
                 Ok(())
             } else {
                 Err(err.into())
@@ -290,16 +1164,228 @@ impl MediaPlayer {
         }
     }
 
+    #[inline]
+    /// Returns if the player is paused via [MediaPlayer::pause].
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
     /// Drives the player state machine until at least one frame
     /// is produced or the [InputSource] reaches the end of the stream.
+    ///
+    /// If prefetching is enabled via [MediaPlayerBuilder::with_prefetch], the queue
+    /// is filled first and the oldest queued frame is returned, with
+    /// [error::PlayerError::EndOfStream] only being returned once the queue is drained.
+    ///
+    /// Returns [error::PlayerError::Paused] immediately if the player is paused,
+    /// and [error::PlayerError::EndOfStream] once the limit set via
+    /// [MediaPlayer::set_decode_limit] is reached.
     pub fn process_next_frame(&mut self) -> crate::Result<DecodedFrame> {
+        if self.is_paused {
+            return Err(error::PlayerError::Paused);
+        }
+        self.produce_next_frame()
+    }
+
+    /// Produce the next frame like [MediaPlayer::process_next_frame], but ignoring
+    /// if the player is paused.
+    fn produce_next_frame(&mut self) -> crate::Result<DecodedFrame> {
+        if self.decode_limit_reached {
+            return Err(error::PlayerError::EndOfStream);
+        }
+
+        let frame = if self.prefetch_capacity == 0 {
+            self.decode_next_frame()?
+        } else {
+            self.next_prefetched_frame()?
+        };
+        if !self.within_decode_limit(&frame) {
+            return Err(error::PlayerError::EndOfStream);
+        }
+        self.record_position(&frame);
+        Ok(frame)
+    }
+
+    /// Fill the prefetch queue and take the oldest frame from it.
+    fn next_prefetched_frame(&mut self) -> crate::Result<DecodedFrame> {
+        while self.prefetch_queue.len() < self.prefetch_capacity {
+            match self.decode_next_frame() {
+                Ok(frame) => self.prefetch_queue.push_back(frame),
+                Err(error::PlayerError::EndOfStream) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.prefetch_queue
+            .pop_front()
+            .ok_or(error::PlayerError::EndOfStream)
+    }
+
+    /// Take the oldest frame from the prefetch queue without decoding anything.
+    ///
+    /// Returns `None` if the queue is empty, the player is paused, the decode limit
+    /// is reached or prefetching is not enabled via [MediaPlayerBuilder::with_prefetch].
+    pub fn try_next_frame(&mut self) -> Option<DecodedFrame> {
+        if self.is_paused || self.decode_limit_reached {
+            return None;
+        }
+
+        let frame = self.prefetch_queue.pop_front()?;
+        if !self.within_decode_limit(&frame) {
+            return None;
+        }
+        self.record_position(&frame);
+        Some(frame)
+    }
+
+    /// Returns the PTS of the most recently produced video frame, or audio frame
+    /// if there is no video.
+    ///
+    /// After seeking, this is the seek target until the next frame is produced.
+    pub fn position(&self) -> Duration {
+        self.position_video
+            .or(self.position_audio)
+            .unwrap_or(self.position_base)
+    }
+
+    /// Returns the duration of the [InputSource].
+    pub fn duration(&self) -> Duration {
+        self.source.duration()
+    }
+
+    /// Returns if the [InputSource] supports seeking, see [InputSource::is_seekable].
+    ///
+    /// When this is `false`, [MediaPlayer::seek] and the other seeking methods
+    /// return [error::PlayerError::NotSeekable].
+    pub fn is_seekable(&self) -> bool {
+        self.source.is_seekable()
+    }
+
+    /// Returns the first video frame with a PTS at or after the `position`.
+    ///
+    /// This performs a [SeekMode::Exact] seek, decoding forward from the nearest
+    /// preceding keyframe and discarding any frames before the target. Audio and
+    /// subtitle frames decoded along the way are discarded. Once returned, playback
+    /// continues from the frame after the one returned.
+    ///
+    /// This also works while the player is paused, which it remains afterwards.
+    ///
+    /// Returns [error::PlayerError::NoAvailableStreams] if there is no video stream
+    /// and [error::PlayerError::EndOfStream] if the `position` is past
+    /// [MediaPlayer::duration].
+    pub fn frame_at(&mut self, position: Duration) -> crate::Result<VideoFrame> {
+        self.check_frame_position(position)?;
+        self.seek_with(position, SeekMode::Exact)?;
+        self.next_video_frame()
+    }
+
+    /// Returns the video frame at each of the `positions`, in the same order as the
+    /// `positions` are provided.
+    ///
+    /// This behaves like calling [MediaPlayer::frame_at] for each position, but the
+    /// positions are visited in sorted order and close positions are reached by
+    /// decoding forward from the previous frame rather than seeking again.
+    ///
+    /// Each frame is kept in memory until all positions are extracted, combine this
+    /// with [MediaPlayerBuilder::with_target_resolution] to downscale the frames when
+    /// extracting many thumbnails.
+    pub fn extract_frames(
+        &mut self,
+        positions: &[Duration],
+    ) -> crate::Result<Vec<VideoFrame>> {
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+        order.sort_by_key(|&index| positions[index]);
+
+        let mut frames: Vec<Option<VideoFrame>> =
+            positions.iter().map(|_| None).collect();
+        let mut last_index: Option<usize> = None;
+        for index in order {
+            let target = positions[index];
+            self.check_frame_position(target)?;
+
+            let last_frame = last_index.and_then(|last| frames[last].as_ref());
+            let frame = match last_frame {
+                // The previous frame is also the first frame at or after this target.
+                Some(last) if last.pts() >= target => {
+                    self.reference_video_frame(last)?
+                },
+                Some(last) if target - last.pts() <= MAX_FORWARD_DECODE_DISTANCE => {
+                    self.discard_until = Some(target);
+                    self.next_video_frame()?
+                },
+                _ => self.frame_at(target)?,
+            };
+
+            frames[index] = Some(frame);
+            last_index = Some(index);
+        }
+
+        Ok(frames
+            .into_iter()
+            .map(|frame| frame.expect("every position has been extracted"))
+            .collect())
+    }
+
+    /// Check a frame can be extracted at the `position`.
+    fn check_frame_position(&self, position: Duration) -> crate::Result<()> {
+        if self.decoder_video.is_none() {
+            return Err(error::PlayerError::NoAvailableStreams);
+        }
+
+        // Looping would otherwise keep decoding forever without reaching the target.
+        let duration = self.duration();
+        if !duration.is_zero() && position > duration {
+            return Err(error::PlayerError::EndOfStream);
+        }
+
+        Ok(())
+    }
+
+    /// Decode until the next video frame, discarding any other frames.
+    ///
+    /// Frames are extracted explicitly, so this ignores if the player is paused.
+    fn next_video_frame(&mut self) -> crate::Result<VideoFrame> {
+        loop {
+            if let DecodedFrame::Video(video) = self.produce_next_frame()? {
+                return Ok(video);
+            }
+        }
+    }
+
+    /// Create a new [VideoFrame] referencing the same data as the `frame`.
+    fn reference_video_frame(&self, frame: &VideoFrame) -> crate::Result<VideoFrame> {
+        let raw_frame = self.frame_pool.acquire()?;
+        let result = unsafe { ffmpeg::av_frame_ref(raw_frame.ptr, frame.inner.ptr) };
+        error::convert_ff_result(result)?;
+        Ok(VideoFrame {
+            inner: self.frame_pool.wrap(raw_frame),
+            display_index: frame.display_index,
+        })
+    }
+
+    fn record_position(&mut self, frame: &DecodedFrame) {
+        match frame {
+            DecodedFrame::Video(video) => self.position_video = Some(video.pts()),
+            DecodedFrame::Audio(audio) => self.position_audio = Some(audio.pts()),
+            DecodedFrame::Subtitle(_) => {},
+        }
+    }
+
+    /// Returns the number of frames currently waiting in the prefetch queue.
+    pub fn num_prefetched_frames(&self) -> usize {
+        self.prefetch_queue.len()
+    }
+
+    fn decode_next_frame(&mut self) -> crate::Result<DecodedFrame> {
         let start = std::time::Instant::now();
-        let frame = loop {
+        let decoded_time_before = self.statistics.frames_decoded_time;
+        let mut frame = loop {
             let result = self.get_next_frame();
             match result {
+                Ok(frame) if self.should_discard(&frame) => continue,
                 Ok(frame) => break frame,
                 Err(err) if err.needs_data() || err.is_eof() => {
-                    if self.end_of_packet_stream {
+                    if self.end_of_packet_stream && !self.restart_loop()? {
                         tracing::debug!("end of stream processes");
                         return Err(error::PlayerError::EndOfStream);
                     }
@@ -314,15 +1400,87 @@ impl MediaPlayer {
                     self.flush()?;
                     continue;
                 },
+                // A non-blocking source has no data available yet, unlike the decoders
+                // needing more packets this cannot be resolved by reading again straight
+                // away, so it is treated the same as a timeout the caller can retry.
+                Err(err)
+                    if err.needs_data() || err.kind() == FFmpegErrorKind::Timeout =>
+                {
+                    // The failed read stays flagged on the IO context, formats read
+                    // linearly never seek to clear it so the retry would fail too.
+                    self.source.clear_io_error();
+                    return Err(error::PlayerError::NetworkTimeout(err));
+                },
                 Err(err) => return Err(err.into()),
                 Ok(()) => {},
             };
 
-            self.dispatch_packet()?;
-        };
-        self.statistics.frames_decoded_total += 1;
-        self.statistics.frames_total_time += start.elapsed();
-        Ok(frame)
+            self.dispatch_packet()?;
+        };
+
+        if let DecodedFrame::Video(video) = &frame {
+            check_frame_resolution(video, self.max_resolution)?;
+        }
+
+        if let (DecodedFrame::Audio(audio), Some(loudness)) =
+            (&frame, self.loudness.as_mut())
+        {
+            if let Err(err) = loudness.analyze(&audio.inner) {
+                // The analysis is best-effort and must not interrupt playback.
+                tracing::warn!(error = ?err, "failed to analyze loudness, disabling it");
+                self.loudness = None;
+            }
+        }
+
+        if !self.loop_offset.is_zero() {
+            frame.offset_pts(self.loop_offset);
+        }
+
+        if let DecodedFrame::Video(video) = &mut frame {
+            video.display_index = self.next_display_index;
+            self.next_display_index += 1;
+        }
+
+        let latency = start.elapsed();
+        let decode_time = self.statistics.frames_decoded_time - decoded_time_before;
+        self.statistics.record_frame(latency, decode_time);
+        Ok(frame)
+    }
+
+    /// Drives the player state machine like [MediaPlayer::process_next_frame], but
+    /// drops any video frames which are too far behind the playback `clock`.
+    ///
+    /// While the video is behind, the decoder also skips non-reference frames
+    /// entirely so it can catch back up, this trades smoothness of the video for
+    /// keeping it in sync with the audio when the CPU cannot keep up.
+    ///
+    /// Each dropped frame is counted in [PlayerStatistics::dropped_frames],
+    /// audio and subtitle frames are never dropped.
+    ///
+    /// Like [MediaPlayer::process_next_frame], this returns
+    /// [error::PlayerError::Paused] immediately if the player is paused.
+    pub fn process_next_frame_realtime(
+        &mut self,
+        clock: Duration,
+    ) -> crate::Result<DecodedFrame> {
+        loop {
+            let frame = self.process_next_frame()?;
+            let DecodedFrame::Video(video) = &frame else {
+                return Ok(frame);
+            };
+
+            let is_late = video.pts() + MAX_VIDEO_FRAME_LATENESS < clock;
+            if let Some(video_decoder) = self.decoder_video.as_mut() {
+                video_decoder.decoder.set_skip_non_reference_frames(is_late);
+            }
+
+            if !is_late {
+                return Ok(frame);
+            }
+
+            tracing::debug!(pts = ?video.pts(), clock = ?clock, "dropping late video frame");
+            self.statistics.dropped_frames += 1;
+        }
     }
 
     /// Retrieves the next available frame from the decoders.
@@ -336,7 +1494,6 @@ impl MediaPlayer {
         #[cfg(feature = "trace-hotpath")]
         tracing::trace!("trying to get next frame");
 
-        // TODO: We always allocate a new frame because of lifetimes, can we improve this?
         if let Some(frame) = self.get_ready_frame()? {
             #[cfg(feature = "trace-hotpath")]
             tracing::trace!("using ready frame");
@@ -345,35 +1502,45 @@ impl MediaPlayer {
 
         let start = std::time::Instant::now();
         if let Some(video) = self.decoder_video.as_mut() {
-            let is_ok =
-                ignore_out_of_data_error(video.decoder.decode(&mut self.frame_video))?;
+            let result =
+                ignore_out_of_data_error(video.decoder.decode(&mut self.frame_video));
+            let is_ok = self
+                .skip_if_corrupt(result, MediaType::Video)?
+                .unwrap_or(false);
             if is_ok {
                 #[cfg(feature = "trace-hotpath")]
                 tracing::trace!("video frame is ready");
-                self.frame_video_ready = Some(self.frame_video.pts);
+                self.frame_video_ready = Some(self.frame_video.ready_timestamp());
                 self.statistics.num_video_frames_decoded += 1;
             }
         }
 
         if let Some(audio) = self.decoder_audio.as_mut() {
-            let is_ok =
-                ignore_out_of_data_error(audio.decoder.decode(&mut self.frame_audio))?;
+            let result =
+                ignore_out_of_data_error(audio.decoder.decode(&mut self.frame_audio));
+            let is_ok = self
+                .skip_if_corrupt(result, MediaType::Audio)?
+                .unwrap_or(false);
             if is_ok {
                 #[cfg(feature = "trace-hotpath")]
                 tracing::trace!("audio frame is ready");
-                self.frame_audio_ready = Some(self.frame_audio.pts);
+                self.frame_audio_ready = Some(self.frame_audio.ready_timestamp());
                 self.statistics.num_audio_frames_decoded += 1;
             }
         }
 
         if let Some(subtitle) = self.decoder_subtitle.as_mut() {
-            let is_ok = ignore_out_of_data_error(
+            let result = ignore_out_of_data_error(
                 subtitle.decoder.decode(&mut self.frame_subtitle),
-            )?;
+            );
+            let is_ok = self
+                .skip_if_corrupt(result, MediaType::Subtitle)?
+                .unwrap_or(false);
             if is_ok {
                 #[cfg(feature = "trace-hotpath")]
                 tracing::trace!("subtitle frame is ready");
-                self.frame_subtitle_ready = Some(self.frame_subtitle.pts);
+                self.frame_subtitle_ready =
+                    Some(ready_timestamp_or_last(self.frame_subtitle.pts));
                 self.statistics.num_subtitle_frames_decoded += 1;
             }
         }
@@ -389,6 +1556,8 @@ impl MediaPlayer {
     }
 
     fn get_ready_frame(&mut self) -> Result<Option<DecodedFrame>, error::FFmpegError> {
+        // Ready timestamps are in `AV_TIME_BASE` units so streams can be compared,
+        // streams without a ready frame use `i64::MAX`, see `ready_timestamp_or_last`.
         let video_ready_ts = self.frame_video_ready.unwrap_or(i64::MAX);
         let audio_ready_ts = self.frame_audio_ready.unwrap_or(i64::MAX);
         let subtitle_ready_ts = self.frame_subtitle_ready.unwrap_or(i64::MAX);
@@ -397,18 +1566,29 @@ impl MediaPlayer {
             && video_ready_ts <= subtitle_ready_ts
             && video_ready_ts != i64::MAX
         {
-            let blank_frame = MediaRawFrame::new()?;
+            let blank_frame = self.frame_pool.acquire()?;
             self.frame_video_ready = None;
             let ready_frame = mem::replace(&mut self.frame_video, blank_frame);
-            Ok(Some(DecodedFrame::Video(VideoFrame { inner: ready_frame })))
+            Ok(Some(DecodedFrame::Video(VideoFrame {
+                inner: self.frame_pool.wrap(ready_frame),
+                display_index: 0,
+            })))
         } else if audio_ready_ts <= video_ready_ts
             && audio_ready_ts <= subtitle_ready_ts
             && audio_ready_ts != i64::MAX
         {
-            let blank_frame = MediaRawFrame::new()?;
+            let stream_index = self
+                .decoder_audio
+                .as_ref()
+                .map(|audio| audio.stream.index)
+                .expect("audio frame is ready without an audio decoder");
+            let blank_frame = self.frame_pool.acquire()?;
             self.frame_audio_ready = None;
             let ready_frame = mem::replace(&mut self.frame_audio, blank_frame);
-            Ok(Some(DecodedFrame::Audio(AudioFrame { inner: ready_frame })))
+            Ok(Some(DecodedFrame::Audio(AudioFrame {
+                inner: self.frame_pool.wrap(ready_frame),
+                stream_index,
+            })))
         } else if subtitle_ready_ts <= video_ready_ts
             && subtitle_ready_ts <= audio_ready_ts
             && subtitle_ready_ts != i64::MAX
@@ -446,27 +1626,48 @@ impl MediaPlayer {
 
         if let Some(video_decoder) = self.decoder_video.as_mut() {
             if self.packet.stream_index as usize == video_decoder.stream.index {
+                if self.video_needs_keyframe {
+                    if self.packet.flags & ffmpeg::AV_PKT_FLAG_KEY as i32 == 0 {
+                        #[cfg(feature = "trace-hotpath")]
+                        tracing::trace!("dropping video packet until next keyframe");
+                        unsafe { ffmpeg::av_packet_unref(&mut *self.packet) };
+                        return Ok(());
+                    }
+                    tracing::debug!("resynced video at keyframe");
+                    self.video_needs_keyframe = false;
+                }
+
+                notify_packet(&mut self.on_packet, &self.packet, &video_decoder.stream);
                 #[cfg(feature = "trace-hotpath")]
                 tracing::trace!("writing packet to video decoder");
-                video_decoder.decoder.write_packet(&mut self.packet)?;
+                let result = video_decoder.decoder.write_packet(&mut self.packet);
+                self.skip_if_corrupt(result, MediaType::Video)?;
                 return Ok(());
             }
         }
 
         if let Some(audio_decoder) = self.decoder_audio.as_mut() {
             if self.packet.stream_index as usize == audio_decoder.stream.index {
+                notify_packet(&mut self.on_packet, &self.packet, &audio_decoder.stream);
                 #[cfg(feature = "trace-hotpath")]
                 tracing::trace!("writing packet to audio decoder");
-                audio_decoder.decoder.write_packet(&mut self.packet)?;
+                let result = audio_decoder.decoder.write_packet(&mut self.packet);
+                self.skip_if_corrupt(result, MediaType::Audio)?;
                 return Ok(());
             }
         }
 
         if let Some(subtitle_decoder) = self.decoder_subtitle.as_mut() {
             if self.packet.stream_index as usize == subtitle_decoder.stream.index {
+                notify_packet(
+                    &mut self.on_packet,
+                    &self.packet,
+                    &subtitle_decoder.stream,
+                );
                 #[cfg(feature = "trace-hotpath")]
                 tracing::trace!("writing packet to subtitle decoder");
-                subtitle_decoder.decoder.write_packet(&mut self.packet)?;
+                let result = subtitle_decoder.decoder.write_packet(&mut self.packet);
+                self.skip_if_corrupt(result, MediaType::Subtitle)?;
                 return Ok(());
             }
         }
@@ -477,6 +1678,114 @@ impl MediaPlayer {
         Ok(())
     }
 
+    /// Swallow the error if it was caused by corrupt data and skipping corrupt
+    /// packets is enabled, returning `None` in place of the result.
+    ///
+    /// Corrupt video leaves the decoder without its reference frames, so video
+    /// packets are dropped until the next keyframe.
+    fn skip_if_corrupt<T>(
+        &mut self,
+        result: Result<T, error::FFmpegError>,
+        media_type: MediaType,
+    ) -> Result<Option<T>, error::FFmpegError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err)
+                if self.skip_corrupt && err.kind() == FFmpegErrorKind::InvalidData =>
+            {
+                tracing::warn!(error = ?err, ?media_type, "skipping corrupt packet");
+                self.statistics.corrupt_packets_skipped += 1;
+                // A rejected packet is not taken by the decoder, so must be released.
+                unsafe { ffmpeg::av_packet_unref(&mut *self.packet) };
+                if media_type == MediaType::Video {
+                    self.video_needs_keyframe = true;
+                }
+                Ok(None)
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns if the frame was decoded before the target of an exact seek
+    /// and should not be returned to the user.
+    ///
+    /// Once the video stream, or audio stream if there is no video, reaches the
+    /// target position, frames are no longer discarded.
+    fn should_discard(&mut self, frame: &DecodedFrame) -> bool {
+        let Some(target) = self.discard_until else {
+            return false;
+        };
+
+        let is_primary_stream = match frame {
+            DecodedFrame::Video(_) => true,
+            DecodedFrame::Audio(_) => self.decoder_video.is_none(),
+            DecodedFrame::Subtitle(_) => return false,
+        };
+
+        if frame.pts() < target {
+            return true;
+        }
+
+        if is_primary_stream {
+            self.discard_until = None;
+        }
+
+        false
+    }
+
+    /// Discard packets from every stream which is not being decoded.
+    fn discard_unused_streams(&mut self) {
+        let video = self.decoder_video.as_ref().map(|video| video.stream.index);
+        let audio = self.decoder_audio.as_ref().map(|audio| audio.stream.index);
+        let subtitle = self
+            .decoder_subtitle
+            .as_ref()
+            .map(|subtitle| subtitle.stream.index);
+
+        self.source.keep_streams(|stream| {
+            Some(stream.index) == video
+                || Some(stream.index) == audio
+                || Some(stream.index) == subtitle
+        });
+    }
+
+    /// Seek back to the start of the source if the [LoopMode] allows another loop.
+    ///
+    /// Returns `false` if playback should end instead.
+    fn restart_loop(&mut self) -> crate::Result<bool> {
+        if !self.loop_mode.should_restart(self.loops_completed) {
+            return Ok(false);
+        }
+
+        tracing::debug!(
+            loops_completed = self.loops_completed,
+            "restarting playback"
+        );
+        self.source.rewind()?;
+        self.flush_decoder_buffers();
+        self.end_of_packet_stream = false;
+
+        self.loops_completed += 1;
+        self.loop_offset += self.source.duration();
+
+        Ok(true)
+    }
+
+    /// Discard any packets and frames buffered within the decoders.
+    fn flush_decoder_buffers(&mut self) {
+        if let Some(video_decoder) = self.decoder_video.as_mut() {
+            video_decoder.decoder.flush_buffers();
+        }
+
+        if let Some(audio_decoder) = self.decoder_audio.as_mut() {
+            audio_decoder.decoder.flush_buffers();
+        }
+
+        if let Some(subtitle_decoder) = self.decoder_subtitle.as_mut() {
+            subtitle_decoder.decoder.flush_buffers();
+        }
+    }
+
     fn flush(&mut self) -> Result<(), error::FFmpegError> {
         tracing::debug!("flushing decoders");
 
@@ -501,20 +1810,61 @@ impl MediaPlayer {
 /// The core components that can be accessed for video, audio and subtitles.
 pub trait Frame {
     /// Returns the presentation timestamp of the frame.
+    ///
+    /// If the frame has no PTS, FFmpeg's best effort estimate of the timestamp is
+    /// used instead.
     fn pts(&self) -> Duration;
 
+    /// Returns the decoding timestamp of the packet the frame was decoded from.
+    ///
+    /// Returns `None` if the timestamp is unknown.
+    fn dts(&self) -> Option<Duration>;
+
     /// Signals if the frame data is backed by hardware (i.e. GPU)
     fn is_hw_backed(&self) -> bool;
 }
 
 #[derive(Debug)]
 /// A frame which has been decoded from the [InputSource].
+///
+/// Frames uniquely own their data, so they can be moved to another thread,
+/// i.e. a render thread, once received from the player.
 pub enum DecodedFrame {
     Video(VideoFrame),
     Audio(AudioFrame),
     Subtitle(SubtitleFrame),
 }
 
+impl DecodedFrame {
+    /// Shift the PTS of the frame forward by `offset`.
+    fn offset_pts(&mut self, offset: Duration) {
+        let inner = match self {
+            DecodedFrame::Video(frame) => &mut frame.inner,
+            DecodedFrame::Audio(frame) => &mut frame.inner,
+            DecodedFrame::Subtitle(frame) => {
+                // Subtitle timestamps are always in `AV_TIME_BASE` units.
+                if frame.inner.pts != ffmpeg::AV_NOPTS_VALUE {
+                    frame.inner.pts += offset.as_micros() as i64;
+                }
+                return;
+            },
+        };
+
+        let offset = unsafe {
+            ffmpeg::av_rescale_q(
+                offset.as_micros() as i64,
+                crate::AV_TIME_BASE_Q,
+                inner.time_base,
+            )
+        };
+        for timestamp in [&mut inner.pts, &mut inner.best_effort_timestamp] {
+            if *timestamp != ffmpeg::AV_NOPTS_VALUE {
+                *timestamp += offset;
+            }
+        }
+    }
+}
+
 impl Frame for DecodedFrame {
     fn pts(&self) -> Duration {
         match self {
@@ -524,6 +1874,14 @@ impl Frame for DecodedFrame {
         }
     }
 
+    fn dts(&self) -> Option<Duration> {
+        match self {
+            DecodedFrame::Video(frame) => frame.dts(),
+            DecodedFrame::Audio(frame) => frame.dts(),
+            DecodedFrame::Subtitle(frame) => frame.dts(),
+        }
+    }
+
     fn is_hw_backed(&self) -> bool {
         match self {
             DecodedFrame::Video(frame) => frame.is_hw_backed(),
@@ -538,19 +1896,23 @@ impl Frame for DecodedFrame {
 /// In the pixel format of one of the target [OutputPixelFormat] formats
 /// you configure on the player.
 pub struct VideoFrame {
-    inner: MediaRawFrame,
+    inner: PooledFrame,
+    display_index: u64,
 }
 
 impl std::fmt::Debug for VideoFrame {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "VideoFrame(pix_fmt={:?}, resolution={}x{}, planes={}, pts={:?})",
+            "VideoFrame(pix_fmt={:?}, resolution={}x{}, planes={}, pts={:?}, \
+            keyframe={}, picture_type={:?})",
             self.pixel_format(),
             self.width(),
             self.height(),
             self.num_planes(),
             self.pts(),
+            self.is_keyframe(),
+            self.picture_type(),
         )
     }
 }
@@ -563,6 +1925,118 @@ impl VideoFrame {
             .expect("unexpected video pixel format encountered")
     }
 
+    #[inline]
+    /// Returns if the frame is a key frame.
+    pub fn is_keyframe(&self) -> bool {
+        self.inner.flags & ffmpeg::AV_FRAME_FLAG_KEY as i32 != 0
+    }
+
+    #[inline]
+    /// Returns the number of the packet the frame was decoded from, in decode order.
+    ///
+    /// Packets are numbered from `0` when the player is created and again after
+    /// every seek, so following a seek this counts from the keyframe decoding
+    /// resumed at rather than the start of the source. Unlike
+    /// [VideoFrame::display_index] this is not in presentation order when the
+    /// video contains B-frames.
+    ///
+    /// Returns `None` if the decoder did not associate the frame with a packet.
+    pub fn coded_picture_number(&self) -> Option<u64> {
+        let number = self.inner.opaque.addr() as u64;
+        number.checked_sub(1)
+    }
+
+    #[inline]
+    /// Returns the index of the frame within the sequence of video frames
+    /// produced by the player, starting at `0`.
+    ///
+    /// The index is reset to `0` after every seek, including [MediaPlayer::restart],
+    /// but keeps increasing when playback loops via [MediaPlayerBuilder::with_loop].
+    /// Frames dropped by [MediaPlayer::process_next_frame_realtime] still
+    /// consume an index, leaving a gap in the sequence.
+    pub fn display_index(&self) -> u64 {
+        self.display_index
+    }
+
+    #[inline]
+    /// Returns the picture type of the frame, i.e. if it is an I, P or B frame.
+    pub fn picture_type(&self) -> PictureType {
+        PictureType::from_av_picture_type(self.inner.pict_type)
+    }
+
+    #[inline]
+    /// Returns the matrix coefficients used to convert the frame from YUV to RGB.
+    pub fn color_space(&self) -> ColorSpace {
+        ColorSpace::from_av_color_space(self.inner.colorspace)
+    }
+
+    #[inline]
+    /// Returns if the frame uses limited or full range values.
+    pub fn color_range(&self) -> ColorRange {
+        ColorRange::from_av_color_range(self.inner.color_range)
+    }
+
+    #[inline]
+    /// Returns the color primaries of the frame, i.e. BT.709 or BT.2020.
+    pub fn color_primaries(&self) -> ColorPrimaries {
+        ColorPrimaries::from_av_color_primaries(self.inner.color_primaries)
+    }
+
+    #[inline]
+    /// Returns the transfer characteristics of the frame, i.e. BT.709 or PQ for HDR10.
+    pub fn color_transfer(&self) -> ColorTransfer {
+        ColorTransfer::from_av_color_transfer(self.inner.color_trc)
+    }
+
+    /// Returns the static HDR10 metadata attached to the frame.
+    ///
+    /// Returns `None` if the stream carries no mastering display or
+    /// content light level metadata.
+    pub fn hdr10_metadata(&self) -> Option<Hdr10Metadata> {
+        let mastering_display = self
+            .inner
+            .side_data(ffmpeg::AV_FRAME_DATA_MASTERING_DISPLAY_METADATA)
+            .map(|data| unsafe {
+                &*data.data.cast::<ffmpeg::AVMasteringDisplayMetadata>()
+            });
+        let content_light_level = self
+            .inner
+            .side_data(ffmpeg::AV_FRAME_DATA_CONTENT_LIGHT_LEVEL)
+            .map(|data| unsafe { &*data.data.cast::<ffmpeg::AVContentLightMetadata>() });
+
+        Hdr10Metadata::from_raw(mastering_display, content_light_level)
+    }
+
+    /// Returns the raw 3x3 display matrix attached to the frame, if any.
+    ///
+    /// See the FFmpeg `display.h` documentation for how the matrix is laid out.
+    pub fn display_matrix(&self) -> Option<[i32; 9]> {
+        let side_data = self.inner.side_data(ffmpeg::AV_FRAME_DATA_DISPLAYMATRIX)?;
+        if side_data.size < mem::size_of::<[i32; 9]>() {
+            return None;
+        }
+        Some(unsafe { ptr::read_unaligned(side_data.data.cast::<[i32; 9]>()) })
+    }
+
+    /// Returns the clockwise rotation in degrees which must be applied to display
+    /// the frame correctly, typically `0`, `90`, `180` or `270`.
+    ///
+    /// This is `0` if the frame has no display matrix.
+    pub fn rotation(&self) -> f64 {
+        match self.display_matrix() {
+            Some(matrix) => unsafe { crate::display_matrix_rotation(matrix.as_ptr()) },
+            None => 0.0,
+        }
+    }
+
+    #[inline]
+    /// Returns the width to height ratio of a single pixel, as `(numerator, denominator)`.
+    ///
+    /// This is `(1, 1)` for square pixels or if the frame does not specify it.
+    pub fn sample_aspect_ratio(&self) -> (u32, u32) {
+        crate::stream::sample_aspect_ratio_from_av(self.inner.sample_aspect_ratio)
+    }
+
     #[inline]
     /// The width of the frame in pixels.
     pub fn width(&self) -> usize {
@@ -576,11 +2050,20 @@ impl VideoFrame {
     }
 
     #[inline]
-    /// Returns the stride of the given plane.
+    /// Returns the stride of the given plane in bytes.
+    ///
+    /// This is always positive, even for planes stored bottom-up in memory,
+    /// see [VideoFrame::plane_data].
     pub fn stride(&self, index: usize) -> usize {
         assert!(index < self.num_planes(), "index out of range");
-        // TODO: Negative values situation possible within this context?
-        self.inner.linesize[index] as usize
+        self.inner.linesize[index].unsigned_abs() as usize
+    }
+
+    #[inline]
+    /// Returns if the rows of the plane are stored bottom-up in memory,
+    /// i.e. the frame has a negative linesize.
+    fn is_bottom_up(&self, index: usize) -> bool {
+        self.inner.linesize[index] < 0
     }
 
     #[inline]
@@ -593,7 +2076,7 @@ impl VideoFrame {
             return self.width();
         }
 
-        if let Some(desc) = self.pixel_format().descriptor() {
+        if let Some(desc) = self.descriptor() {
             let s = desc.log2_chroma_w;
             (self.width() + (1 << s) - 1) >> s
         } else {
@@ -611,29 +2094,41 @@ impl VideoFrame {
             return self.height();
         }
 
-        if let Some(desc) = self.pixel_format().descriptor() {
-            let s = desc.log2_chroma_w;
+        if let Some(desc) = self.descriptor() {
+            let s = desc.log2_chroma_h;
             (self.height() + (1 << s) - 1) >> s
         } else {
             self.height()
         }
     }
 
+    fn descriptor(&self) -> Option<&'static ffmpeg::AVPixFmtDescriptor> {
+        unsafe { ffmpeg::av_pix_fmt_desc_get(self.inner.format).as_ref() }
+    }
+
     #[inline]
     /// Returns the number of video planes within the frame.
+    ///
+    /// This is determined by the pixel format, i.e. `2` for [OutputPixelFormat::Nv12]
+    /// and `3` for [OutputPixelFormat::Yuv420p].
     pub fn num_planes(&self) -> usize {
-        let mut total = 0;
-        for i in 0..8 {
-            if self.inner.linesize[i] == 0 {
-                break;
-            }
-            total += 1;
-        }
-        total
+        // Logic taken from av_pix_fmt_count_planes().
+        let Some(desc) = self.descriptor() else {
+            return 0;
+        };
+        desc.comp[..desc.nb_components as usize]
+            .iter()
+            .map(|comp| comp.plane as usize + 1)
+            .max()
+            .unwrap_or(0)
     }
 
     /// Retrieve the raw data of a given plane.
     ///
+    /// The rows are returned in the order they are stored in memory, which is
+    /// bottom-up for frames with a negative linesize, use
+    /// [VideoFrame::copy_to_tightly_packed] to always get the rows top-down.
+    ///
     /// If the frame is hardware backed, it will transfer the data
     /// from the device to system memory which may increase latency.
     pub fn plane_data(&mut self, index: usize) -> crate::Result<&[u8]> {
@@ -643,24 +2138,163 @@ impl VideoFrame {
             self.inner.copy_hw_to_software()?;
         }
 
-        let ptr = self.inner.data[index];
+        let stride = self.stride(index);
+        let height = self.plane_height(index);
+
+        let mut ptr = self.inner.data[index];
         debug_assert!(!ptr.is_null());
 
-        let buffer = unsafe {
-            std::slice::from_raw_parts(
-                ptr,
-                self.stride(index) * self.plane_height(index),
-            )
-        };
+        // With a negative linesize the data pointer is the top row, which is
+        // the last row in memory, so rewind to the start of the plane.
+        if self.is_bottom_up(index) && height > 0 {
+            ptr = unsafe { ptr.sub(stride * (height - 1)) };
+        }
+
+        let buffer = unsafe { std::slice::from_raw_parts(ptr, stride * height) };
 
         Ok(buffer)
     }
+
+    /// Copy the frame data into `out` with the stride padding removed,
+    /// replacing any existing contents of the buffer.
+    ///
+    /// Each plane is written in order with every row being exactly the width of
+    /// the plane in bytes, so the output can be uploaded directly into a texture.
+    /// For example, [OutputPixelFormat::Nv12] produces the Y plane followed by the
+    /// interleaved UV plane and [OutputPixelFormat::Rgba] produces a single plane.
+    ///
+    /// If the frame is hardware backed, it will transfer the data
+    /// from the device to system memory first which may increase latency.
+    pub fn copy_to_tightly_packed(&mut self, out: &mut Vec<u8>) -> crate::Result<()> {
+        if self.is_hw_backed() {
+            self.inner.copy_hw_to_software()?;
+        }
+
+        out.clear();
+        for index in 0..self.num_planes() {
+            let row_size = self.packed_row_size(index);
+            let stride = self.stride(index);
+            let height = self.plane_height(index);
+
+            let is_bottom_up = self.is_bottom_up(index);
+
+            let data = self.plane_data(index)?;
+            out.reserve(row_size * height);
+            for row in 0..height {
+                let row = if is_bottom_up { height - 1 - row } else { row };
+                out.extend_from_slice(&data[row * stride..][..row_size]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compute a 64-bit perceptual average hash (aHash) of the frame.
+    ///
+    /// The luma plane is downscaled to 8x8 by averaging, then each bit is set if
+    /// the block is brighter than the mean, starting from the top-left block in
+    /// the most significant bit. Similar looking frames produce hashes with a small
+    /// Hamming distance, i.e. `(a ^ b).count_ones()`, making this useful for
+    /// deduplicating thumbnails or detecting scene cuts.
+    ///
+    /// This requires a luma plane so is only supported for [OutputPixelFormat::Nv12],
+    /// [OutputPixelFormat::Yuv420p] and [OutputPixelFormat::P010le] frames, an error
+    /// is returned for RGB formats.
+    ///
+    /// If the frame is hardware backed, it will transfer the data
+    /// from the device to system memory first which may increase latency.
+    pub fn average_hash(&mut self) -> crate::Result<u64> {
+        const HASH_SIZE: usize = 8;
+
+        // The format, stride and orientation must be read from the software frame.
+        if self.is_hw_backed() {
+            self.inner.copy_hw_to_software()?;
+        }
+
+        let format = OutputPixelFormat::try_from_av_pix_fmt(self.inner.format)
+            .ok_or_else(|| {
+                error::FFmpegError::custom(
+                    "average hash requires a frame in a known pixel format",
+                )
+            })?;
+        let bytes_per_sample = match format {
+            OutputPixelFormat::Nv12 | OutputPixelFormat::Yuv420p => 1,
+            OutputPixelFormat::P010le => 2,
+            OutputPixelFormat::Rgba
+            | OutputPixelFormat::Rgb24
+            | OutputPixelFormat::Bgra => {
+                return Err(error::FFmpegError::custom(format!(
+                    "average hash requires a frame with a luma plane, got {format:?}",
+                ))
+                .into());
+            },
+        };
+
+        let width = self.width();
+        let height = self.height();
+        let stride = self.stride(0);
+        let is_bottom_up = self.is_bottom_up(0);
+        let data = self.plane_data(0)?;
+
+        let mut blocks = [0u64; HASH_SIZE * HASH_SIZE];
+        for (block_y, block_row) in blocks.chunks_exact_mut(HASH_SIZE).enumerate() {
+            // Frames smaller than the hash still cover every block with a pixel.
+            let y_start = block_y * height / HASH_SIZE;
+            let y_end = ((block_y + 1) * height / HASH_SIZE).max(y_start + 1);
+
+            for (block_x, block) in block_row.iter_mut().enumerate() {
+                let x_start = block_x * width / HASH_SIZE;
+                let x_end = ((block_x + 1) * width / HASH_SIZE).max(x_start + 1);
+
+                let mut sum = 0;
+                for y in y_start..y_end {
+                    let y = if is_bottom_up { height - 1 - y } else { y };
+                    let row = &data[y * stride..];
+                    sum += (x_start..x_end)
+                        .map(|x| match bytes_per_sample {
+                            // The 10-bit samples are stored in the high bits.
+                            2 => u64::from(row[x * 2 + 1]),
+                            _ => u64::from(row[x]),
+                        })
+                        .sum::<u64>();
+                }
+                let num_pixels = ((y_end - y_start) * (x_end - x_start)) as u64;
+                *block = sum / num_pixels;
+            }
+        }
+
+        // Comparing against the sum avoids rounding the mean.
+        let total: u64 = blocks.iter().sum();
+        let hash = blocks.iter().fold(0u64, |hash, &block| {
+            (hash << 1) | u64::from(block * blocks.len() as u64 > total)
+        });
+
+        Ok(hash)
+    }
+
+    /// Returns the size of a row of the plane in bytes without any padding.
+    fn packed_row_size(&self, index: usize) -> usize {
+        let size = unsafe {
+            ffmpeg::av_image_get_linesize(
+                self.inner.format,
+                self.width() as i32,
+                index as i32,
+            )
+        };
+        assert!(size >= 0, "unable to determine the plane row size");
+        size as usize
+    }
 }
 
 impl Frame for VideoFrame {
     #[inline]
     fn pts(&self) -> Duration {
-        pts_to_duration(self.inner.pts, self.inner.time_base)
+        pts_to_duration(self.inner.timestamp(), self.inner.time_base)
+    }
+
+    #[inline]
+    fn dts(&self) -> Option<Duration> {
+        self.inner.dts()
     }
 
     #[inline]
@@ -669,32 +2303,156 @@ impl Frame for VideoFrame {
     }
 }
 
+#[cfg(feature = "wgpu")]
+impl VideoFrame {
+    /// Import the frame into a new [wgpu::Texture] which can be sampled by shaders.
+    ///
+    /// Hardware frames decoded with [Accelerator::Vulkan], [Accelerator::D3D11] or
+    /// [Accelerator::VideoToolbox] share their device surface with `device` when it
+    /// uses a compatible backend, producing a [wgpu::TextureFormat::NV12] texture
+    /// for NV12 surfaces if [wgpu::Features::TEXTURE_FORMAT_NV12] is enabled.
+    ///
+    /// Otherwise the frame is transferred to system memory if needed and uploaded
+    /// through the staging buffer of the `queue`, which happens when the queue is
+    /// next submitted. Only [OutputPixelFormat::Rgba] and [OutputPixelFormat::Bgra]
+    /// frames can be uploaded, as wgpu does not allow writing to multi-planar
+    /// textures.
+    ///
+    /// [Accelerator::Vulkan]: crate::Accelerator::Vulkan
+    /// [Accelerator::D3D11]: crate::Accelerator::D3D11
+    /// [Accelerator::VideoToolbox]: crate::Accelerator::VideoToolbox
+    pub fn import_to_wgpu(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> crate::Result<wgpu::Texture> {
+        let software;
+        let frame: &MediaRawFrame = if self.is_hw_backed() {
+            if let Some(texture) = crate::interop::import_hw_frame(&self.inner, device) {
+                return Ok(texture);
+            }
+            software = self.inner.to_software()?;
+            &software
+        } else {
+            &self.inner
+        };
+
+        let texture_format = match OutputPixelFormat::try_from_av_pix_fmt(frame.format) {
+            Some(OutputPixelFormat::Rgba) => wgpu::TextureFormat::Rgba8Unorm,
+            Some(OutputPixelFormat::Bgra) => wgpu::TextureFormat::Bgra8Unorm,
+            Some(pixel_format) => {
+                return Err(error::FFmpegError::custom(format!(
+                    "pixel format {pixel_format:?} cannot be uploaded to wgpu"
+                ))
+                .into());
+            },
+            None => {
+                return Err(error::FFmpegError::custom(
+                    "frames in an unknown pixel format cannot be uploaded to wgpu",
+                )
+                .into());
+            },
+        };
+
+        if frame.linesize[0] < 0 {
+            return Err(error::FFmpegError::custom(
+                "frames with a negative stride cannot be uploaded to wgpu",
+            )
+            .into());
+        }
+
+        let width = frame.width as u32;
+        let height = frame.height as u32;
+        let stride = frame.linesize[0] as usize;
+        let data = unsafe {
+            std::slice::from_raw_parts(frame.data[0], stride * height as usize)
+        };
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("libav-player video frame"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(stride as u32),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        Ok(texture)
+    }
+}
+
 /// A decoded audio frame.
 pub struct AudioFrame {
-    inner: MediaRawFrame,
+    inner: PooledFrame,
+    stream_index: usize,
 }
 
 impl std::fmt::Debug for AudioFrame {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "AudioFrame(sample_fmt={:?}, samples={}, channels={}, planes={}, pts={:?})",
+            "AudioFrame(sample_fmt={:?}, sample_rate={}, samples={}, channels={}, \
+            planes={}, pts={:?}, stream={})",
             self.sample_format(),
+            self.sample_rate(),
             self.num_samples(),
             self.num_channels(),
             self.num_planes(),
             self.pts(),
+            self.stream_index,
         )
     }
 }
 
 impl AudioFrame {
+    #[inline]
+    /// Returns the index of the audio stream the frame was decoded from.
+    pub fn stream_index(&self) -> usize {
+        self.stream_index
+    }
+
     #[inline]
     /// Returns the number of audio channels.
     pub fn num_channels(&self) -> usize {
         self.inner.ch_layout.nb_channels as usize
     }
 
+    #[inline]
+    /// Returns the number of samples per second for each channel.
+    pub fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate.max(0) as u32
+    }
+
+    #[inline]
+    /// Returns the layout of the audio channels, describing which
+    /// speaker each channel maps to.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        ChannelLayout::from_av_channel_layout(&self.inner.ch_layout)
+    }
+
     #[inline]
     /// Returns the number of audio samples per channel.
     pub fn num_samples(&self) -> usize {
@@ -734,32 +2492,243 @@ impl AudioFrame {
         }
     }
 
-    #[inline]
-    /// Retrieve the raw data of a given plane.
+    #[inline]
+    /// Retrieve the raw data of a given plane.
+    ///
+    /// If the frame is hardware backed, it will transfer the data
+    /// from the device to system memory which may increase latency.
+    pub fn plane_data(&mut self, index: usize) -> crate::Result<&[u8]> {
+        assert!(index < self.num_planes(), "index out of range");
+
+        if self.is_hw_backed() {
+            self.inner.copy_hw_to_software()?;
+        }
+
+        let ptr = self.inner.data[index];
+        debug_assert!(!ptr.is_null());
+
+        let buffer = unsafe {
+            std::slice::from_raw_parts(ptr, self.inner.linesize[index] as usize)
+        };
+
+        Ok(buffer)
+    }
+
+    /// Convert the samples into interleaved `f32` values in the range `-1.0..=1.0`.
+    ///
+    /// Planar audio is interleaved and integer samples are normalised, i.e. `S16`
+    /// samples are divided by `32768`. Samples are ordered by channel within each
+    /// sample in the same order as [AudioFrame::channel_layout] describes.
+    ///
+    /// This is intended for quick playback, for anything more involved configure
+    /// the player with [MediaPlayerBuilder::with_target_audio_format] instead.
+    pub fn to_interleaved_f32(&mut self) -> Vec<f32> {
+        let mut output = Vec::with_capacity(self.num_channels() * self.num_samples());
+        self.for_each_sample(|sample| output.push(sample));
+        output
+    }
+
+    /// Returns the peak absolute sample value across all channels.
+    ///
+    /// Samples are normalised the same way as [AudioFrame::to_interleaved_f32],
+    /// so this is in the range `0.0..=1.0` where `1.0` is full scale. Float
+    /// samples can exceed full scale, in which case the audio is clipping.
+    ///
+    /// Returns `0.0` for a frame without any samples.
+    pub fn peak(&self) -> f32 {
+        let mut peak = 0.0f32;
+        self.for_each_sample(|sample| peak = peak.max(sample.abs()));
+        peak
+    }
+
+    /// Returns the root mean square of the samples across all channels.
+    ///
+    /// Like [AudioFrame::peak], this is in the range `0.0..=1.0` for samples
+    /// which do not exceed full scale, a full scale sine wave has an RMS of
+    /// roughly `0.707`.
+    ///
+    /// Returns `0.0` for a frame without any samples.
+    pub fn rms(&self) -> f32 {
+        let mut sum_of_squares = 0.0f64;
+        let mut count = 0usize;
+        self.for_each_sample(|sample| {
+            sum_of_squares += sample as f64 * sample as f64;
+            count += 1;
+        });
+
+        if count == 0 {
+            return 0.0;
+        }
+        (sum_of_squares / count as f64).sqrt() as f32
+    }
+
+    /// Returns the magnitude spectrum of the first `fft_size` samples, mixed down
+    /// to mono, as `fft_size / 2 + 1` bins from DC up to the Nyquist frequency.
     ///
-    /// If the frame is hardware backed, it will transfer the data
-    /// from the device to system memory which may increase latency.
-    pub fn plane_data(&mut self, index: usize) -> crate::Result<&[u8]> {
-        assert!(index < self.num_planes(), "index out of range");
+    /// Bin `k` is centred on `k * sample_rate / fft_size` Hz. A Hann window is
+    /// applied before the transform and the magnitudes are normalised so that
+    /// a full scale sine wave centred on a bin reads roughly `1.0`.
+    ///
+    /// If the frame has fewer than `fft_size` samples the remainder is padded
+    /// with silence. The transform is set up on every call, which is cheap
+    /// compared to decoding but worth keeping in mind for very large sizes.
+    ///
+    /// Returns an error if `fft_size` is not a power of two of at least `2`.
+    pub fn spectrum(&self, fft_size: usize) -> crate::Result<Vec<f32>> {
+        if fft_size < 2 || !fft_size.is_power_of_two() {
+            return Err(error::FFmpegError::custom(format!(
+                "fft size must be a power of two and at least 2, got {fft_size}",
+            ))
+            .into());
+        }
 
-        if self.is_hw_backed() {
-            self.inner.copy_hw_to_software()?;
+        let num_channels = self.num_channels().max(1);
+        let mut input = vec![0.0f32; fft_size];
+        let mut index = 0;
+        self.for_each_sample(|sample| {
+            if let Some(mono) = input.get_mut(index / num_channels) {
+                *mono += sample / num_channels as f32;
+            }
+            index += 1;
+        });
+
+        // A periodic Hann window, which keeps the leakage into neighbouring bins low.
+        let mut window_sum = 0.0f32;
+        for (n, sample) in input.iter_mut().enumerate() {
+            let phase = 2.0 * std::f32::consts::PI * n as f32 / fft_size as f32;
+            let weight = 0.5 - 0.5 * phase.cos();
+            *sample *= weight;
+            window_sum += weight;
         }
 
-        let ptr = self.inner.data[index];
-        debug_assert!(!ptr.is_null());
+        // The transform outputs `fft_size / 2 + 1` complex values.
+        let num_bins = fft_size / 2 + 1;
+        let mut output = vec![0.0f32; num_bins * 2];
+
+        let mut ctx = ptr::null_mut();
+        let mut transform = None;
+        let scale = 1.0f32;
+        let result = unsafe {
+            ffmpeg::av_tx_init(
+                &raw mut ctx,
+                &raw mut transform,
+                ffmpeg::AV_TX_FLOAT_RDFT,
+                0,
+                fft_size as i32,
+                ptr::from_ref(&scale).cast(),
+                ffmpeg::AV_TX_UNALIGNED as u64,
+            )
+        };
+        error::convert_ff_result(result)?;
+        let Some(transform) = transform else {
+            unsafe { ffmpeg::av_tx_uninit(&raw mut ctx) };
+            return Err(error::FFmpegError::custom("failed to create transform").into());
+        };
 
-        let buffer = unsafe {
-            std::slice::from_raw_parts(ptr, self.inner.linesize[index] as usize)
+        unsafe {
+            transform(
+                ctx,
+                output.as_mut_ptr().cast(),
+                input.as_mut_ptr().cast(),
+                mem::size_of::<f32>() as isize,
+            );
+            ffmpeg::av_tx_uninit(&raw mut ctx);
+        }
+
+        let spectrum = output
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(bin, complex)| {
+                let magnitude = complex[0].hypot(complex[1]) / window_sum;
+                // The energy of every other bin is split with its negative frequency.
+                if bin == 0 || bin == num_bins - 1 {
+                    magnitude
+                } else {
+                    magnitude * 2.0
+                }
+            })
+            .collect();
+        Ok(spectrum)
+    }
+
+    /// Call `callback` with every sample normalised into a `f32`, in interleaved order.
+    fn for_each_sample(&self, mut callback: impl FnMut(f32)) {
+        let num_channels = self.num_channels();
+        let num_samples = self.num_samples();
+        if num_channels == 0 || num_samples == 0 {
+            return;
+        }
+        let format = self.sample_format();
+
+        let av_sample_fmt = format.to_av_sample_fmt();
+        let bytes_per_sample =
+            unsafe { ffmpeg::av_get_bytes_per_sample(av_sample_fmt) } as usize;
+
+        // Audio with more than 8 channels only has its planes in `extended_data`.
+        let planes: Vec<&[u8]> = if format.is_planar() {
+            (0..num_channels)
+                .map(|channel| unsafe {
+                    let ptr = *self.inner.extended_data.add(channel);
+                    std::slice::from_raw_parts(ptr, num_samples * bytes_per_sample)
+                })
+                .collect()
+        } else {
+            let plane = unsafe {
+                std::slice::from_raw_parts(
+                    *self.inner.extended_data,
+                    num_samples * num_channels * bytes_per_sample,
+                )
+            };
+            vec![plane]
         };
 
-        Ok(buffer)
+        for sample in 0..num_samples {
+            for channel in 0..num_channels {
+                let (plane, index) = if format.is_planar() {
+                    (planes[channel], sample)
+                } else {
+                    (planes[0], sample * num_channels + channel)
+                };
+
+                let start = index * bytes_per_sample;
+                let bytes = &plane[start..start + bytes_per_sample];
+                callback(sample_to_f32(format, bytes));
+            }
+        }
+    }
+}
+
+/// Converts a single raw sample into a normalised `f32`.
+fn sample_to_f32(format: SampleFormat, bytes: &[u8]) -> f32 {
+    match format {
+        SampleFormat::U8 | SampleFormat::U8P => (bytes[0] as f32 - 128.0) / 128.0,
+        SampleFormat::S16 | SampleFormat::S16P => {
+            i16::from_ne_bytes(bytes.try_into().unwrap()) as f32 / 32_768.0
+        },
+        SampleFormat::S32 | SampleFormat::S32P => {
+            let sample = i32::from_ne_bytes(bytes.try_into().unwrap());
+            (sample as f64 / 2_147_483_648.0) as f32
+        },
+        SampleFormat::S64 | SampleFormat::S64P => {
+            let sample = i64::from_ne_bytes(bytes.try_into().unwrap());
+            (sample as f64 / 9_223_372_036_854_775_808.0) as f32
+        },
+        SampleFormat::FLT | SampleFormat::FLTP => {
+            f32::from_ne_bytes(bytes.try_into().unwrap())
+        },
+        SampleFormat::DBL | SampleFormat::DBLP => {
+            f64::from_ne_bytes(bytes.try_into().unwrap()) as f32
+        },
     }
 }
 
 impl Frame for AudioFrame {
     fn pts(&self) -> Duration {
-        pts_to_duration(self.inner.pts, self.inner.time_base)
+        pts_to_duration(self.inner.timestamp(), self.inner.time_base)
+    }
+
+    fn dts(&self) -> Option<Duration> {
+        self.inner.dts()
     }
 
     fn is_hw_backed(&self) -> bool {
@@ -785,12 +2754,41 @@ impl std::fmt::Debug for SubtitleFrame {
 }
 
 impl SubtitleFrame {
+    /// Returns the position the subtitle should be displayed from.
+    pub fn start_time(&self) -> Duration {
+        self.pts() + Duration::from_millis(self.inner.start_display_time as u64)
+    }
+
+    /// Returns the position the subtitle should be hidden at.
+    ///
+    /// Returns `None` if the subtitle should be displayed until the next one
+    /// replaces it.
+    pub fn end_time(&self) -> Option<Duration> {
+        if self.inner.end_display_time == 0 {
+            return None;
+        }
+        Some(self.pts() + Duration::from_millis(self.inner.end_display_time as u64))
+    }
+
+    /// Returns how long the subtitle should be displayed for.
+    ///
+    /// Returns `None` if the subtitle should be displayed until the next one
+    /// replaces it.
+    pub fn duration(&self) -> Option<Duration> {
+        let end_time = self.end_time()?;
+        Some(end_time.saturating_sub(self.start_time()))
+    }
+
     /// The format of the subtitle data.
+    ///
+    /// Returns [SubtitleFormat::Unknown] if the subtitle has no content.
     pub fn format(&self) -> SubtitleFormat {
-        SubtitleFormat::try_from_subtitle_format(
-            self.inner.format as ffmpeg::AVSubtitleType,
-        )
-        .expect("unsupported subtitle format provided")
+        // `AVSubtitle::format` only distinguishes graphics from text,
+        // so the type of the rectangles is used instead.
+        self.raw_rectangles()
+            .first()
+            .map(|rect| SubtitleFormat::from_av_subtitle_type(unsafe { (**rect).type_ }))
+            .unwrap_or(SubtitleFormat::Unknown)
     }
 
     /// Returns the subtitle text content if it is
@@ -828,7 +2826,7 @@ impl SubtitleFrame {
     /// Returns the subtitle content in bitmap form
     /// if the format matches.
     pub fn iter_bitmap(&self) -> Option<impl Iterator<Item = SubtitleBitmap<'_>>> {
-        if self.format() != SubtitleFormat::Bitmap {
+        if !self.format().is_bitmap() {
             return None;
         }
 
@@ -841,14 +2839,113 @@ impl SubtitleFrame {
         Some(iter)
     }
 
+    /// Returns the subtitle text content with any ASS dialogue fields
+    /// and override codes removed.
+    ///
+    /// The text of each rectangle is joined with a new line, `None` is returned
+    /// if the subtitle only contains bitmap data.
+    pub fn text(&self) -> Option<String> {
+        if !self.format().is_text() {
+            return None;
+        }
+        self.collect_text(true)
+    }
+
+    /// Returns the subtitle text content as produced by the decoder.
+    ///
+    /// Unlike [SubtitleFrame::text], this preserves the ASS dialogue fields
+    /// and override codes.
+    pub fn text_raw(&self) -> Option<String> {
+        if !self.format().is_text() {
+            return None;
+        }
+        self.collect_text(false)
+    }
+
+    fn collect_text(&self, strip_ass_markup: bool) -> Option<String> {
+        let mut output = String::new();
+        let mut has_text = false;
+
+        for rect in self.raw_rectangles() {
+            let rect = unsafe { &**rect };
+
+            let line = match rect.type_ {
+                ffmpeg::SUBTITLE_TEXT if !rect.text.is_null() => {
+                    let str_view = unsafe { std::ffi::CStr::from_ptr(rect.text) };
+                    str_view.to_string_lossy()
+                },
+                ffmpeg::SUBTITLE_ASS if !rect.ass.is_null() => {
+                    let str_view = unsafe { std::ffi::CStr::from_ptr(rect.ass) };
+                    let ass = str_view.to_string_lossy();
+                    if strip_ass_markup {
+                        Cow::Owned(strip_ass_event(&ass))
+                    } else {
+                        ass
+                    }
+                },
+                _ => continue,
+            };
+
+            if has_text {
+                output.push('\n');
+            }
+            output.push_str(&line);
+            has_text = true;
+        }
+
+        has_text.then_some(output)
+    }
+
     fn raw_rectangles(&self) -> &[*mut ffmpeg::AVSubtitleRect] {
-        unsafe { std::slice::from_raw_parts(self.inner.rects, self.inner.num_rects as usize) }
+        if self.inner.rects.is_null() {
+            return &[];
+        }
+        unsafe {
+            std::slice::from_raw_parts(self.inner.rects, self.inner.num_rects as usize)
+        }
+    }
+}
+
+/// Strips the dialogue fields and override codes from a decoded ASS event.
+///
+/// FFmpeg produces events in the form of
+/// `ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text`,
+/// we only keep the `Text` field and convert the hard line breaks and spaces.
+fn strip_ass_event(event: &str) -> String {
+    let text = event.splitn(9, ',').nth(8).unwrap_or(event);
+
+    let mut output = String::with_capacity(text.len());
+    let mut in_override = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => in_override = true,
+            '}' if in_override => in_override = false,
+            _ if in_override => {},
+            '\\' => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    output.push('\n');
+                },
+                Some('h') => {
+                    chars.next();
+                    output.push(' ');
+                },
+                _ => output.push(c),
+            },
+            _ => output.push(c),
+        }
     }
+    output
 }
 
 impl Frame for SubtitleFrame {
     fn pts(&self) -> Duration {
-        Duration::from_secs(0)
+        pts_to_duration(self.inner.pts, crate::AV_TIME_BASE_Q)
+    }
+
+    fn dts(&self) -> Option<Duration> {
+        None
     }
 
     fn is_hw_backed(&self) -> bool {
@@ -878,6 +2975,7 @@ impl<'frame> SubtitleBitmap<'frame> {
 }
 
 #[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Statistics collected from the player around timings, etc...
 pub struct PlayerStatistics {
     /// The number of video frames read from the stream and decoded so far.
@@ -898,14 +2996,86 @@ pub struct PlayerStatistics {
     ///
     /// This includes the time to read packets and decode.
     pub frames_total_time: Duration,
+    /// The number of frames discarded because they were too late to be displayed.
+    pub dropped_frames: u64,
+    /// The number of packets skipped because a decoder rejected them as corrupt,
+    /// see [MediaPlayerBuilder::skip_corrupt].
+    pub corrupt_packets_skipped: u64,
+    /// The rolling average number of frames decoded per second, based on the
+    /// time spent decoding each frame.
+    ///
+    /// This excludes the time spent reading packets, so reflects the throughput
+    /// of the decoders themselves.
+    pub decode_fps: f64,
+    /// The rolling average time taken to produce a frame, including the time
+    /// to read packets and decode.
+    pub average_decode_latency: Duration,
+}
+
+/// The weight given to the latest frame when updating the rolling averages.
+///
+/// This roughly averages over the last 20 frames.
+const STATISTICS_SMOOTHING_FACTOR: f64 = 0.1;
+
+impl PlayerStatistics {
+    /// Reset all the counters and timings back to zero.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Record a frame being returned by the player, taking `latency` in total
+    /// and `decode_time` of which was spent decoding.
+    fn record_frame(&mut self, latency: Duration, decode_time: Duration) {
+        self.frames_decoded_total += 1;
+        self.frames_total_time += latency;
+
+        if self.frames_decoded_total == 1 {
+            self.average_decode_latency = latency;
+        } else {
+            self.average_decode_latency = Duration::from_secs_f64(rolling_average(
+                self.average_decode_latency.as_secs_f64(),
+                latency.as_secs_f64(),
+            ));
+        }
+
+        // The frame time is recovered from the current FPS so no extra state is needed.
+        let decode_time = decode_time.as_secs_f64();
+        let average_decode_time = if self.decode_fps > 0.0 {
+            rolling_average(1.0 / self.decode_fps, decode_time)
+        } else {
+            decode_time
+        };
+        self.decode_fps = if average_decode_time > 0.0 {
+            1.0 / average_decode_time
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Returns the `timestamp` unchanged unless it is `AV_NOPTS_VALUE`, in which case
+/// the frame is ordered after every ready frame which has a timestamp.
+///
+/// The raw `AV_NOPTS_VALUE` is `i64::MIN` and would otherwise always be picked first,
+/// `i64::MAX` is reserved for streams with no ready frame.
+fn ready_timestamp_or_last(timestamp: i64) -> i64 {
+    if timestamp == ffmpeg::AV_NOPTS_VALUE {
+        i64::MAX - 1
+    } else {
+        timestamp
+    }
+}
+
+fn rolling_average(average: f64, value: f64) -> f64 {
+    average * (1.0 - STATISTICS_SMOOTHING_FACTOR) + value * STATISTICS_SMOOTHING_FACTOR
 }
 
-struct MediaRawFrame {
+pub(crate) struct MediaRawFrame {
     ptr: *mut ffmpeg::AVFrame,
 }
 
 impl MediaRawFrame {
-    fn new() -> Result<Self, error::FFmpegError> {
+    pub(crate) fn new() -> Result<Self, error::FFmpegError> {
         let packet = unsafe { ffmpeg::av_frame_alloc() };
         if packet.is_null() {
             Err(error::FFmpegError::custom("failed to allocate frame"))
@@ -916,21 +3086,71 @@ impl MediaRawFrame {
 
     fn copy_hw_to_software(&mut self) -> Result<(), error::FFmpegError> {
         if !self.hw_frames_ctx.is_null() {
-            #[allow(unused_mut)]
-            let mut sw_frame = MediaRawFrame::new()?;
-            let result =
-                unsafe { ffmpeg::av_hwframe_transfer_data(sw_frame.ptr, self.ptr, 0) };
-            error::convert_ff_result(result)?;
-            *self = sw_frame;
+            *self = self.to_software()?;
         }
         Ok(())
     }
 
+    /// Transfer the hardware frame into a new frame in system memory.
+    fn to_software(&self) -> Result<MediaRawFrame, error::FFmpegError> {
+        let sw_frame = MediaRawFrame::new()?;
+        let result =
+            unsafe { ffmpeg::av_hwframe_transfer_data(sw_frame.ptr, self.ptr, 0) };
+        error::convert_ff_result(result)?;
+        Ok(sw_frame)
+    }
+
     fn reset(&mut self) {
         unsafe { ffmpeg::av_frame_unref(self.ptr) }
     }
+
+    /// Returns the PTS of the frame, falling back to the best effort timestamp
+    /// estimated by FFmpeg if the frame has no PTS.
+    fn timestamp(&self) -> i64 {
+        if self.pts == ffmpeg::AV_NOPTS_VALUE {
+            self.best_effort_timestamp
+        } else {
+            self.pts
+        }
+    }
+
+    /// Returns the timestamp used to order the frame against the ready frames of
+    /// other streams, in `AV_TIME_BASE` units.
+    fn ready_timestamp(&self) -> i64 {
+        let timestamp = self.timestamp();
+        if timestamp == ffmpeg::AV_NOPTS_VALUE {
+            return ready_timestamp_or_last(timestamp);
+        }
+
+        unsafe { ffmpeg::av_rescale_q(timestamp, self.time_base, crate::AV_TIME_BASE_Q) }
+    }
+
+    fn dts(&self) -> Option<Duration> {
+        if self.pkt_dts == ffmpeg::AV_NOPTS_VALUE {
+            None
+        } else {
+            Some(pts_to_duration(self.pkt_dts, self.time_base))
+        }
+    }
+
+    /// Returns the side data of the given type attached to the frame, if any.
+    fn side_data(
+        &self,
+        kind: ffmpeg::AVFrameSideDataType,
+    ) -> Option<&ffmpeg::AVFrameSideData> {
+        let side_data = unsafe { ffmpeg::av_frame_get_side_data(self.ptr, kind) };
+        if side_data.is_null() || unsafe { (*side_data).data.is_null() } {
+            None
+        } else {
+            Some(unsafe { &*side_data })
+        }
+    }
 }
 
+// SAFETY: The frame is uniquely owned and FFmpeg allows frames to be
+//         unreferenced and freed from any thread.
+unsafe impl Send for MediaRawFrame {}
+
 impl std::ops::Deref for MediaRawFrame {
     type Target = ffmpeg::AVFrame;
 
@@ -954,6 +3174,79 @@ impl Drop for MediaRawFrame {
     }
 }
 
+/// The maximum number of released frames kept around for reuse.
+const MAX_POOLED_FRAMES: usize = 8;
+
+#[derive(Clone, Default)]
+/// A free-list of frames which can be reused by the player rather than
+/// allocating a new frame every time one is handed to the user.
+struct FramePool {
+    frames: Arc<Mutex<Vec<MediaRawFrame>>>,
+}
+
+impl FramePool {
+    /// Take a blank frame from the pool or allocate a new one if the pool is empty.
+    fn acquire(&self) -> Result<MediaRawFrame, error::FFmpegError> {
+        let maybe_frame = self.lock().pop();
+        match maybe_frame {
+            Some(frame) => Ok(frame),
+            None => MediaRawFrame::new(),
+        }
+    }
+
+    /// Wrap a frame so that it is returned to the pool once dropped.
+    fn wrap(&self, frame: MediaRawFrame) -> PooledFrame {
+        PooledFrame {
+            frame: Some(frame),
+            pool: self.clone(),
+        }
+    }
+
+    /// Unreference the frame data and return it to the pool.
+    fn release(&self, mut frame: MediaRawFrame) {
+        frame.reset();
+        let mut frames = self.lock();
+        if frames.len() < MAX_POOLED_FRAMES {
+            frames.push(frame);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<MediaRawFrame>> {
+        self.frames.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A [MediaRawFrame] which is returned to its [FramePool] on drop.
+///
+/// This is [Send] as the frame is uniquely owned and the pool is shared behind
+/// a lock, so the frame can be released from any thread.
+struct PooledFrame {
+    frame: Option<MediaRawFrame>,
+    pool: FramePool,
+}
+
+impl std::ops::Deref for PooledFrame {
+    type Target = MediaRawFrame;
+
+    fn deref(&self) -> &Self::Target {
+        self.frame.as_ref().expect("frame is only taken on drop")
+    }
+}
+
+impl std::ops::DerefMut for PooledFrame {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.frame.as_mut().expect("frame is only taken on drop")
+    }
+}
+
+impl Drop for PooledFrame {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            self.pool.release(frame);
+        }
+    }
+}
+
 struct SubtitleRawFrame {
     inner: ffmpeg::AVSubtitle,
 }
@@ -973,6 +3266,9 @@ impl SubtitleRawFrame {
     }
 }
 
+// SAFETY: The subtitle is uniquely owned and only freed on drop.
+unsafe impl Send for SubtitleRawFrame {}
+
 impl std::ops::Deref for SubtitleRawFrame {
     type Target = ffmpeg::AVSubtitle;
 
@@ -998,55 +3294,550 @@ struct TaggedDecoder<D> {
     decoder: D,
 }
 
+#[inline]
+fn notify_packet(
+    on_packet: &mut Option<PacketCallback>,
+    packet: &ffmpeg::AVPacket,
+    stream: &StreamInfo,
+) {
+    if let Some(on_packet) = on_packet.as_mut() {
+        on_packet(PacketInfo::new(packet, stream.time_base.to_av_rational()));
+    }
+}
+
 #[inline]
 fn ignore_out_of_data_error(
     result: Result<(), error::FFmpegError>,
 ) -> Result<bool, error::FFmpegError> {
     match result {
         Ok(()) => Ok(true),
-        Err(err) if err.needs_data() => Ok(false),
+        // A decoder which has been fully drained returns EOF, this must not stop
+        // the other decoders from returning the frames they still have buffered.
+        Err(err) if err.needs_data() || err.is_eof() => Ok(false),
         Err(err) => Err(err),
     }
 }
 
-struct MediaPacket {
-    ptr: *mut ffmpeg::AVPacket,
+/// Returns [error::PlayerError::ResolutionTooLarge] if the video frame exceeds
+/// the `max` resolution.
+fn check_frame_resolution(
+    video: &VideoFrame,
+    max: Option<Resolution>,
+) -> crate::Result<()> {
+    let Some(max) = max else {
+        return Ok(());
+    };
+    let resolution = Resolution {
+        width: video.width(),
+        height: video.height(),
+    };
+    if resolution.width > max.width || resolution.height > max.height {
+        return Err(error::PlayerError::ResolutionTooLarge { resolution, max });
+    }
+    Ok(())
 }
 
-impl MediaPacket {
-    fn new() -> Result<Self, error::FFmpegError> {
-        let packet = unsafe { ffmpeg::av_packet_alloc() };
-        if packet.is_null() {
-            Err(error::FFmpegError::custom("failed to allocate packet"))
-        } else {
-            Ok(Self { ptr: packet })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{
+        create_mkv_with_two_audio_tracks,
+        create_ts_with_programs,
+    };
+
+    /// Create a blank video frame, allocating its buffers if it has a size.
+    fn synthetic_video_frame(
+        format: ffmpeg::AVPixelFormat,
+        width: i32,
+        height: i32,
+    ) -> VideoFrame {
+        let pool = FramePool::default();
+        let mut raw_frame = pool.acquire().unwrap();
+        raw_frame.format = format;
+        raw_frame.width = width;
+        raw_frame.height = height;
+        if width > 0 && height > 0 {
+            let result = unsafe { ffmpeg::av_frame_get_buffer(raw_frame.ptr, 0) };
+            error::convert_ff_result(result).unwrap();
+        }
+
+        VideoFrame {
+            inner: pool.wrap(raw_frame),
+            display_index: 0,
         }
     }
 
-    fn reset(&mut self) {
-        unsafe { ffmpeg::av_packet_unref(self.ptr) }
+    /// Create a blank 48kHz stereo audio frame, allocating its buffers if it
+    /// has any samples.
+    fn synthetic_audio_frame(
+        format: ffmpeg::AVSampleFormat,
+        nb_samples: i32,
+    ) -> AudioFrame {
+        let pool = FramePool::default();
+        let mut raw_frame = pool.acquire().unwrap();
+        raw_frame.format = format;
+        raw_frame.sample_rate = 48_000;
+        raw_frame.nb_samples = nb_samples;
+        raw_frame.ch_layout = ChannelLayout::Stereo.to_av_channel_layout();
+        if nb_samples > 0 {
+            let result = unsafe { ffmpeg::av_frame_get_buffer(raw_frame.ptr, 0) };
+            error::convert_ff_result(result).unwrap();
+        }
+
+        AudioFrame {
+            inner: pool.wrap(raw_frame),
+            stream_index: 0,
+        }
     }
-}
 
-impl std::ops::Deref for MediaPacket {
-    type Target = ffmpeg::AVPacket;
+    #[test]
+    fn test_check_frame_resolution() {
+        let max = Resolution {
+            width: 1280,
+            height: 720,
+        };
 
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.ptr }
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_RGBA, 1280, 720);
+        check_frame_resolution(&video, Some(max)).unwrap();
+
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_RGBA, 1280, 1080);
+        match check_frame_resolution(&video, Some(max)) {
+            Err(error::PlayerError::ResolutionTooLarge { resolution, max }) => {
+                assert_eq!((resolution.width, resolution.height), (1280, 1080));
+                assert_eq!((max.width, max.height), (1280, 720));
+            },
+            other => panic!("expected resolution too large error, got {other:?}"),
+        }
+        check_frame_resolution(&video, None).unwrap();
     }
-}
 
-impl std::ops::DerefMut for MediaPacket {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.ptr }
+    #[test]
+    fn test_strip_ass_event() {
+        let event = "0,0,Default,,0,0,0,,{\\i1}Hello{\\i0}\\Nworld,\\hagain";
+        assert_eq!(strip_ass_event(event), "Hello\nworld, again");
+
+        assert_eq!(strip_ass_event("plain text"), "plain text");
     }
-}
 
-impl Drop for MediaPacket {
-    fn drop(&mut self) {
-        if !self.ptr.is_null() {
-            self.reset();
-            unsafe { ffmpeg::av_packet_free(&raw mut self.ptr) };
+    #[test]
+    fn test_sample_to_f32() {
+        assert_eq!(sample_to_f32(SampleFormat::U8, &[128]), 0.0);
+        assert_eq!(sample_to_f32(SampleFormat::U8P, &[0]), -1.0);
+        assert_eq!(
+            sample_to_f32(SampleFormat::S16, &i16::MIN.to_ne_bytes()),
+            -1.0
+        );
+        assert_eq!(
+            sample_to_f32(SampleFormat::S16P, &16_384i16.to_ne_bytes()),
+            0.5
+        );
+        assert_eq!(
+            sample_to_f32(SampleFormat::S32, &i32::MIN.to_ne_bytes()),
+            -1.0
+        );
+        assert_eq!(
+            sample_to_f32(SampleFormat::S64, &i64::MAX.to_ne_bytes()),
+            1.0
+        );
+        assert_eq!(
+            sample_to_f32(SampleFormat::FLTP, &0.25f32.to_ne_bytes()),
+            0.25
+        );
+        assert_eq!(
+            sample_to_f32(SampleFormat::DBL, &(-0.75f64).to_ne_bytes()),
+            -0.75
+        );
+    }
+
+    #[test]
+    fn test_statistics_record_frame() {
+        let mut statistics = PlayerStatistics::default();
+        for _ in 0..100 {
+            statistics
+                .record_frame(Duration::from_millis(20), Duration::from_millis(10));
+        }
+
+        assert_eq!(statistics.frames_decoded_total, 100);
+        assert_eq!(statistics.frames_total_time, Duration::from_secs(2));
+        assert!((statistics.decode_fps - 100.0).abs() < 1e-6);
+        assert!(
+            statistics
+                .average_decode_latency
+                .abs_diff(Duration::from_millis(20))
+                < Duration::from_micros(1)
+        );
+
+        statistics.reset();
+        assert_eq!(statistics.frames_decoded_total, 0);
+        assert_eq!(statistics.decode_fps, 0.0);
+        assert_eq!(statistics.average_decode_latency, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_frame_pts_falls_back_to_best_effort_timestamp() {
+        let mut video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_NONE, 0, 0);
+        video.inner.time_base = ffmpeg::AVRational { num: 1, den: 25 };
+        video.inner.pts = ffmpeg::AV_NOPTS_VALUE;
+        video.inner.best_effort_timestamp = 50;
+        video.inner.pkt_dts = ffmpeg::AV_NOPTS_VALUE;
+
+        let mut frame = DecodedFrame::Video(video);
+        assert_eq!(frame.pts(), Duration::from_secs(2));
+        assert_eq!(frame.dts(), None);
+
+        frame.offset_pts(Duration::from_secs(1));
+        assert_eq!(frame.pts(), Duration::from_secs(3));
+
+        let DecodedFrame::Video(video) = &mut frame else {
+            unreachable!()
+        };
+        video.inner.pts = 50;
+        video.inner.pkt_dts = 25;
+        assert_eq!(frame.pts(), Duration::from_secs(2));
+        assert_eq!(frame.dts(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_coded_picture_number() {
+        let mut video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_NONE, 0, 0);
+        assert_eq!(video.coded_picture_number(), None);
+
+        video.inner.opaque = ptr::without_provenance_mut(5);
+        assert_eq!(video.coded_picture_number(), Some(4));
+    }
+
+    #[test]
+    fn test_plane_height_uses_vertical_chroma_subsampling() {
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_YUV422P, 64, 48);
+        assert_eq!(video.num_planes(), 3);
+        assert_eq!(video.plane_width(0), 64);
+        assert_eq!(video.plane_height(0), 48);
+        for index in [1, 2] {
+            assert_eq!(video.plane_width(index), 32);
+            assert_eq!(video.plane_height(index), 48);
+        }
+    }
+
+    /// Create a single frame YUV4MPEG2 file in 4:2:2, with the top half of the
+    /// chroma planes set to `top` and the bottom half set to `bottom`.
+    fn create_y4m_422(width: usize, height: usize, top: u8, bottom: u8) -> Vec<u8> {
+        let mut y4m =
+            format!("YUV4MPEG2 W{width} H{height} F25:1 Ip A1:1 C422\nFRAME\n")
+                .into_bytes();
+        y4m.resize(y4m.len() + width * height, 128);
+        for _ in 0..2 {
+            let half = width / 2 * height / 2;
+            y4m.resize(y4m.len() + half, top);
+            y4m.resize(y4m.len() + half, bottom);
+        }
+        y4m
+    }
+
+    #[test]
+    fn test_plane_data_covers_full_chroma_height() {
+        let y4m = create_y4m_422(64, 48, 100, 200);
+        let mut source = InputSource::open_from_memory(y4m).unwrap();
+        let stream = unsafe { &*source.streams()[0] };
+
+        // The player always converts to an output format, so decode the raw frame.
+        let mut ctx = unsafe {
+            let codec = ffmpeg::avcodec_find_decoder((*stream.codecpar).codec_id);
+            let ctx = ffmpeg::avcodec_alloc_context3(codec);
+            let result = ffmpeg::avcodec_parameters_to_context(ctx, stream.codecpar);
+            error::convert_ff_result(result).unwrap();
+            let result = ffmpeg::avcodec_open2(ctx, codec, ptr::null_mut());
+            error::convert_ff_result(result).unwrap();
+            ctx
+        };
+        let mut packet = MediaPacket::new().unwrap();
+        source.read_packet(&mut packet).unwrap();
+        let pool = FramePool::default();
+        let raw_frame = pool.acquire().unwrap();
+        unsafe {
+            let result = ffmpeg::avcodec_send_packet(ctx, &*packet);
+            error::convert_ff_result(result).unwrap();
+            let result = ffmpeg::avcodec_receive_frame(ctx, raw_frame.ptr);
+            error::convert_ff_result(result).unwrap();
+            ffmpeg::avcodec_free_context(&raw mut ctx);
+        }
+        let mut video = VideoFrame {
+            inner: pool.wrap(raw_frame),
+            display_index: 0,
+        };
+        assert_eq!(video.inner.format, ffmpeg::AV_PIX_FMT_YUV422P);
+
+        for index in [1, 2] {
+            let stride = video.stride(index);
+            let data = video.plane_data(index).unwrap();
+            assert_eq!(data.len(), stride * 48);
+            assert!(data[..32].iter().all(|&value| value == 100));
+            // The bottom rows are missing if the plane is cut to half height.
+            assert!(data[47 * stride..][..32].iter().all(|&value| value == 200));
+        }
+    }
+
+    #[test]
+    fn test_num_planes_from_pixel_format() {
+        let cases = [
+            (ffmpeg::AV_PIX_FMT_NV12, 2),
+            (ffmpeg::AV_PIX_FMT_YUV420P, 3),
+            (ffmpeg::AV_PIX_FMT_RGBA, 1),
+            (ffmpeg::AV_PIX_FMT_P010LE, 2),
+        ];
+
+        for (format, expected) in cases {
+            // The planes are counted even before any data is allocated.
+            let video = synthetic_video_frame(format, 0, 0);
+            assert_eq!(video.num_planes(), expected, "format {format}");
+        }
+    }
+
+    #[test]
+    fn test_negative_linesize_plane_data() {
+        let mut video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_RGBA, 4, 3);
+
+        // Fill each row in memory with its index then flip the frame vertically
+        // the same way FFmpeg's `vflip` filter does.
+        let linesize = video.inner.linesize[0];
+        for row in 0..3 {
+            unsafe {
+                ptr::write_bytes(
+                    video.inner.data[0].add(row * linesize as usize),
+                    row as u8,
+                    linesize as usize,
+                );
+            }
         }
+        video.inner.data[0] = unsafe { video.inner.data[0].add(2 * linesize as usize) };
+        video.inner.linesize[0] = -linesize;
+        assert_eq!(video.stride(0), linesize as usize);
+
+        let data = video.plane_data(0).unwrap();
+        assert_eq!(data.len(), linesize as usize * 3);
+        assert_eq!(data[0], 0);
+        assert_eq!(data[data.len() - 1], 2);
+
+        let mut packed = Vec::new();
+        video.copy_to_tightly_packed(&mut packed).unwrap();
+        assert_eq!(packed.len(), 4 * 4 * 3);
+        assert!(packed[..16].iter().all(|&byte| byte == 2));
+        assert!(packed[16..32].iter().all(|&byte| byte == 1));
+        assert!(packed[32..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_audio_peak_and_rms() {
+        let audio = synthetic_audio_frame(ffmpeg::AV_SAMPLE_FMT_S16P, 4);
+
+        // The left channel is silent, the right alternates between half scale.
+        let channels: [[i16; 4]; 2] = [[0; 4], [16_384, -16_384, 16_384, -16_384]];
+        for (channel, samples) in channels.iter().enumerate() {
+            let plane = unsafe {
+                std::slice::from_raw_parts_mut(
+                    audio.inner.data[channel].cast::<i16>(),
+                    4,
+                )
+            };
+            plane.copy_from_slice(samples);
+        }
+        assert_eq!(audio.peak(), 0.5);
+        // Half the samples are silent, so the mean square is `0.5 * 0.5² = 0.125`.
+        assert!((audio.rms() - 0.125f32.sqrt()).abs() < 1e-6);
+
+        let empty = synthetic_audio_frame(ffmpeg::AV_SAMPLE_FMT_S16P, 0);
+        assert_eq!(empty.peak(), 0.0);
+        assert_eq!(empty.rms(), 0.0);
+    }
+
+    #[test]
+    fn test_video_average_hash() {
+        let mut video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_NV12, 64, 48);
+
+        // The right half of the luma plane is bright, the left half is dark.
+        let stride = video.stride(0);
+        let luma =
+            unsafe { std::slice::from_raw_parts_mut(video.inner.data[0], stride * 48) };
+        for row in luma.chunks_exact_mut(stride) {
+            row[..32].fill(16);
+            row[32..64].fill(235);
+        }
+        assert_eq!(video.average_hash().unwrap(), 0x0F0F_0F0F_0F0F_0F0F);
+
+        // A flat frame has no blocks brighter than the mean.
+        let stride = video.stride(0);
+        let luma =
+            unsafe { std::slice::from_raw_parts_mut(video.inner.data[0], stride * 48) };
+        luma.fill(128);
+        assert_eq!(video.average_hash().unwrap(), 0);
+
+        // Formats outside of the output formats are rejected rather than panicking.
+        let mut video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_GRAY8, 64, 48);
+        assert!(video.average_hash().is_err());
+    }
+
+    #[test]
+    fn test_audio_spectrum() {
+        const FFT_SIZE: usize = 1024;
+        const SINE_BIN: usize = 64;
+
+        let audio = synthetic_audio_frame(ffmpeg::AV_SAMPLE_FMT_FLT, FFT_SIZE as i32);
+
+        // The same full scale sine in both channels, centred on a single bin.
+        let samples = unsafe {
+            std::slice::from_raw_parts_mut(
+                audio.inner.data[0].cast::<f32>(),
+                FFT_SIZE * 2,
+            )
+        };
+        for (n, sample) in samples.chunks_exact_mut(2).enumerate() {
+            let phase =
+                2.0 * std::f32::consts::PI * (SINE_BIN * n) as f32 / FFT_SIZE as f32;
+            sample.fill(phase.sin());
+        }
+        let spectrum = audio.spectrum(FFT_SIZE).unwrap();
+        assert_eq!(spectrum.len(), FFT_SIZE / 2 + 1);
+
+        let (peak_bin, peak) = spectrum
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        assert_eq!(peak_bin, SINE_BIN);
+        assert!((peak - 1.0).abs() < 1e-3, "peak magnitude was {peak}");
+        // The Hann window leaks into the neighbouring bins only.
+        assert!(spectrum[SINE_BIN + 2..].iter().all(|&bin| bin < 1e-3));
+
+        let empty = synthetic_audio_frame(ffmpeg::AV_SAMPLE_FMT_FLT, 0);
+        assert!(empty.spectrum(16).unwrap().iter().all(|&bin| bin == 0.0));
+
+        for fft_size in [0, 1, 1000] {
+            assert!(audio.spectrum(fft_size).is_err());
+        }
+    }
+
+    fn next_audio_frame(player: &mut MediaPlayer) -> AudioFrame {
+        loop {
+            if let DecodedFrame::Audio(audio) = player.process_next_frame().unwrap() {
+                return audio;
+            }
+        }
+    }
+
+    #[test]
+    fn test_switch_audio_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("two-audio-tracks.mkv");
+        create_mkv_with_two_audio_tracks(&path);
+
+        let source = InputSource::open_file(&path).unwrap();
+        let mut player = MediaPlayerBuilder::for_source(source)
+            .with_audio_stream(Some(0))
+            .build()
+            .unwrap();
+        assert_eq!(player.available_audio_streams().len(), 2);
+        let format = player.audio_format().unwrap();
+        assert_eq!(format.sample_rate, 48_000);
+        assert_eq!(format.channels, 2);
+
+        player.play().unwrap();
+        assert_eq!(next_audio_frame(&mut player).stream_index(), 0);
+
+        player.switch_audio_stream(1).unwrap();
+        let switched_format = player.audio_format().unwrap();
+        assert_ne!(switched_format, format);
+        assert_eq!(switched_format.sample_rate, 22_050);
+        assert_eq!(switched_format.channels, 1);
+
+        let audio = next_audio_frame(&mut player);
+        assert_eq!(audio.stream_index(), 1);
+        assert_eq!(audio.sample_rate(), 22_050);
+        assert_eq!(audio.num_channels(), 1);
+    }
+
+    #[test]
+    fn test_with_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("programs.ts");
+        create_ts_with_programs(&path);
+
+        let source = InputSource::open_file(&path).unwrap();
+        let programs = source.programs();
+        let program_streams = |id: i32| {
+            let program = programs.iter().find(|program| program.id == id).unwrap();
+            program.stream_indices.clone()
+        };
+        let first_program = program_streams(1);
+        let second_program = program_streams(2);
+
+        let player = MediaPlayerBuilder::for_source(source)
+            .with_program(2)
+            .build()
+            .unwrap();
+        let video_index = player.decoder_video.as_ref().unwrap().stream.index;
+        let audio_index = player.decoder_audio.as_ref().unwrap().stream.index;
+        assert!(second_program.contains(&video_index));
+        assert!(second_program.contains(&audio_index));
+        // Only the audio is shared, so the video must not be from the first program.
+        assert!(!first_program.contains(&video_index));
+    }
+
+    #[cfg(feature = "wgpu")]
+    /// Create a device on the no-op backend, which needs no GPU.
+    fn noop_wgpu_device() -> (wgpu::Device, wgpu::Queue) {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        // The no-op backend resolves its requests immediately.
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let mut future = std::pin::pin!(future);
+            match future
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+            {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("wgpu request did not complete immediately"),
+            }
+        }
+
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::NOOP,
+            backend_options: wgpu::BackendOptions {
+                noop: wgpu::NoopBackendOptions { enable: true },
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let adapter =
+            block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .unwrap();
+        block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap()
+    }
+
+    #[cfg(feature = "wgpu")]
+    #[test]
+    fn test_video_import_to_wgpu() {
+        let (device, queue) = noop_wgpu_device();
+
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_RGBA, 64, 32);
+        let texture = video.import_to_wgpu(&device, &queue).unwrap();
+        assert_eq!(texture.width(), 64);
+        assert_eq!(texture.height(), 32);
+        assert_eq!(texture.format(), wgpu::TextureFormat::Rgba8Unorm);
+        assert!(
+            texture
+                .usage()
+                .contains(wgpu::TextureUsages::TEXTURE_BINDING)
+        );
+
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_BGRA, 64, 32);
+        let texture = video.import_to_wgpu(&device, &queue).unwrap();
+        assert_eq!(texture.format(), wgpu::TextureFormat::Bgra8Unorm);
+
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_YUV420P, 64, 32);
+        assert!(video.import_to_wgpu(&device, &queue).is_err());
+
+        let video = synthetic_video_frame(ffmpeg::AV_PIX_FMT_GRAY8, 64, 32);
+        assert!(video.import_to_wgpu(&device, &queue).is_err());
     }
 }
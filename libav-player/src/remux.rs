@@ -0,0 +1,161 @@
+use std::ffi::CString;
+use std::path::Path;
+use std::ptr;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::error;
+
+/// A container being written to a file, used to copy packets from an
+/// [InputSource](crate::InputSource) without re-encoding them.
+///
+/// The output format is guessed from the extension of the file.
+pub(crate) struct OutputContext {
+    ctx: *mut ffmpeg::AVFormatContext,
+}
+
+impl OutputContext {
+    /// Create the output context for the file at the given path, which is
+    /// kept as the URL of the context.
+    ///
+    /// The file is not created until [OutputContext::write_header] is called.
+    pub(crate) fn new(path: &Path) -> Result<Self, error::FFmpegError> {
+        let path = CString::new(path.to_string_lossy().as_bytes()).map_err(|_| {
+            error::FFmpegError::custom("output path contains a null byte")
+        })?;
+
+        let mut output = Self {
+            ctx: ptr::null_mut(),
+        };
+        let result = unsafe {
+            ffmpeg::avformat_alloc_output_context2(
+                &raw mut output.ctx,
+                ptr::null(),
+                ptr::null(),
+                path.as_ptr(),
+            )
+        };
+        error::convert_ff_result(result)?;
+        if output.ctx.is_null() {
+            return Err(error::FFmpegError::custom(
+                "failed to allocate output context",
+            ));
+        }
+
+        Ok(output)
+    }
+
+    /// Add a new stream to the output copying the codec parameters of the input
+    /// `stream`, returning the index of the new stream.
+    pub(crate) fn add_stream(
+        &mut self,
+        stream: &ffmpeg::AVStream,
+    ) -> Result<usize, error::FFmpegError> {
+        let output = unsafe { ffmpeg::avformat_new_stream(self.ctx, ptr::null()) };
+        if output.is_null() {
+            return Err(error::FFmpegError::custom(
+                "failed to allocate output stream",
+            ));
+        }
+
+        unsafe {
+            let output = &mut *output;
+            let result =
+                ffmpeg::avcodec_parameters_copy(output.codecpar, stream.codecpar);
+            error::convert_ff_result(result)?;
+
+            // The tag is specific to the input container, so the muxer picks its own.
+            (*output.codecpar).codec_tag = 0;
+            output.time_base = stream.time_base;
+            output.disposition = stream.disposition;
+
+            Ok(output.index as usize)
+        }
+    }
+
+    /// Open the output file and write the container header.
+    ///
+    /// This must be called after all the streams are added and before
+    /// any packets are written.
+    pub(crate) fn write_header(&mut self) -> Result<(), error::FFmpegError> {
+        unsafe {
+            let ctx = &mut *self.ctx;
+            if (*ctx.oformat).flags & ffmpeg::AVFMT_NOFILE as i32 == 0 {
+                let result = ffmpeg::avio_open(
+                    &raw mut ctx.pb,
+                    ctx.url,
+                    ffmpeg::AVIO_FLAG_WRITE as i32,
+                );
+                error::convert_ff_result(result)
+                    .map_err(|err| err.with_context("opening output file"))?;
+            }
+
+            let result = ffmpeg::avformat_write_header(self.ctx, ptr::null_mut());
+            error::convert_ff_result(result)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the packet to the output stream at the given index, rescaling its
+    /// timestamps from the `time_base` of the input stream.
+    ///
+    /// The muxer takes ownership of the packet data, leaving the packet blank.
+    pub(crate) fn write_packet(
+        &mut self,
+        packet: &mut ffmpeg::AVPacket,
+        time_base: ffmpeg::AVRational,
+        stream_index: usize,
+    ) -> Result<(), error::FFmpegError> {
+        unsafe {
+            let stream = &**(*self.ctx).streams.add(stream_index);
+            ffmpeg::av_packet_rescale_ts(packet, time_base, stream.time_base);
+        }
+        packet.stream_index = stream_index as i32;
+        // The byte position within the input is meaningless in the output.
+        packet.pos = -1;
+
+        let result = unsafe { ffmpeg::av_interleaved_write_frame(self.ctx, packet) };
+        error::convert_ff_result(result)?;
+        Ok(())
+    }
+
+    /// Flush any interleaved packets and write the container trailer.
+    pub(crate) fn write_trailer(&mut self) -> Result<(), error::FFmpegError> {
+        let result = unsafe { ffmpeg::av_write_trailer(self.ctx) };
+        error::convert_ff_result(result)?;
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for OutputContext {
+    type Target = ffmpeg::AVFormatContext;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ctx }
+    }
+}
+
+impl std::ops::DerefMut for OutputContext {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.ctx }
+    }
+}
+
+impl Drop for OutputContext {
+    fn drop(&mut self) {
+        if self.ctx.is_null() {
+            return;
+        }
+
+        unsafe {
+            let ctx = &mut *self.ctx;
+            if !ctx.pb.is_null()
+                && (*ctx.oformat).flags & ffmpeg::AVFMT_NOFILE as i32 == 0
+            {
+                ffmpeg::avio_closep(&raw mut ctx.pb);
+            }
+            ffmpeg::avformat_free_context(self.ctx);
+        }
+    }
+}
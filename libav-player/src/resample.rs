@@ -0,0 +1,236 @@
+use std::{mem, ptr};
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::{AudioFormat, ChannelLayout, error};
+
+/// Converts decoded audio frames into a fixed target [AudioFormat] using `swresample`.
+///
+/// The resampler is configured lazily from the first frame it receives and will be
+/// reconfigured if the layout of the incoming audio changes mid-stream.
+pub(crate) struct AudioResampler {
+    ctx: *mut ffmpeg::SwrContext,
+    target: AudioFormat,
+    target_layout: ffmpeg::AVChannelLayout,
+    input_format: ffmpeg::AVSampleFormat,
+    input_sample_rate: i32,
+    input_layout: ffmpeg::AVChannelLayout,
+    /// The PTS following the last frame converted, used to timestamp the
+    /// samples drained by [AudioResampler::flush].
+    next_pts: i64,
+    next_pts_time_base: ffmpeg::AVRational,
+}
+
+impl AudioResampler {
+    /// Create a new resampler producing frames in the target format.
+    ///
+    /// If a `layout` is provided the audio is remixed into that layout using
+    /// FFmpeg's standard coefficients, overriding the number of channels in the target.
+    /// Otherwise, the default layout for the number of target channels is used.
+    pub(crate) fn new(mut target: AudioFormat, layout: Option<ChannelLayout>) -> Self {
+        let target_layout = match layout {
+            Some(layout) => {
+                target.channels = layout.num_channels() as u32;
+                layout.to_av_channel_layout()
+            },
+            None => ChannelLayout::Unspecified {
+                channels: target.channels,
+            }
+            .to_av_channel_layout(),
+        };
+
+        Self {
+            ctx: ptr::null_mut(),
+            target,
+            target_layout,
+            input_format: ffmpeg::AV_SAMPLE_FMT_NONE,
+            input_sample_rate: 0,
+            input_layout: unsafe { mem::zeroed() },
+            next_pts: ffmpeg::AV_NOPTS_VALUE,
+            next_pts_time_base: ffmpeg::AVRational { num: 0, den: 1 },
+        }
+    }
+
+    #[inline]
+    /// Returns the format frames are converted into.
+    pub(crate) fn target(&self) -> AudioFormat {
+        self.target
+    }
+
+    /// Convert the `input` frame into the target format, writing the result to `output`.
+    ///
+    /// Returns `EAGAIN` if the resampler is buffering and has no samples
+    /// to output yet.
+    pub(crate) fn convert(
+        &mut self,
+        input: &ffmpeg::AVFrame,
+        output: &mut ffmpeg::AVFrame,
+    ) -> Result<(), error::FFmpegError> {
+        if self.needs_configure(input) {
+            self.configure(input)?;
+        }
+
+        unsafe { ffmpeg::av_frame_unref(output) };
+        // This must happen first, as the properties include the input sample rate.
+        let result = unsafe { ffmpeg::av_frame_copy_props(output, input) };
+        error::convert_ff_result(result)?;
+        self.describe_output(output)?;
+
+        if input.pts != ffmpeg::AV_NOPTS_VALUE && input.sample_rate > 0 {
+            let duration = unsafe {
+                ffmpeg::av_rescale_q(
+                    input.nb_samples as i64,
+                    ffmpeg::AVRational {
+                        num: 1,
+                        den: input.sample_rate,
+                    },
+                    input.time_base,
+                )
+            };
+            self.next_pts = input.pts + duration;
+            self.next_pts_time_base = input.time_base;
+        }
+
+        let result = unsafe { ffmpeg::swr_convert_frame(self.ctx, output, input) };
+        error::convert_ff_result(result)?;
+
+        if output.nb_samples == 0 {
+            return Err(error::FFmpegError::from_raw_errno(-(ffmpeg::EAGAIN as i32)));
+        }
+
+        Ok(())
+    }
+
+    /// Drain the samples the resampler is still holding back into `output`,
+    /// this should be called once the input has ended.
+    ///
+    /// Returns `EOF` once there are no samples left to drain.
+    pub(crate) fn flush(
+        &mut self,
+        output: &mut ffmpeg::AVFrame,
+    ) -> Result<(), error::FFmpegError> {
+        let eof = || error::FFmpegError::from_raw_errno(ffmpeg::AVERROR_EOF);
+        if self.ctx.is_null() {
+            return Err(eof());
+        }
+
+        unsafe { ffmpeg::av_frame_unref(output) };
+        self.describe_output(output)?;
+        output.pts = self.next_pts;
+        output.time_base = self.next_pts_time_base;
+
+        let result = unsafe { ffmpeg::swr_convert_frame(self.ctx, output, ptr::null()) };
+        error::convert_ff_result(result)?;
+
+        if output.nb_samples == 0 {
+            return Err(eof());
+        }
+
+        if self.next_pts != ffmpeg::AV_NOPTS_VALUE {
+            self.next_pts += unsafe {
+                ffmpeg::av_rescale_q(
+                    output.nb_samples as i64,
+                    ffmpeg::AVRational {
+                        num: 1,
+                        den: output.sample_rate,
+                    },
+                    self.next_pts_time_base,
+                )
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Describe the target format on the `output` frame, so `swr_convert_frame`
+    /// allocates its buffers in that format.
+    fn describe_output(
+        &self,
+        output: &mut ffmpeg::AVFrame,
+    ) -> Result<(), error::FFmpegError> {
+        let result = unsafe {
+            ffmpeg::av_channel_layout_copy(&mut output.ch_layout, &self.target_layout)
+        };
+        error::convert_ff_result(result)?;
+        output.format = self.target.sample_format.to_av_sample_fmt();
+        output.sample_rate = self.target.sample_rate as i32;
+        Ok(())
+    }
+
+    fn needs_configure(&self, frame: &ffmpeg::AVFrame) -> bool {
+        self.ctx.is_null()
+            || frame.format != self.input_format
+            || frame.sample_rate != self.input_sample_rate
+            || unsafe {
+                ffmpeg::av_channel_layout_compare(&frame.ch_layout, &self.input_layout)
+                    != 0
+            }
+    }
+
+    fn configure(&mut self, frame: &ffmpeg::AVFrame) -> Result<(), error::FFmpegError> {
+        self.reset();
+
+        let result = unsafe {
+            ffmpeg::swr_alloc_set_opts2(
+                &raw mut self.ctx,
+                &self.target_layout,
+                self.target.sample_format.to_av_sample_fmt(),
+                self.target.sample_rate as i32,
+                &frame.ch_layout,
+                frame.format,
+                frame.sample_rate,
+                0,
+                ptr::null_mut(),
+            )
+        };
+        if let Err(err) = error::convert_ff_result(result) {
+            self.reset();
+            return Err(err);
+        }
+
+        let result = unsafe { ffmpeg::swr_init(self.ctx) };
+        if let Err(err) = error::convert_ff_result(result) {
+            self.reset();
+            return Err(err);
+        }
+
+        let result = unsafe {
+            ffmpeg::av_channel_layout_copy(&mut self.input_layout, &frame.ch_layout)
+        };
+        if let Err(err) = error::convert_ff_result(result) {
+            self.reset();
+            return Err(err);
+        }
+        self.input_format = frame.format;
+        self.input_sample_rate = frame.sample_rate;
+
+        tracing::debug!(
+            input_sample_rate = frame.sample_rate,
+            input_channels = frame.ch_layout.nb_channels,
+            target = ?self.target,
+            "configured audio resampler",
+        );
+
+        Ok(())
+    }
+
+    /// Free the resampler context and any samples it has buffered.
+    ///
+    /// The resampler will be reconfigured on the next call to [AudioResampler::convert].
+    pub(crate) fn reset(&mut self) {
+        if !self.ctx.is_null() {
+            unsafe { ffmpeg::swr_free(&raw mut self.ctx) };
+        }
+        unsafe { ffmpeg::av_channel_layout_uninit(&mut self.input_layout) };
+        self.input_format = ffmpeg::AV_SAMPLE_FMT_NONE;
+        self.input_sample_rate = 0;
+        self.next_pts = ffmpeg::AV_NOPTS_VALUE;
+    }
+}
+
+impl Drop for AudioResampler {
+    fn drop(&mut self) {
+        self.reset();
+        unsafe { ffmpeg::av_channel_layout_uninit(&mut self.target_layout) };
+    }
+}
@@ -2,9 +2,10 @@ use std::time::Duration;
 
 use rusty_ffmpeg::ffi as ffmpeg;
 
-use crate::{MediaType, pts_to_duration};
+use crate::{ChannelLayout, MediaType, pts_to_duration};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A single immutable audio, video or subtitle stream from an [InputSource](crate::InputSource).
 pub struct StreamInfo {
     /// The media type of the stream.
@@ -18,6 +19,12 @@ pub struct StreamInfo {
     /// Returns the resolution of the stream, providing it is a
     /// video stream.
     pub resolution: Option<Resolution>,
+    /// Returns the name of the pixel format the stream is encoded in, i.e. `yuv420p`
+    /// or `yuv420p10le`, providing it is a video stream.
+    ///
+    /// This is the format stored in the container and not the format of the
+    /// frames produced by the player.
+    pub pixel_format: Option<String>,
     /// Returns the total number of frames in the stream.
     pub num_frames: usize,
     /// The estimated duration of the stream.
@@ -29,6 +36,42 @@ pub struct StreamInfo {
     pub bitrate: Option<usize>,
     /// Returns the name of the media codec this stream uses.
     pub codec_name: String,
+    /// The name of the codec profile the stream uses, i.e. `High` for H.264 or
+    /// `Main 10` for HEVC.
+    ///
+    /// This is `None` if the profile is unknown.
+    pub profile: Option<String>,
+    /// The codec level the stream uses, the meaning of this is codec specific.
+    ///
+    /// This is negative if the level is unknown.
+    pub level: i32,
+    /// The language of the stream if the container provides it.
+    ///
+    /// This is typically an ISO 639-2 code like `eng`, note some containers
+    /// use `und` to mark the language as undefined.
+    pub language: Option<String>,
+    /// The disposition flags of the stream, describing how the
+    /// stream is intended to be used.
+    pub disposition: StreamDisposition,
+    /// The clockwise rotation in degrees which must be applied to display the
+    /// video correctly, typically `0`, `90`, `180` or `270`.
+    ///
+    /// This is `0` if the stream carries no display matrix.
+    pub rotation: f64,
+    /// The width to height ratio of a single pixel, as `(numerator, denominator)`.
+    ///
+    /// This is `(1, 1)` for square pixels or if the stream does not specify it,
+    /// anamorphic content will have non-square pixels.
+    pub sample_aspect_ratio: (u32, u32),
+    /// Returns the sample rate of the stream, providing it is an audio stream.
+    pub sample_rate: Option<u32>,
+    /// Returns the channel layout of the stream, providing it is an audio stream.
+    pub channel_layout: Option<ChannelLayout>,
+    /// The codec specific data stored by the container, see [StreamInfo::extradata].
+    extradata: Vec<u8>,
+    // The codec is looked up from the source when opening the stream, so is not
+    // meaningful outside of the process.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) codec_id: ffmpeg::AVCodecID,
 }
 
@@ -42,8 +85,18 @@ impl std::fmt::Debug for StreamInfo {
             .field("num_frames", &self.num_frames)
             .field("duration", &self.duration)
             .field("resolution", &self.resolution)
+            .field("pixel_format", &self.pixel_format)
             .field("bitrate", &self.bitrate)
             .field("codec_name", &self.codec_name)
+            .field("profile", &self.profile)
+            .field("level", &self.level)
+            .field("language", &self.language)
+            .field("disposition", &self.disposition)
+            .field("rotation", &self.rotation)
+            .field("sample_aspect_ratio", &self.sample_aspect_ratio)
+            .field("sample_rate", &self.sample_rate)
+            .field("channel_layout", &self.channel_layout)
+            .field("extradata_size", &self.extradata.len())
             .finish()
     }
 }
@@ -68,11 +121,36 @@ impl StreamInfo {
         let duration = pts_to_duration(stream.duration, stream.time_base);
 
         let mut resolution = None;
+        let mut pixel_format = None;
         if media_type == MediaType::Video {
             resolution = Some(Resolution {
                 width: codec_params.width as usize,
                 height: codec_params.height as usize,
             });
+
+            let name = unsafe {
+                ffmpeg::av_get_pix_fmt_name(codec_params.format as ffmpeg::AVPixelFormat)
+            };
+            if !name.is_null() {
+                let raw_name = unsafe { std::ffi::CStr::from_ptr(name) };
+                pixel_format = Some(raw_name.to_string_lossy().to_string());
+            }
+        }
+
+        // Containers can override the aspect ratio provided by the codec.
+        let sample_aspect_ratio = if stream.sample_aspect_ratio.num > 0 {
+            sample_aspect_ratio_from_av(stream.sample_aspect_ratio)
+        } else {
+            sample_aspect_ratio_from_av(codec_params.sample_aspect_ratio)
+        };
+
+        let mut sample_rate = None;
+        let mut channel_layout = None;
+        if media_type == MediaType::Audio {
+            sample_rate = Some(codec_params.sample_rate.max(0) as u32);
+            channel_layout = Some(ChannelLayout::from_av_channel_layout(
+                &codec_params.ch_layout,
+            ));
         }
 
         let mut bitrate = None;
@@ -89,6 +167,49 @@ impl StreamInfo {
             "unknown".to_string()
         };
 
+        // Unknown profiles are negative, typically `AV_PROFILE_UNKNOWN` or `-1`.
+        let profile = if codec_params.profile < 0 {
+            None
+        } else {
+            let name = unsafe {
+                ffmpeg::avcodec_profile_name(codec_params.codec_id, codec_params.profile)
+            };
+            if name.is_null() {
+                None
+            } else {
+                let raw_name = unsafe { std::ffi::CStr::from_ptr(name) };
+                Some(raw_name.to_string_lossy().to_string())
+            }
+        };
+
+        let language = unsafe { crate::get_metadata(stream.metadata, c"language") };
+        let disposition = StreamDisposition::from_av_disposition(stream.disposition);
+
+        let display_matrix = unsafe {
+            ffmpeg::av_packet_side_data_get(
+                codec_params.coded_side_data,
+                codec_params.nb_coded_side_data,
+                ffmpeg::AV_PKT_DATA_DISPLAYMATRIX,
+            )
+        };
+        let rotation = if display_matrix.is_null() {
+            0.0
+        } else {
+            unsafe { crate::display_matrix_rotation((*display_matrix).data.cast()) }
+        };
+
+        let extradata = if codec_params.extradata.is_null() {
+            Vec::new()
+        } else {
+            unsafe {
+                std::slice::from_raw_parts(
+                    codec_params.extradata,
+                    codec_params.extradata_size.max(0) as usize,
+                )
+            }
+            .to_vec()
+        };
+
         Self {
             media_type,
             index,
@@ -97,19 +218,114 @@ impl StreamInfo {
             num_frames,
             duration,
             resolution,
+            pixel_format,
             bitrate,
             codec_name,
+            profile,
+            level: codec_params.level,
+            language,
+            disposition,
+            rotation,
+            sample_aspect_ratio,
+            sample_rate,
+            channel_layout,
+            extradata,
             codec_id: codec_params.codec_id,
         }
     }
 
+    #[inline]
+    /// Returns the codec private data the container stores for the stream, i.e.
+    /// the SPS/PPS of H.264 or the `AudioSpecificConfig` of AAC.
+    ///
+    /// This is needed to initialise a decoder for the stream elsewhere, like in
+    /// another pipeline or an SDP description. The format is codec and container
+    /// specific, MP4 for example stores H.264 as an `avcC` record rather than Annex-B.
+    ///
+    /// Returns `None` if the stream has no extradata.
+    pub fn extradata(&self) -> Option<&[u8]> {
+        if self.extradata.is_empty() {
+            None
+        } else {
+            Some(&self.extradata)
+        }
+    }
+
+    /// Returns the aspect ratio the video should be displayed at, as `(numerator, denominator)`.
+    ///
+    /// This is the resolution scaled by the [StreamInfo::sample_aspect_ratio] and
+    /// reduced, i.e. `(16, 9)`. Returns `None` if the stream is not a video stream.
+    pub fn display_aspect_ratio(&self) -> Option<(u32, u32)> {
+        let resolution = self.resolution?;
+        let (sar_num, sar_den) = self.sample_aspect_ratio;
+
+        let numerator = resolution.width as u64 * sar_num as u64;
+        let denominator = resolution.height as u64 * sar_den as u64;
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+
+        let divisor = gcd(numerator, denominator);
+        Some(((numerator / divisor) as u32, (denominator / divisor) as u32))
+    }
+
     pub(crate) fn codec(&self) -> &'static ffmpeg::AVCodec {
         crate::codec::find_decoder_by_id(self.codec_id)
             .expect("codec could not be found")
     }
 }
 
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The disposition flags of a stream.
+///
+/// These are used to pick between multiple streams of the same type,
+/// for example, selecting the forced subtitles for a given language.
+pub struct StreamDisposition {
+    /// The stream should be selected by default.
+    pub default: bool,
+    /// The stream should always be displayed, typically used for subtitles
+    /// which only translate foreign dialogue.
+    pub forced: bool,
+    /// The stream is intended for hearing impaired audiences.
+    pub hearing_impaired: bool,
+    /// The stream is intended for visually impaired audiences.
+    pub visual_impaired: bool,
+    /// The stream contains commentary.
+    pub comment: bool,
+}
+
+impl StreamDisposition {
+    pub(crate) fn from_av_disposition(disposition: i32) -> Self {
+        let has_flag = |flag: u32| disposition & flag as i32 != 0;
+        Self {
+            default: has_flag(ffmpeg::AV_DISPOSITION_DEFAULT),
+            forced: has_flag(ffmpeg::AV_DISPOSITION_FORCED),
+            hearing_impaired: has_flag(ffmpeg::AV_DISPOSITION_HEARING_IMPAIRED),
+            visual_impaired: has_flag(ffmpeg::AV_DISPOSITION_VISUAL_IMPAIRED),
+            comment: has_flag(ffmpeg::AV_DISPOSITION_COMMENT),
+        }
+    }
+}
+
+/// Converts a sample aspect ratio, treating the `0/1` "unknown" value as square pixels.
+pub(crate) fn sample_aspect_ratio_from_av(ratio: ffmpeg::AVRational) -> (u32, u32) {
+    if ratio.num <= 0 || ratio.den <= 0 {
+        (1, 1)
+    } else {
+        (ratio.num as u32, ratio.den as u32)
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The fractional rate of a given stream.
 ///
 /// This is represented in the form of a numerator and a denominator.
@@ -130,6 +346,16 @@ impl std::fmt::Debug for Fraction {
     }
 }
 
+impl std::fmt::Display for Fraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == 0 {
+            write!(f, "unknown fps")
+        } else {
+            write!(f, "{:.2} fps", self.as_f32())
+        }
+    }
+}
+
 impl Fraction {
     /// Creates a new [Fraction] using the given fractional components.
     pub(crate) fn new(numerator: usize, denominator: usize) -> Self {
@@ -176,6 +402,7 @@ impl PartialEq for Fraction {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The resolution of a video stream.
 pub struct Resolution {
     /// The width of the video resolution in pixels.
@@ -183,3 +410,27 @@ pub struct Resolution {
     /// The height of the video resolution in pixels.
     pub height: usize,
 }
+
+impl std::fmt::Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl Resolution {
+    /// Returns the ratio of the width to the height, i.e. `1.777` for 1920x1080.
+    ///
+    /// Returns `0.0` if the height is zero.
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.height == 0 {
+            0.0
+        } else {
+            self.width as f32 / self.height as f32
+        }
+    }
+
+    /// Returns the number of pixels in millions, i.e. `2.07` for 1920x1080.
+    pub fn megapixels(&self) -> f32 {
+        (self.width * self.height) as f32 / 1_000_000.0
+    }
+}
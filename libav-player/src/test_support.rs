@@ -0,0 +1,220 @@
+use std::ffi::CStr;
+use std::path::Path;
+use std::ptr;
+
+use rusty_ffmpeg::ffi as ffmpeg;
+
+use crate::packet::MediaPacket;
+use crate::remux::OutputContext;
+use crate::{InputSource, error};
+
+/// Add a font attachment stream holding `data` to the output, returning the
+/// index of the new stream.
+pub(crate) fn add_attachment(
+    output: &mut OutputContext,
+    filename: &CStr,
+    mime_type: &CStr,
+    data: &[u8],
+) -> Result<usize, error::FFmpegError> {
+    let stream = unsafe { ffmpeg::avformat_new_stream(&mut **output, ptr::null()) };
+    if stream.is_null() {
+        return Err(error::FFmpegError::custom(
+            "failed to allocate output stream",
+        ));
+    }
+
+    unsafe {
+        let stream = &mut *stream;
+        let codec_params = &mut *stream.codecpar;
+        codec_params.codec_type = ffmpeg::AVMEDIA_TYPE_ATTACHMENT;
+        codec_params.codec_id = ffmpeg::AV_CODEC_ID_TTF;
+
+        // Attachments store the whole file as the codec extradata.
+        let extradata = ffmpeg::av_mallocz(
+            data.len() + ffmpeg::AV_INPUT_BUFFER_PADDING_SIZE as usize,
+        ) as *mut u8;
+        if extradata.is_null() {
+            return Err(error::FFmpegError::custom(
+                "failed to allocate attachment data",
+            ));
+        }
+        ptr::copy_nonoverlapping(data.as_ptr(), extradata, data.len());
+        codec_params.extradata = extradata;
+        codec_params.extradata_size = data.len() as i32;
+
+        let result = ffmpeg::av_dict_set(
+            &raw mut stream.metadata,
+            c"filename".as_ptr(),
+            filename.as_ptr(),
+            0,
+        );
+        error::convert_ff_result(result)?;
+        let result = ffmpeg::av_dict_set(
+            &raw mut stream.metadata,
+            c"mimetype".as_ptr(),
+            mime_type.as_ptr(),
+            0,
+        );
+        error::convert_ff_result(result)?;
+
+        Ok(stream.index as usize)
+    }
+}
+
+/// Add a program with the given `id` containing the streams at
+/// `stream_indices`, which may also belong to other programs.
+pub(crate) fn add_program(
+    output: &mut OutputContext,
+    id: i32,
+    stream_indices: &[usize],
+) -> Result<(), error::FFmpegError> {
+    let ctx: *mut ffmpeg::AVFormatContext = &mut **output;
+    let program = unsafe { ffmpeg::av_new_program(ctx, id) };
+    if program.is_null() {
+        return Err(error::FFmpegError::custom("failed to allocate program"));
+    }
+
+    for &index in stream_indices {
+        unsafe { ffmpeg::av_program_add_stream_index(ctx, id, index as u32) };
+    }
+
+    Ok(())
+}
+
+/// Write a Matroska file at `path` with the start of the test audio stream
+/// followed by a font attachment holding `font`.
+pub(crate) fn create_mkv_with_attachment(path: &Path, font: &[u8]) {
+    let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+    let mut output = OutputContext::new(path).unwrap();
+    let audio = unsafe { &*source.streams()[1] };
+    let time_base = audio.time_base;
+    output.add_stream(audio).unwrap();
+    add_attachment(
+        &mut output,
+        c"font.ttf",
+        c"application/x-truetype-font",
+        font,
+    )
+    .unwrap();
+    output.write_header().unwrap();
+
+    let mut packet = MediaPacket::new().unwrap();
+    let mut written = 0;
+    while written < 20 {
+        packet.reset();
+        source.read_packet(&mut packet).unwrap();
+        if packet.stream_index == 1 {
+            output.write_packet(&mut packet, time_base, 0).unwrap();
+            written += 1;
+        }
+    }
+    output.write_trailer().unwrap();
+}
+
+/// Write a MPEG-TS file with two programs sharing the same audio stream,
+/// each with their own copy of the video.
+pub(crate) fn create_ts_with_programs(path: &Path) {
+    let mut source = InputSource::open_file("../media/test.mp4").unwrap();
+    let mut output = OutputContext::new(path).unwrap();
+    let video = unsafe { &*source.streams()[0] };
+    let audio = unsafe { &*source.streams()[1] };
+    let video_time_base = video.time_base;
+    let audio_time_base = audio.time_base;
+    output.add_stream(audio).unwrap();
+    output.add_stream(video).unwrap();
+    output.add_stream(video).unwrap();
+    add_program(&mut output, 1, &[0, 1]).unwrap();
+    add_program(&mut output, 2, &[0, 2]).unwrap();
+    output.write_header().unwrap();
+
+    let mut packet = MediaPacket::new().unwrap();
+    let mut copy = MediaPacket::new().unwrap();
+    for _ in 0..60 {
+        packet.reset();
+        source.read_packet(&mut packet).unwrap();
+        if packet.stream_index == 1 {
+            output
+                .write_packet(&mut packet, audio_time_base, 0)
+                .unwrap();
+            continue;
+        }
+
+        copy.reset();
+        let result = unsafe { ffmpeg::av_packet_ref(&mut *copy, &*packet) };
+        error::convert_ff_result(result).unwrap();
+        output
+            .write_packet(&mut packet, video_time_base, 1)
+            .unwrap();
+        output.write_packet(&mut copy, video_time_base, 2).unwrap();
+    }
+    output.write_trailer().unwrap();
+}
+
+/// Create a WAV file holding `num_samples` of silence in 16-bit mono.
+pub(crate) fn create_silent_wav(sample_rate: u32, num_samples: u32) -> Vec<u8> {
+    let data_len = num_samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.resize(44 + data_len as usize, 0);
+    wav
+}
+
+/// Write a Matroska file with two audio tracks in different formats, two
+/// seconds of the 48kHz stereo test audio and two seconds of 22.05kHz mono
+/// silence.
+pub(crate) fn create_mkv_with_two_audio_tracks(path: &Path) {
+    let mut first = InputSource::open_file("../media/test.mp4").unwrap();
+    let mut second =
+        InputSource::open_from_memory(create_silent_wav(22_050, 44_100)).unwrap();
+    let mut output = OutputContext::new(path).unwrap();
+    let first_audio = unsafe { &*first.streams()[1] };
+    let second_audio = unsafe { &*second.streams()[0] };
+    let first_time_base = first_audio.time_base;
+    let second_time_base = second_audio.time_base;
+    output.add_stream(first_audio).unwrap();
+    output.add_stream(second_audio).unwrap();
+    output.write_header().unwrap();
+
+    // The muxer interleaves the tracks, buffering the first until the second
+    // is written.
+    let end_pts = 2 * first_time_base.den as i64 / first_time_base.num as i64;
+    let mut packet = MediaPacket::new().unwrap();
+    loop {
+        packet.reset();
+        first.read_packet(&mut packet).unwrap();
+        if packet.stream_index != 1 {
+            continue;
+        }
+        if packet.pts >= end_pts {
+            break;
+        }
+        output
+            .write_packet(&mut packet, first_time_base, 0)
+            .unwrap();
+    }
+    loop {
+        packet.reset();
+        match second.read_packet(&mut packet) {
+            Ok(()) => {},
+            Err(err) if err.is_eof() => break,
+            Err(err) => panic!("failed to read WAV packet: {err}"),
+        }
+        output
+            .write_packet(&mut packet, second_time_base, 1)
+            .unwrap();
+    }
+    output.write_trailer().unwrap();
+}
@@ -0,0 +1,365 @@
+use libav_player::{
+    AudioFormat,
+    ChannelLayout,
+    DecodedFrame,
+    Frame,
+    InputSource,
+    MediaPlayerBuilder,
+    MediaType,
+    PlayerError,
+    SampleFormat,
+};
+
+#[test]
+fn test_audio_decode() -> anyhow::Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let mut audio_frame_count = 0;
+    let mut audible_frames = 0;
+    let mut last_pts = None;
+    loop {
+        let frame = match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Audio(mut audio) = frame {
+            audio_frame_count += 1;
+            assert!(
+                audio.num_samples() > 0,
+                "audio frame should contain samples"
+            );
+            assert_eq!(audio.num_channels(), 2, "test media is stereo AAC");
+            assert_eq!(audio.channel_layout(), ChannelLayout::Stereo);
+            assert_eq!(audio.sample_rate(), 48_000);
+
+            let samples = audio.to_interleaved_f32();
+            assert_eq!(samples.len(), audio.num_samples() * audio.num_channels());
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+
+            let peak = samples
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            assert_eq!(audio.peak(), peak);
+            assert!(audio.rms() <= audio.peak());
+            if audio.rms() > 0.0 {
+                audible_frames += 1;
+            }
+
+            if let Some(last_pts) = last_pts {
+                assert!(audio.pts() >= last_pts, "audio frames should be ordered");
+            }
+            last_pts = Some(audio.pts());
+        }
+    }
+
+    assert!(audio_frame_count > 0, "no audio frames were decoded");
+    assert!(last_pts.unwrap() > std::time::Duration::ZERO);
+    assert!(audible_frames > 0, "expected some non-silent audio");
+
+    Ok(())
+}
+
+#[test]
+fn test_audio_resample() -> anyhow::Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_audio_format(SampleFormat::FLT, 44_100, 1)
+        .build()?;
+
+    let format = player.audio_format().expect("audio stream should exist");
+    assert_eq!(
+        format,
+        AudioFormat {
+            sample_format: SampleFormat::FLT,
+            sample_rate: 44_100,
+            channels: 1,
+        }
+    );
+
+    player.play()?;
+
+    let mut audio_frame_count = 0;
+    loop {
+        let frame = match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Audio(audio) = frame {
+            audio_frame_count += 1;
+            assert!(audio.num_samples() > 0);
+            assert_eq!(audio.num_channels(), 1);
+            assert_eq!(audio.channel_layout(), ChannelLayout::Mono);
+            assert_eq!(audio.sample_rate(), 44_100);
+            assert_eq!(audio.sample_format(), SampleFormat::FLT);
+            assert_eq!(audio.num_planes(), 1);
+        }
+    }
+
+    assert!(audio_frame_count > 0, "no audio frames were decoded");
+
+    Ok(())
+}
+
+#[test]
+fn test_audio_resample_drains_tail() -> anyhow::Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    // Exactly one second of audio, so the output should be one second at the new rate.
+    let source = InputSource::open_from_memory(create_wav(2, 48_000, 48_000))?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_audio_format(SampleFormat::FLT, 44_100, 2)
+        .build()?;
+
+    player.play()?;
+
+    let mut total_samples = 0;
+    loop {
+        match player.process_next_frame() {
+            Ok(DecodedFrame::Audio(audio)) => total_samples += audio.num_samples(),
+            Ok(_) => {},
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        }
+    }
+
+    // Without draining the resampler, the samples held back for filtering are lost.
+    assert!(
+        total_samples.abs_diff(44_100) <= 2,
+        "expected around 44100 samples, got {total_samples}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_loudness_analysis() -> anyhow::Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_loudness_analysis()
+        .build()?;
+    assert_eq!(player.loudness(), None, "no audio has been analysed yet");
+
+    player.play()?;
+
+    let mut sample_peak = 0.0f32;
+    loop {
+        let frame = match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Audio(audio) = frame {
+            sample_peak = sample_peak.max(audio.peak());
+        }
+    }
+
+    let loudness = player.loudness().expect("loudness should be measured");
+    assert!(loudness.integrated_lufs.is_finite());
+    assert!(loudness.integrated_lufs < 0.0);
+    assert!(loudness.loudness_range >= 0.0);
+    // The true peak accounts for inter-sample peaks, so is never below the sample peak.
+    let sample_peak_db = 20.0 * (sample_peak as f64).log10();
+    assert!(
+        loudness.true_peak >= sample_peak_db - 0.1,
+        "true peak {} should not be below the sample peak {sample_peak_db}",
+        loudness.true_peak,
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_loudness_analysis_disabled() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    player.play()?;
+    for _ in 0..10 {
+        player.process_next_frame()?;
+    }
+
+    assert_eq!(player.loudness(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_switch_audio_stream_single_stream() -> anyhow::Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let video_index = source
+        .find_best_stream(MediaType::Video, None)?
+        .expect("video stream should exist")
+        .index;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    let audio_streams = player.available_audio_streams().to_vec();
+    assert_eq!(
+        audio_streams.len(),
+        1,
+        "test media has a single audio stream"
+    );
+    assert!(
+        audio_streams
+            .iter()
+            .all(|stream| stream.media_type == MediaType::Audio)
+    );
+
+    let err = player.switch_audio_stream(video_index).unwrap_err();
+    assert!(matches!(err, PlayerError::InvalidStream { .. }));
+
+    player.switch_audio_stream(audio_streams[0].index)?;
+    player.play()?;
+
+    let mut audio_frame_count = 0;
+    loop {
+        match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(DecodedFrame::Audio(_)) => audio_frame_count += 1,
+            Ok(_) => {},
+        }
+    }
+    assert!(audio_frame_count > 0, "no audio frames were decoded");
+
+    Ok(())
+}
+
+#[test]
+fn test_switch_audio_stream_invalid_keeps_current() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let video_index = source
+        .find_best_stream(MediaType::Video, None)?
+        .expect("video stream should exist")
+        .index;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    let format = player.audio_format();
+    assert!(format.is_some());
+
+    for index in [video_index, 99] {
+        assert!(matches!(
+            player.switch_audio_stream(index),
+            Err(PlayerError::InvalidStream {
+                expected: MediaType::Audio,
+                ..
+            })
+        ));
+        assert_eq!(player.audio_format(), format);
+    }
+
+    player.play()?;
+    let mut audio_frame_count = 0;
+    for _ in 0..100 {
+        if let DecodedFrame::Audio(_) = player.process_next_frame()? {
+            audio_frame_count += 1;
+        }
+    }
+    assert!(audio_frame_count > 0, "audio should keep playing");
+
+    Ok(())
+}
+
+/// Creates a WAV file containing a sine wave with the given number of 16-bit channels.
+fn create_wav(channels: u16, sample_rate: u32, num_samples: u32) -> Vec<u8> {
+    let block_align = channels * 2;
+    let data_len = num_samples * block_align as u32;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+
+    for n in 0..num_samples {
+        let t = n as f32 / sample_rate as f32;
+        let sample = ((t * 440.0 * std::f32::consts::TAU).sin() * 8_000.0) as i16;
+        for _ in 0..channels {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+
+    wav
+}
+
+#[test]
+fn test_audio_downmix() -> anyhow::Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let source = InputSource::open_from_memory(create_wav(6, 48_000, 48_000))?;
+    let stream = source
+        .find_best_stream(MediaType::Audio, None)?
+        .expect("audio stream should exist");
+    assert_eq!(stream.channel_layout.unwrap().num_channels(), 6);
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_audio_downmix(ChannelLayout::Stereo)
+        .build()?;
+    assert_eq!(player.audio_format().unwrap().channels, 2);
+
+    player.play()?;
+
+    let mut audio_frame_count = 0;
+    loop {
+        let frame = match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Audio(audio) = frame {
+            audio_frame_count += 1;
+            assert_eq!(audio.num_channels(), 2);
+            assert_eq!(audio.channel_layout(), ChannelLayout::Stereo);
+            assert_eq!(audio.sample_rate(), 48_000);
+        }
+    }
+
+    assert!(audio_frame_count > 0, "no audio frames were decoded");
+
+    Ok(())
+}
+
+#[test]
+fn test_audio_downmix_noop() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let player = MediaPlayerBuilder::for_source(source)
+        .with_audio_downmix(ChannelLayout::Stereo)
+        .build()?;
+
+    let format = player.audio_format().expect("audio stream should exist");
+    assert_eq!(format.channels, 2);
+    assert_eq!(format.sample_format, SampleFormat::FLTP);
+
+    Ok(())
+}
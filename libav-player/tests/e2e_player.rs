@@ -1,11 +1,26 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use libav_player::{
+    AcceleratorConfig,
+    DecodeLimit,
     DecodedFrame,
     Frame,
     InputSource,
+    LoopMode,
+    MediaPlayer,
     MediaPlayerBuilder,
     MediaType,
+    OnExceed,
     OutputPixelFormat,
+    PacketInfo,
     PlayerError,
+    ScalingMode,
+    SeekMode,
+    ThreadCount,
 };
 
 #[test]
@@ -14,6 +29,7 @@ fn test_video_decode() -> anyhow::Result<()> {
         rusty_ffmpeg::ffi::av_log_set_level(rusty_ffmpeg::ffi::AV_LOG_VERBOSE as i32);
     };
     let _ = tracing_subscriber::fmt::try_init();
+    libav_player::init_logging();
 
     let source = InputSource::open_file("../media/test.mp4")?;
 
@@ -42,3 +58,1500 @@ fn test_video_decode() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_video_decode_frame_count() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let stream = source.find_best_stream(MediaType::Video, None)?.unwrap();
+    assert!(stream.num_frames > 0);
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let mut video_frames = 0;
+    loop {
+        match player.process_next_frame() {
+            Ok(DecodedFrame::Video(_)) => video_frames += 1,
+            Ok(_) => {},
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        }
+    }
+
+    // The frames still buffered in the decoder at the end of the stream
+    // must be drained rather than lost.
+    assert_eq!(video_frames, stream.num_frames);
+    // Polling again after the end of the stream must not decode anything more.
+    assert!(matches!(
+        player.process_next_frame(),
+        Err(PlayerError::EndOfStream)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_video_decode_drains_buffered_frames() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let video_index = source
+        .find_best_stream(MediaType::Video, None)?
+        .unwrap()
+        .index;
+
+    let video_packets = Arc::new(Mutex::new(0));
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .on_packet(Box::new({
+            let video_packets = video_packets.clone();
+            move |info: PacketInfo| {
+                if info.stream_index == video_index {
+                    *video_packets.lock().unwrap() += 1;
+                }
+            }
+        }))
+        .build()?;
+
+    player.play()?;
+
+    let mut video_frames = 0;
+    let mut frames_after_last_packet = 0;
+    let mut packets_seen = 0;
+    loop {
+        match player.process_next_frame() {
+            Ok(DecodedFrame::Video(_)) => {
+                video_frames += 1;
+                let packets = *video_packets.lock().unwrap();
+                if packets == packets_seen {
+                    frames_after_last_packet += 1;
+                } else {
+                    packets_seen = packets;
+                    frames_after_last_packet = 0;
+                }
+            },
+            Ok(_) => {},
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        }
+    }
+
+    // Every packet produces a frame, including those the decoder was still
+    // holding onto when the last packet was sent.
+    assert_eq!(video_frames, *video_packets.lock().unwrap());
+    assert!(
+        frames_after_last_packet > 0,
+        "the decoder should buffer frames which are only returned once drained",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_video_decoder_info_software() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut accelerator_config = AcceleratorConfig::default();
+    accelerator_config.set_accelerators(&[]);
+
+    let player = MediaPlayerBuilder::for_source(source)
+        .with_accelerator_config(accelerator_config)
+        .build()?;
+
+    let info = player
+        .video_decoder_info()
+        .expect("video stream should exist");
+    assert!(!info.is_hardware);
+    assert_eq!(info.accelerator, None);
+    assert_eq!(info.hw_pix_fmt, None);
+    assert!(info.failed_accelerators.is_empty());
+    assert!(!info.is_software_fallback());
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_threads() -> anyhow::Result<()> {
+    let mut accelerator_config = AcceleratorConfig::default();
+    accelerator_config.set_accelerators(&[]);
+
+    // The thread count must not change the frames produced.
+    let mut timestamps = Vec::new();
+    for threads in [
+        ThreadCount::Count(1),
+        ThreadCount::Count(4),
+        ThreadCount::Auto,
+    ] {
+        let source = InputSource::open_file("../media/test.mp4")?;
+        let mut player = MediaPlayerBuilder::for_source(source)
+            .with_accelerator_config(accelerator_config.clone())
+            .with_decode_threads(threads)
+            .with_video_stream(Some(0))
+            .build()?;
+        player.play()?;
+
+        let mut pts = Vec::new();
+        while pts.len() < 30 {
+            if let DecodedFrame::Video(frame) = player.process_next_frame()? {
+                pts.push(frame.pts());
+            }
+        }
+        timestamps.push(pts);
+    }
+
+    assert_eq!(timestamps[0], timestamps[1]);
+    assert_eq!(timestamps[0], timestamps[2]);
+
+    Ok(())
+}
+
+#[test]
+fn test_on_decoder_selected() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let selected = Arc::new(Mutex::new(None));
+    let player = MediaPlayerBuilder::for_source(source)
+        .on_decoder_selected(Box::new({
+            let selected = selected.clone();
+            move |info| *selected.lock().unwrap() = Some(info)
+        }))
+        .build()?;
+
+    let info = selected
+        .lock()
+        .unwrap()
+        .take()
+        .expect("callback should be called once the decoder is opened");
+    let expected = player.video_decoder_info().unwrap();
+    assert_eq!(info.accelerator, expected.accelerator);
+    assert_eq!(info.failed_accelerators, expected.failed_accelerators);
+    if let Some(accelerator) = info.accelerator {
+        assert!(!info.failed_accelerators.contains(&accelerator));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_decoder_codec_accelerator_override() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut accelerator_config = AcceleratorConfig::default();
+    accelerator_config.set_accelerators_for_codec("H264", &[]);
+    assert!(accelerator_config.accelerators_for_codec("h264").is_empty());
+    assert_eq!(
+        accelerator_config.accelerators_for_codec("hevc"),
+        accelerator_config.accelerators(),
+    );
+
+    let player = MediaPlayerBuilder::for_source(source)
+        .with_accelerator_config(accelerator_config)
+        .build()?;
+
+    let info = player
+        .video_decoder_info()
+        .expect("video stream should exist");
+    assert!(!info.is_hardware);
+    assert_eq!(info.accelerator, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_video_output_pixel_format() -> anyhow::Result<()> {
+    let cases: &[&[OutputPixelFormat]] = &[
+        &[OutputPixelFormat::Rgba],
+        &[OutputPixelFormat::Nv12],
+        &[OutputPixelFormat::Rgba, OutputPixelFormat::Nv12],
+        &[OutputPixelFormat::Rgb24],
+        &[OutputPixelFormat::Bgra],
+        &[OutputPixelFormat::Bgra, OutputPixelFormat::Rgb24],
+    ];
+
+    for &formats in cases {
+        let source = InputSource::open_file("../media/test.mp4")?;
+
+        let mut player = MediaPlayerBuilder::for_source(source)
+            .with_target_pixel_formats(formats)
+            .build()?;
+
+        player.play()?;
+
+        let mut video_frames = 0;
+        while video_frames < 5 {
+            let frame = match player.process_next_frame() {
+                Err(PlayerError::EndOfStream) => break,
+                Err(other) => return Err(other.into()),
+                Ok(frame) => frame,
+            };
+
+            if let DecodedFrame::Video(video) = frame {
+                assert!(
+                    formats.contains(&video.pixel_format()),
+                    "frame pixel format {:?} is not one of {formats:?}",
+                    video.pixel_format(),
+                );
+                video_frames += 1;
+            }
+        }
+        assert_eq!(video_frames, 5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_target_resolution() -> anyhow::Result<()> {
+    let cases = [
+        (ScalingMode::Fit, (640, 360)),
+        (ScalingMode::Fill, (640, 640)),
+        (ScalingMode::Stretch, (640, 640)),
+    ];
+
+    for (mode, (width, height)) in cases {
+        let source = InputSource::open_file("../media/test.mp4")?;
+
+        let mut player = MediaPlayerBuilder::for_source(source)
+            .with_target_resolution(640, 640, mode)
+            .build()?;
+
+        let resolution = player
+            .output_resolution()
+            .expect("video stream should exist");
+        assert_eq!((resolution.width, resolution.height), (width, height));
+
+        player.play()?;
+
+        let video = loop {
+            match player.process_next_frame() {
+                Err(PlayerError::EndOfStream) => panic!("no video frames were decoded"),
+                Err(other) => return Err(other.into()),
+                Ok(DecodedFrame::Video(video)) => break video,
+                Ok(_) => continue,
+            }
+        };
+        assert_eq!((video.width(), video.height()), (width, height), "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_crop() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_crop(100, 60, 1280, 720)
+        .build()?;
+
+    let resolution = player
+        .output_resolution()
+        .expect("video stream should exist");
+    assert_eq!((resolution.width, resolution.height), (1280, 720));
+
+    player.play()?;
+
+    let video = loop {
+        match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => panic!("no video frames were decoded"),
+            Err(other) => return Err(other.into()),
+            Ok(DecodedFrame::Video(video)) => break video,
+            Ok(_) => continue,
+        }
+    };
+    assert_eq!((video.width(), video.height()), (1280, 720));
+
+    Ok(())
+}
+
+#[test]
+fn test_video_crop_out_of_bounds() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let result = MediaPlayerBuilder::for_source(source)
+        .with_crop(1000, 0, 1280, 720)
+        .build();
+    match result {
+        Err(PlayerError::InvalidCrop { x, width, .. }) => {
+            assert_eq!((x, width), (1000, 1280));
+        },
+        Err(other) => panic!("expected invalid crop error, got {other:?}"),
+        Ok(_) => panic!("expected invalid crop error"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_crop_overflow() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    // The end of the region overflows rather than being out of bounds.
+    let result = MediaPlayerBuilder::for_source(source)
+        .with_crop(usize::MAX, 0, 1280, 720)
+        .build();
+    match result {
+        Err(PlayerError::InvalidCrop { x, width, .. }) => {
+            assert_eq!((x, width), (usize::MAX, 1280));
+        },
+        Err(other) => panic!("expected invalid crop error, got {other:?}"),
+        Ok(_) => panic!("expected invalid crop error"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_max_resolution() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let result = MediaPlayerBuilder::for_source(source)
+        .with_max_resolution(1280, 1280, OnExceed::Error)
+        .build();
+    match result {
+        Err(PlayerError::ResolutionTooLarge { resolution, max }) => {
+            assert_eq!((resolution.width, resolution.height), (1920, 1080));
+            assert_eq!((max.width, max.height), (1280, 1280));
+        },
+        Err(other) => panic!("expected resolution too large error, got {other:?}"),
+        Ok(_) => panic!("expected resolution too large error"),
+    }
+
+    // The limit applies to the scaled output, not the source.
+    let source = InputSource::open_file("../media/test.mp4")?;
+    MediaPlayerBuilder::for_source(source)
+        .with_target_resolution(640, 360, ScalingMode::Fit)
+        .with_max_resolution(1280, 1280, OnExceed::Error)
+        .build()?;
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_max_resolution(1280, 1280, OnExceed::Downscale)
+        .build()?;
+    let resolution = player
+        .output_resolution()
+        .expect("video stream should exist");
+    assert_eq!((resolution.width, resolution.height), (1280, 720));
+
+    player.play()?;
+    let video = player.frame_at(Duration::ZERO)?;
+    assert_eq!((video.width(), video.height()), (1280, 720));
+
+    Ok(())
+}
+
+#[test]
+fn test_video_tightly_packed_copy() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_pixel_formats(vec![OutputPixelFormat::Nv12])
+        .build()?;
+
+    player.play()?;
+
+    let mut buffer = Vec::new();
+    loop {
+        let frame = match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Video(mut video) = frame {
+            video.copy_to_tightly_packed(&mut buffer)?;
+
+            let luma_size = video.width() * video.height();
+            let chroma_size = video.plane_width(1) * 2 * video.plane_height(1);
+            assert_eq!(buffer.len(), luma_size + chroma_size);
+            return Ok(());
+        }
+    }
+
+    panic!("no video frames were decoded");
+}
+
+#[test]
+fn test_video_yuv420p_planes() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_pixel_formats(vec![OutputPixelFormat::Yuv420p])
+        .build()?;
+
+    player.play()?;
+
+    let mut video = player.frame_at(Duration::ZERO)?;
+    assert_eq!(video.pixel_format(), OutputPixelFormat::Yuv420p);
+    assert_eq!(video.num_planes(), 3);
+
+    assert_eq!(video.plane_width(0), 1920);
+    assert_eq!(video.plane_height(0), 1080);
+    for index in [1, 2] {
+        assert_eq!(video.plane_width(index), 960);
+        assert_eq!(video.plane_height(index), 540);
+        let stride = video.stride(index);
+        assert!(stride >= 960);
+
+        let data = video.plane_data(index)?;
+        assert_eq!(data.len(), stride * 540);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_frame_moved_to_thread() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_pixel_formats(vec![OutputPixelFormat::Rgba])
+        .build()?;
+
+    player.play()?;
+
+    let video = player.frame_at(Duration::ZERO)?;
+    let expected_size = video.width() * video.height() * 4;
+
+    let render_thread = std::thread::spawn(move || {
+        let mut video = video;
+        let mut buffer = Vec::new();
+        video.copy_to_tightly_packed(&mut buffer)?;
+        Ok::<_, PlayerError>(buffer.len())
+    });
+
+    let size = render_thread.join().expect("render thread panicked")?;
+    assert_eq!(size, expected_size);
+
+    // The player keeps decoding after the frame has been released on another thread.
+    player.frame_at(Duration::from_secs(1))?;
+
+    Ok(())
+}
+
+/// A reader which stalls once, timing out the first read at or past `stall_at` bytes.
+///
+/// Reads are cut short at `stall_at`, so the stall lands exactly on it rather than
+/// part way through a read.
+struct StallingReader {
+    inner: File,
+    stall_at: u64,
+    has_stalled: bool,
+}
+
+impl Read for StallingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.has_stalled {
+            return self.inner.read(buf);
+        }
+
+        let position = self.inner.stream_position()?;
+        if position >= self.stall_at {
+            self.has_stalled = true;
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        let len = buf.len().min((self.stall_at - position) as usize);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl Seek for StallingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// A reader which is unable to seek, like a pipe.
+struct NonSeekableReader {
+    inner: File,
+}
+
+impl Read for NonSeekableReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for NonSeekableReader {
+    fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[test]
+fn test_seek_not_seekable() -> anyhow::Result<()> {
+    // The SRT demuxer reads the file linearly, so it can be opened without seeking.
+    let reader = NonSeekableReader {
+        inner: File::open("../media/subtitle-text.srt")?,
+    };
+    let source = InputSource::open_reader(reader)?;
+    assert!(!source.is_seekable());
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    assert!(!player.is_seekable());
+
+    player.play()?;
+
+    let result = player.seek(Duration::from_secs(1));
+    assert!(matches!(result, Err(PlayerError::NotSeekable)));
+    let result = player.seek_bytes(0);
+    assert!(matches!(result, Err(PlayerError::NotSeekable)));
+
+    // A failed seek must leave the player usable.
+    assert!(player.process_next_frame().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_probe_not_seekable() -> anyhow::Result<()> {
+    let reader = NonSeekableReader {
+        inner: File::open("../media/subtitle-text.srt")?,
+    };
+    let mut source = InputSource::open_reader(reader)?;
+
+    // Probing could not rewind the source, so nothing is read.
+    assert_eq!(source.estimate_gop(0, 4), None);
+    let result = source.build_keyframe_index(0);
+    assert!(matches!(result, Err(PlayerError::NotSeekable)));
+
+    let mut expected = InputSource::open_file("../media/subtitle-text.srt")?;
+    let packet = source.read_raw_packet()?.expect("packets remain");
+    let expected_packet = expected.read_raw_packet()?.expect("packets remain");
+    assert_eq!(packet.pts(), expected_packet.pts());
+
+    Ok(())
+}
+
+#[test]
+fn test_read_timeout_is_recoverable() -> anyhow::Result<()> {
+    let reader = StallingReader {
+        inner: File::open("../media/test.mp4")?,
+        stall_at: 10 * 1024 * 1024,
+        has_stalled: false,
+    };
+    let source = InputSource::open_reader(reader)?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let mut timeouts = 0;
+    let mut frames_after_timeout = 0;
+    loop {
+        match player.process_next_frame() {
+            Err(PlayerError::NetworkTimeout(_)) => timeouts += 1,
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(_) if timeouts > 0 => frames_after_timeout += 1,
+            Ok(_) => {},
+        }
+    }
+
+    assert_eq!(timeouts, 1);
+    assert!(
+        frames_after_timeout > 0,
+        "playback should resume after the timeout"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_read_timeout_is_recoverable_linear() -> anyhow::Result<()> {
+    // MPEG-TS is read linearly, so the retry does not seek past the failed read.
+    let dir = tempfile::tempdir()?;
+    let path = remux_test_media_to_ts(dir.path())?;
+    let reader = StallingReader {
+        inner: File::open(&path)?,
+        // On a packet boundary well past the data read while probing.
+        stall_at: 188 * 60_000,
+        has_stalled: false,
+    };
+    let source = InputSource::open_reader(reader)?;
+
+    // The packet cut short by the timeout may be corrupt.
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .skip_corrupt(true)
+        .build()?;
+
+    player.play()?;
+
+    let mut timeouts = 0;
+    let mut frames_after_timeout = 0;
+    loop {
+        match player.process_next_frame() {
+            Err(PlayerError::NetworkTimeout(_)) => {
+                timeouts += 1;
+                assert_eq!(timeouts, 1, "the retry should read from the source again");
+            },
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(_) if timeouts > 0 => frames_after_timeout += 1,
+            Ok(_) => {},
+        }
+    }
+
+    assert_eq!(timeouts, 1);
+    assert!(
+        frames_after_timeout > 0,
+        "playback should resume after the timeout"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_on_packet() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let video_index = source
+        .find_best_stream(MediaType::Video, None)?
+        .unwrap()
+        .index;
+
+    let packets = Arc::new(Mutex::new(Vec::new()));
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .on_packet(Box::new({
+            let packets = packets.clone();
+            move |info: PacketInfo| packets.lock().unwrap().push(info)
+        }))
+        .build()?;
+
+    player.play()?;
+
+    let mut frame_count = 0;
+    loop {
+        match player.process_next_frame() {
+            Ok(DecodedFrame::Video(_)) => frame_count += 1,
+            Ok(_) => {},
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        }
+    }
+
+    let packets = packets.lock().unwrap();
+    let video_packets: Vec<_> = packets
+        .iter()
+        .filter(|info| info.stream_index == video_index)
+        .collect();
+    assert!(video_packets.len() >= frame_count);
+    assert!(video_packets[0].is_keyframe);
+    assert!(video_packets.iter().all(|info| info.size > 0));
+
+    // The decode order of the video packets must never go backwards.
+    let dts: Vec<_> = video_packets.iter().filter_map(|info| info.dts).collect();
+    assert!(dts.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_numbers() -> anyhow::Result<()> {
+    fn collect_video_frames(
+        player: &mut MediaPlayer,
+        count: usize,
+    ) -> anyhow::Result<Vec<(u64, Option<u64>)>> {
+        let mut frames = Vec::new();
+        while frames.len() < count {
+            if let DecodedFrame::Video(video) = player.process_next_frame()? {
+                frames.push((video.display_index(), video.coded_picture_number()));
+            }
+        }
+        Ok(frames)
+    }
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let frames = collect_video_frames(&mut player, 30)?;
+    let display_indices: Vec<_> = frames.iter().map(|(index, _)| *index).collect();
+    assert_eq!(display_indices, (0..30).collect::<Vec<_>>());
+
+    let mut coded_numbers: Vec<_> = frames
+        .iter()
+        .map(|(_, number)| number.expect("frames should have a packet number"))
+        .collect();
+    coded_numbers.sort();
+    coded_numbers.dedup();
+    assert_eq!(coded_numbers.len(), 30, "packet numbers must be unique");
+
+    player.seek(Duration::from_secs(5))?;
+
+    let frames = collect_video_frames(&mut player, 5)?;
+    assert_eq!(frames[0], (0, Some(0)), "numbering restarts after a seek");
+    assert_eq!(frames[4].0, 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_exact_seek() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let target = Duration::from_secs(5);
+    player.seek_with(target, SeekMode::Exact)?;
+
+    loop {
+        let frame = match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Video(video) = frame {
+            assert!(video.pts() >= target, "frame before seek target returned");
+            assert!(
+                video.pts() - target < Duration::from_millis(40),
+                "first frame should be the frame at the target position",
+            );
+            return Ok(());
+        }
+    }
+
+    panic!("no video frames were decoded");
+}
+
+#[test]
+fn test_seek_backward_flushes_decoders() -> anyhow::Result<()> {
+    let mut source = InputSource::open_file("../media/test.mp4")?;
+    let target = Duration::from_secs(2);
+    let keyframe = *source
+        .build_keyframe_index(0)?
+        .find(target)
+        .expect("index has keyframes");
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    while next_video_pts(&mut player)? < Duration::from_secs(8) {}
+    player.seek(target)?;
+    // The frame comes from the keyframe before the target, not the old position.
+    let pts = next_video_pts(&mut player)?;
+    assert!(
+        pts >= keyframe.pts && pts < target + Duration::from_millis(40),
+        "expected a frame from the GOP before the target, got {pts:?}",
+    );
+
+    while next_video_pts(&mut player)? < Duration::from_secs(8) {}
+    player.seek_with(target, SeekMode::Exact)?;
+    let pts = next_video_pts(&mut player)?;
+    assert!(
+        pts >= target && pts < target + Duration::from_millis(40),
+        "first frame after an exact seek should be at the target, got {pts:?}",
+    );
+
+    Ok(())
+}
+
+/// Remux the test media to an MPEG-TS file in `dir`, which unlike MP4 supports
+/// seeking by byte offset.
+fn remux_test_media_to_ts(dir: &Path) -> anyhow::Result<PathBuf> {
+    let path = dir.join("test.ts");
+    let mut source = InputSource::open_file("../media/test.mp4")?;
+    source.remux_to(&path, &[0, 1])?;
+    Ok(path)
+}
+
+fn next_video_pts(player: &mut MediaPlayer) -> anyhow::Result<Duration> {
+    loop {
+        if let DecodedFrame::Video(video) = player.process_next_frame()? {
+            return Ok(video.pts());
+        }
+    }
+}
+
+#[test]
+fn test_seek_bytes() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = remux_test_media_to_ts(dir.path())?;
+    let file_size = std::fs::metadata(&path)?.len() as i64;
+    let source = InputSource::open_file(&path)?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    player.play()?;
+    let first_pts = next_video_pts(&mut player)?;
+
+    // Half way through the file is several seconds into the video.
+    player.seek_bytes(file_size / 2)?;
+    let pts = next_video_pts(&mut player)?;
+    assert!(
+        pts > first_pts + Duration::from_secs(3),
+        "expected a frame from the middle of the video, got {pts:?}",
+    );
+
+    player.seek_bytes(0)?;
+    assert_eq!(next_video_pts(&mut player)?, first_pts);
+
+    Ok(())
+}
+
+#[test]
+fn test_frames_without_pts() -> anyhow::Result<()> {
+    // A raw H.264 stream has no container timestamps, so the decoded frames
+    // have no PTS and fall back to the best effort timestamp.
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("test.h264");
+    let mut source = InputSource::open_file("../media/test.mp4")?;
+    source.remux_to(&path, &[0])?;
+
+    let source = InputSource::open_file(&path)?;
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    player.play()?;
+
+    let mut last_pts = next_video_pts(&mut player)?;
+    for _ in 0..50 {
+        let pts = next_video_pts(&mut player)?;
+        assert!(pts > last_pts, "expected {pts:?} to be after {last_pts:?}");
+        last_pts = pts;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_seek_bytes_unsupported() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+    player.process_next_frame()?;
+
+    // MP4 does not support byte seeking, so the player should be left untouched.
+    assert!(player.seek_bytes(1024).is_err());
+    player.process_next_frame()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_reset_stats_on_seek() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_reset_stats_on_seek(true)
+        .build()?;
+
+    player.play()?;
+
+    for _ in 0..10 {
+        player.process_next_frame()?;
+    }
+    assert_eq!(player.statistics().frames_decoded_total, 10);
+
+    player.seek(Duration::from_secs(2))?;
+    assert_eq!(player.statistics().frames_decoded_total, 0);
+    assert_eq!(player.statistics().num_video_frames_decoded, 0);
+
+    player.process_next_frame()?;
+    assert_eq!(player.statistics().frames_decoded_total, 1);
+
+    player.reset_statistics();
+    assert_eq!(player.statistics().frames_decoded_total, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_realtime_drops_late_frames() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let clock = Duration::from_secs(3);
+    loop {
+        let frame = match player.process_next_frame_realtime(clock) {
+            Err(PlayerError::EndOfStream) => panic!("no video frames were decoded"),
+            Err(other) => return Err(other.into()),
+            Ok(frame) => frame,
+        };
+
+        if let DecodedFrame::Video(video) = frame {
+            assert!(
+                video.pts() + Duration::from_millis(100) >= clock,
+                "late frame should have been dropped, got {:?}",
+                video.pts(),
+            );
+            break;
+        }
+    }
+
+    assert!(player.statistics().dropped_frames > 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_prefetch_queue() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_prefetch(4)
+        .build()?;
+
+    player.play()?;
+
+    assert!(player.try_next_frame().is_none());
+
+    player.process_next_frame()?;
+    assert_eq!(player.num_prefetched_frames(), 3);
+    for _ in 0..3 {
+        assert!(player.try_next_frame().is_some());
+    }
+    assert!(player.try_next_frame().is_none());
+
+    player.process_next_frame()?;
+    assert_eq!(player.num_prefetched_frames(), 3);
+    player.seek(Duration::from_secs(10))?;
+    assert_eq!(player.num_prefetched_frames(), 0);
+
+    loop {
+        match player.process_next_frame() {
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+            Ok(_) => continue,
+        }
+    }
+    assert_eq!(player.num_prefetched_frames(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_player_handle() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let player = MediaPlayerBuilder::for_source(source).build()?;
+    let mut handle = player.spawn();
+    handle.play();
+
+    for _ in 0..10 {
+        handle.recv_frame()?;
+    }
+
+    let target = Duration::from_secs(8);
+    handle.seek_with(target, SeekMode::Exact);
+
+    loop {
+        if let DecodedFrame::Video(video) = handle.recv_frame()? {
+            assert!(
+                video.pts() >= target,
+                "frame from before the seek was received, got {:?}",
+                video.pts(),
+            );
+            break;
+        }
+    }
+
+    handle.pause();
+    drop(handle);
+
+    Ok(())
+}
+
+#[test]
+fn test_player_handle_pause() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let player = MediaPlayerBuilder::for_source(source).build()?;
+    let handle = player.spawn();
+    handle.play();
+    handle.recv_frame()?;
+
+    handle.pause();
+    // Frames decoded before the pause are still received, followed by the marker
+    // rather than blocking forever.
+    loop {
+        match handle.recv_frame() {
+            Err(PlayerError::Paused) => break,
+            Err(other) => panic!("expected paused error, got {other:?}"),
+            Ok(_) => continue,
+        }
+    }
+    assert!(handle.try_recv_frame().is_none());
+
+    handle.play();
+    handle.recv_frame()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_position_and_duration() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    assert_eq!(player.position(), Duration::ZERO);
+    assert!(player.duration() > Duration::from_secs(13));
+
+    player.play()?;
+
+    let mut last_video_pts = Duration::ZERO;
+    while last_video_pts < Duration::from_secs(1) {
+        if let DecodedFrame::Video(video) = player.process_next_frame()? {
+            last_video_pts = video.pts();
+            assert_eq!(player.position(), last_video_pts);
+        }
+    }
+
+    let target = Duration::from_secs(5);
+    player.seek(target)?;
+    assert_eq!(player.position(), target);
+
+    loop {
+        if let DecodedFrame::Video(video) = player.process_next_frame()? {
+            assert_eq!(player.position(), video.pts());
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_average_hash() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_pixel_formats(&[OutputPixelFormat::Nv12])
+        .build()?;
+    player.play()?;
+
+    // Decoding the same frame again produces the same hash.
+    let target = Duration::from_secs(3);
+    let hash = player.frame_at(target)?.average_hash()?;
+    assert_eq!(player.frame_at(target)?.average_hash()?, hash);
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_pixel_formats(&[OutputPixelFormat::Rgba])
+        .build()?;
+    player.play()?;
+
+    let mut video = player.frame_at(target)?;
+    assert_eq!(video.pixel_format(), OutputPixelFormat::Rgba);
+    assert!(video.average_hash().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_at() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    for target in [Duration::from_secs(3), Duration::from_millis(1_010)] {
+        let video = player.frame_at(target)?;
+        assert!(
+            video.pts() >= target && video.pts() < target + Duration::from_millis(40),
+            "expected the frame at {target:?}, got {:?}",
+            video.pts(),
+        );
+    }
+
+    let past_end = player.duration() + Duration::from_secs(1);
+    assert!(matches!(
+        player.frame_at(past_end),
+        Err(PlayerError::EndOfStream)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_frames() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_target_resolution(320, 180, ScalingMode::Fit)
+        .build()?;
+
+    player.play()?;
+
+    let positions = [
+        Duration::from_secs(10),
+        Duration::from_secs(2),
+        Duration::from_millis(2_500),
+        Duration::from_secs(2),
+        Duration::from_secs(6),
+    ];
+    let frames = player.extract_frames(&positions)?;
+    assert_eq!(frames.len(), positions.len());
+
+    for (frame, target) in frames.iter().zip(positions) {
+        assert!(
+            frame.pts() >= target && frame.pts() < target + Duration::from_millis(40),
+            "expected the frame at {target:?}, got {:?}",
+            frame.pts(),
+        );
+        assert_eq!(frame.width(), 320);
+        assert_eq!(frame.height(), 180);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_loop_count() -> anyhow::Result<()> {
+    fn count_video_frames(mode: LoopMode) -> anyhow::Result<(usize, Duration)> {
+        let source = InputSource::open_file("../media/test.mp4")?;
+
+        let mut player = MediaPlayerBuilder::for_source(source)
+            .with_loop(mode)
+            .build()?;
+
+        player.play()?;
+
+        let mut frame_count = 0;
+        let mut last_video_pts = Duration::ZERO;
+        loop {
+            let frame = match player.process_next_frame() {
+                Err(PlayerError::EndOfStream) => break,
+                Err(other) => return Err(other.into()),
+                Ok(frame) => frame,
+            };
+
+            if let DecodedFrame::Video(video) = frame {
+                if frame_count > 0 {
+                    assert!(video.pts() > last_video_pts, "pts must be monotonic");
+                }
+                last_video_pts = video.pts();
+                frame_count += 1;
+            }
+        }
+
+        Ok((frame_count, last_video_pts))
+    }
+
+    let (single_count, single_end) = count_video_frames(LoopMode::Off)?;
+    let (looped_count, looped_end) = count_video_frames(LoopMode::Count(2))?;
+
+    assert!(single_count > 0);
+    assert!(
+        looped_count.abs_diff(single_count * 2) <= 2,
+        "expected roughly {} frames, got {looped_count}",
+        single_count * 2,
+    );
+    assert!(looped_end > single_end * 2 - Duration::from_secs(1));
+
+    Ok(())
+}
+
+#[test]
+fn test_with_program_without_programs() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+    assert!(source.programs().is_empty());
+
+    // The container has no programs, so this is a no-op.
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_program(1)
+        .build()?;
+
+    player.play()?;
+    player.process_next_frame()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_pause() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .with_prefetch(2)
+        .build()?;
+
+    player.play()?;
+    player.process_next_frame()?;
+    let position = player.position();
+
+    player.pause()?;
+    assert!(player.is_paused());
+    assert!(matches!(
+        player.process_next_frame(),
+        Err(PlayerError::Paused)
+    ));
+    assert!(player.try_next_frame().is_none());
+    assert_eq!(player.position(), position);
+
+    player.play()?;
+    assert!(!player.is_paused());
+    assert!(player.try_next_frame().is_some());
+    player.process_next_frame()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_at_while_paused() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+    player.pause()?;
+
+    let target = Duration::from_secs(5);
+    let video = player.frame_at(target)?;
+    assert!(video.pts() >= target);
+
+    let positions = [Duration::from_secs(2), Duration::from_secs(8)];
+    let frames = player.extract_frames(&positions)?;
+    assert_eq!(frames.len(), 2);
+    assert!(frames[0].pts() >= positions[0]);
+    assert!(frames[1].pts() >= positions[1]);
+
+    // Extracting frames does not resume playback.
+    assert!(player.is_paused());
+    assert!(matches!(
+        player.process_next_frame(),
+        Err(PlayerError::Paused)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_limit() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    player.set_decode_limit(DecodeLimit::Frames(5));
+    for _ in 0..5 {
+        player.process_next_frame()?;
+    }
+    assert!(matches!(
+        player.process_next_frame(),
+        Err(PlayerError::EndOfStream)
+    ));
+
+    // Setting the limit again re-arms it.
+    player.set_decode_limit(DecodeLimit::Frames(2));
+    player.process_next_frame()?;
+    player.process_next_frame()?;
+    assert!(matches!(
+        player.process_next_frame(),
+        Err(PlayerError::EndOfStream)
+    ));
+
+    let limit = Duration::from_secs(1);
+    player.set_decode_limit(DecodeLimit::Duration(limit));
+    player.seek(Duration::from_secs(5))?;
+
+    let mut first_pts = None;
+    let mut frame_count = 0;
+    loop {
+        let frame = match player.process_next_frame() {
+            Ok(frame) => frame,
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        };
+
+        let first_pts = *first_pts.get_or_insert(frame.pts());
+        assert!(frame.pts() < first_pts + limit);
+        frame_count += 1;
+    }
+    assert!(frame_count > 0);
+    assert!(player.position() < Duration::from_secs(7));
+
+    player.set_decode_limit(DecodeLimit::None);
+    player.process_next_frame()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_seek_to_keyframe() -> anyhow::Result<()> {
+    let mut source = InputSource::open_file("../media/test.mp4")?;
+    let index = source.build_keyframe_index(0)?;
+    let keyframe = *index
+        .find(Duration::from_secs(5))
+        .expect("index has keyframes");
+    assert!(keyframe.pts <= Duration::from_secs(5));
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+    player.seek_to_keyframe(&index, Duration::from_secs(5))?;
+    assert_eq!(player.position(), keyframe.pts);
+
+    let frame = loop {
+        if let DecodedFrame::Video(video) = player.process_next_frame()? {
+            break video;
+        }
+    };
+    assert!(frame.is_keyframe());
+    assert_eq!(frame.pts(), keyframe.pts);
+
+    Ok(())
+}
+
+#[test]
+fn test_seek_to_keyframe_by_byte() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = remux_test_media_to_ts(dir.path())?;
+    let mut source = InputSource::open_file(&path)?;
+
+    let index = source.build_keyframe_index(0)?;
+    let keyframe = *index
+        .find(Duration::from_secs(5))
+        .expect("index has keyframes");
+    assert!(keyframe.byte_pos > 0);
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+    player.seek_to_keyframe(&index, Duration::from_secs(5))?;
+    assert_eq!(next_video_pts(&mut player)?, keyframe.pts);
+
+    Ok(())
+}
+
+#[test]
+fn test_seek_to_keyframe_wrong_stream() -> anyhow::Result<()> {
+    let mut source = InputSource::open_file("../media/test.mp4")?;
+    let index = source.build_keyframe_index(1)?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+    let result = player.seek_to_keyframe(&index, Duration::from_secs(5));
+    assert!(matches!(
+        result,
+        Err(PlayerError::InvalidStream {
+            index: 1,
+            expected: MediaType::Video,
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_restart() -> anyhow::Result<()> {
+    fn count_video_frames(player: &mut MediaPlayer) -> anyhow::Result<usize> {
+        let mut frame_count = 0;
+        loop {
+            match player.process_next_frame() {
+                Ok(DecodedFrame::Video(_)) => frame_count += 1,
+                Ok(_) => {},
+                Err(PlayerError::EndOfStream) => break,
+                Err(other) => return Err(other.into()),
+            }
+        }
+        Ok(frame_count)
+    }
+
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source).build()?;
+
+    player.play()?;
+
+    let first_count = count_video_frames(&mut player)?;
+    assert!(first_count > 0);
+
+    player.restart()?;
+    assert_eq!(player.position(), Duration::ZERO);
+
+    let frame = loop {
+        if let DecodedFrame::Video(video) = player.process_next_frame()? {
+            break video;
+        }
+    };
+    assert!(frame.pts() < Duration::from_millis(100));
+
+    let second_count = 1 + count_video_frames(&mut player)?;
+    assert_eq!(second_count, first_count);
+
+    Ok(())
+}
+
+#[test]
+fn test_skip_corrupt() -> anyhow::Result<()> {
+    // The index is at the end of the file, so only the frame data is damaged.
+    let mut data = std::fs::read("../media/test.mp4")?;
+    let end = data.len() * 9 / 10;
+    let mut state = 0x2545_f491_u32;
+    for offset in (1 << 20..end).step_by(256 << 10) {
+        for byte in &mut data[offset..(offset + 4096).min(end)] {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *byte = state as u8;
+        }
+    }
+
+    let source = InputSource::open_from_memory(data)?;
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .skip_corrupt(true)
+        .build()?;
+
+    player.play()?;
+
+    let mut frame_count = 0;
+    loop {
+        match player.process_next_frame() {
+            Ok(_) => frame_count += 1,
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        }
+    }
+    assert!(frame_count > 0);
+    assert!(player.statistics().corrupt_packets_skipped > 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_audio_only() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .audio_only()
+        .build()?;
+    assert!(player.audio_format().is_some());
+
+    player.play()?;
+    for _ in 0..100 {
+        let frame = player.process_next_frame()?;
+        assert!(matches!(frame, DecodedFrame::Audio(_)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_only() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let mut player = MediaPlayerBuilder::for_source(source)
+        .video_only()
+        .build()?;
+    assert!(player.audio_format().is_none());
+
+    player.play()?;
+    let mut frame_count = 0;
+    loop {
+        match player.process_next_frame() {
+            Ok(DecodedFrame::Video(_)) => frame_count += 1,
+            Ok(DecodedFrame::Audio(_)) => panic!("audio frame produced"),
+            Ok(DecodedFrame::Subtitle(_)) => panic!("subtitle frame produced"),
+            Err(PlayerError::EndOfStream) => break,
+            Err(other) => return Err(other.into()),
+        }
+    }
+    assert!(frame_count > 0);
+
+    Ok(())
+}
@@ -13,6 +13,7 @@ fn test_subtitle_decode() -> anyhow::Result<()> {
         rusty_ffmpeg::ffi::av_log_set_level(rusty_ffmpeg::ffi::AV_LOG_VERBOSE as i32);
     };
     let _ = tracing_subscriber::fmt::try_init();
+    libav_player::init_logging();
 
     let source = InputSource::open_file("../media/subtitles.mp4")?;
 
@@ -23,9 +24,16 @@ fn test_subtitle_decode() -> anyhow::Result<()> {
         .with_subtitle_stream(Some(stream.index))
         .build()?;
 
+    let header = player
+        .subtitle_header()
+        .expect("text subtitles should have an ASS header");
+    assert!(header.contains("[Script Info]"));
+    assert!(header.contains("[V4+ Styles]"));
+
     player.play()?;
 
     let mut frame_count = 0;
+    let mut text_lines = 0;
     loop {
         let frame = match player.process_next_frame() {
             Err(PlayerError::EndOfStream) => break,
@@ -37,11 +45,39 @@ fn test_subtitle_decode() -> anyhow::Result<()> {
 
         if let DecodedFrame::Subtitle(subtitle) = frame {
             tracing::info!("subtitle: {:?}", subtitle);
+            assert!(subtitle.start_time() >= subtitle.pts());
+            if let Some(end_time) = subtitle.end_time() {
+                assert!(end_time >= subtitle.start_time());
+                assert_eq!(subtitle.duration(), Some(end_time - subtitle.start_time()));
+            }
+
+            if let Some(text) = subtitle.text() {
+                tracing::info!("subtitle text: {text:?}");
+                assert!(subtitle.format().is_text());
+                assert!(subtitle.iter_bitmap().is_none());
+                text_lines +=
+                    text.lines().filter(|line| !line.trim().is_empty()).count();
+            }
         }
     }
 
+    assert!(
+        text_lines > 0,
+        "expected at least one non-empty subtitle line"
+    );
+
     dbg!(player.statistics(), frame_count);
     tracing::info!("completed read");
 
     Ok(())
 }
+
+#[test]
+fn test_subtitle_header_without_subtitles() -> anyhow::Result<()> {
+    let source = InputSource::open_file("../media/test.mp4")?;
+
+    let player = MediaPlayerBuilder::for_source(source).build()?;
+    assert_eq!(player.subtitle_header(), None);
+
+    Ok(())
+}
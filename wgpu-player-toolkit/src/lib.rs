@@ -1,6 +1,7 @@
+//! The stream metadata and hardware accelerators are re-exported from
+//! `libav-player`, so both crates expose the same types.
 
-
-
+pub use libav_player::{Accelerator, AcceleratorConfig, StreamInfo};
 
 /// A builder for creating new [WgpuPlayer].
 ///